@@ -0,0 +1 @@
+// Nothing but a comment.
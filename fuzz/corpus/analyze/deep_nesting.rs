@@ -0,0 +1 @@
+pub fn f() -> u8 { ((((((((((((((((((((1)))))))))))))))))))) }
@@ -0,0 +1 @@
+fn f(
\ No newline at end of file
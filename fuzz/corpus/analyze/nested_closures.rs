@@ -0,0 +1,2 @@
+fn f() { || || || || || || || 1; }
+fn g() { if true { if true { if true { loop { match 1 { _ => {} } } } } } }
@@ -0,0 +1,2 @@
+#![allow(unused)]
+mod a { mod b { mod c { pub(crate) fn f(self) {} } } }
@@ -0,0 +1,3 @@
+struct S;
+impl S {}
+trait T {}
@@ -0,0 +1,46 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Runs the whole analysis on arbitrary source, to find the metrics which
+// panic on code nobody would write (no functions, deep nesting, half an item,
+// and so on). Most inputs don't compile, which is fine: rustc reports errors
+// and `analyze` returns `None`, but nothing should panic or divide by zero.
+//
+// Run it with `cargo fuzz run analyze` from the repo's root; `corpus/analyze`
+// has some degenerate crates to start from.
+
+#![no_main]
+#![feature(rustc_private)]
+
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate stupid_stats;
+
+use std::env;
+use std::fs;
+use std::process;
+
+fuzz_target!(|data: &[u8]| {
+    let source = match std::str::from_utf8(data) {
+        Ok(source) => source,
+        Err(_) => return,
+    };
+    let path = env::temp_dir().join(format!("stupid-stats-fuzz-{}.rs", process::id()));
+    if fs::write(&path, source).is_err() {
+        return;
+    }
+    let args: Vec<String> = vec![
+        "stupid".to_owned(),
+        path.to_string_lossy().into_owned(),
+        "--crate-type=lib".to_owned(),
+        "--crate-name=fuzzed".to_owned(),
+        "--edition=2018".to_owned(),
+        "--error-format=short".to_owned(),
+    ];
+    let _ = stupid_stats::analyze(&args, vec![]);
+});
@@ -0,0 +1,130 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Things to do with the summary stats once the report is done, so that CI
+// doesn't need glue scripts: POST them to a webhook, upload them to
+// S3-compatible storage, or run a command on them. Each action gets the
+// summary as JSON, in a file.
+//
+// Rather than taking on an HTTP client and an AWS SDK, we use `curl` and the
+// `aws` CLI, which CI machines nearly always have. An action which fails is
+// retried a few times, then given up on; it never fails the build.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::{self, Command};
+use std::thread;
+use std::time::Duration;
+
+use crate_stats::CrateStats;
+use options::Options;
+
+// How many times to try each action.
+const ATTEMPTS: u32 = 3;
+
+// Runs every action set up in `options`. Uploads are named `<file_stem>.json`
+// (see `STUPID_STATS_JSON_DIR`), so that crates with the same name don't
+// overwrite each other. Returns a message for each action which didn't work.
+pub fn run_actions(options: &Options, stats: &CrateStats, file_stem: &str) -> Vec<String> {
+    if options.webhook.is_none() && options.upload.is_none() && options.post_report.is_none() {
+        return vec![];
+    }
+
+    let path = env::temp_dir()
+        .join(format!("stupid-stats-{}-{}.json", file_stem, process::id()));
+    let mut json = String::new();
+    stats.write_json(&mut json).unwrap();
+    if let Err(e) = fs::write(&path, json) {
+        return vec![format!("couldn't write `{}`: {}", path.display(), e)];
+    }
+
+    let mut commands = vec![];
+    if let Some(ref url) = options.webhook {
+        let mut curl = Command::new("curl");
+        curl.args(&["--fail", "--silent", "--show-error", "--request", "POST"])
+            .args(&["--header", "Content-Type: application/json"])
+            .arg("--data-binary")
+            .arg(format!("@{}", path.display()))
+            .arg(url);
+        commands.push(("webhook", curl));
+    }
+    if let Some(ref dest) = options.upload {
+        let mut aws = Command::new("aws");
+        if let Some(ref endpoint) = options.upload_endpoint {
+            aws.arg("--endpoint-url").arg(endpoint);
+        }
+        aws.args(&["s3", "cp"])
+            .arg(&path)
+            .arg(format!("{}/{}.json", dest.trim_end_matches('/'), file_stem));
+        commands.push(("upload", aws));
+    }
+    if let Some(ref command) = options.post_report {
+        commands.push(("post-report command", shell(command, &path)));
+    }
+
+    let mut errors = vec![];
+    for (name, mut command) in commands {
+        if let Err(e) = run_with_retries(&mut command) {
+            errors.push(format!("{} failed: {}", name, e));
+        }
+    }
+    let _ = fs::remove_file(&path);
+    errors
+}
+
+// Runs `command` with the shell, so that it can have quoted arguments, pipes,
+// and so on, with the path of the JSON as its last argument.
+fn shell(command: &str, path: &Path) -> Command {
+    if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C").arg(format!("{} \"{}\"", command, path.display()));
+        cmd
+    } else {
+        // With `sh -c`, the argument after the script is `$0`, then `$1`.
+        let mut sh = Command::new("sh");
+        sh.arg("-c").arg(format!("{} \"$1\"", command)).arg("sh").arg(path);
+        sh
+    }
+}
+
+enum Failure {
+    // The command couldn't be run at all, e.g., it isn't installed.
+    Spawn(String),
+    Status(Option<i32>),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Failure::Spawn(ref e) => write!(f, "couldn't run it: {}", e),
+            Failure::Status(Some(code)) => {
+                write!(f, "exit code {} after {} attempts", code, ATTEMPTS)
+            }
+            Failure::Status(None) => write!(f, "killed by a signal"),
+        }
+    }
+}
+
+// Tries `command` up to `ATTEMPTS` times, waiting a little longer each time. A
+// command we can't run at all isn't going to start working, so isn't retried.
+fn run_with_retries(command: &mut Command) -> Result<(), Failure> {
+    let mut result = Ok(());
+    for attempt in 0..ATTEMPTS {
+        if attempt > 0 {
+            thread::sleep(Duration::from_secs(1 << (attempt - 1)));
+        }
+        result = match command.status() {
+            Ok(status) if status.success() => return Ok(()),
+            Ok(status) => Err(Failure::Status(status.code())),
+            Err(e) => return Err(Failure::Spawn(e.to_string())),
+        };
+    }
+    result
+}
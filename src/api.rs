@@ -0,0 +1,308 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// A lightweight semver check. We save an inventory of a crate's public
+// functions (free functions and inherent methods which are `pub`; other
+// crates can't use `pub(crate)` or `pub(in ..)` ones), with their signatures,
+// then `stupid api-diff old new` compares two inventories, e.g., from the last
+// release and from `main`, and reports what was added, removed, or changed.
+// Making a function `pub` adds it to the API, and making it less visible
+// removes it.
+//
+// This goes by the syntax, so it doesn't know about re-exports or whether a
+// `pub` item is actually reachable, and methods of trait impls aren't listed
+// (they follow the trait).
+//
+// An inventory is a text file with a line for each function: its path, its
+// visibility, its number of arguments and of type parameters, and its
+// signature, separated by tabs. The signature has the arguments' types but
+// not their names or patterns, which callers don't see.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit, FnKind};
+use rustc_ast_pretty::pprust;
+use rustc_span::symbol::Ident;
+
+struct Entry {
+    visibility: String,
+    args: usize,
+    type_params: usize,
+    signature: String,
+}
+
+impl Entry {
+    fn parse(line: &str) -> Option<(String, Entry)> {
+        let mut fields = line.split('\t');
+        let path = fields.next()?.to_owned();
+        let entry = Entry {
+            visibility: fields.next()?.to_owned(),
+            args: fields.next()?.parse().ok()?,
+            type_params: fields.next()?.parse().ok()?,
+            signature: fields.next()?.to_owned(),
+        };
+        Some((path, entry))
+    }
+}
+
+// Inventories from older versions of stupid-stats list `pub(crate)` and
+// `pub(in ..)` functions too, so we check when we read them.
+fn is_public(entry: &Entry) -> bool {
+    entry.visibility == "pub"
+}
+
+fn signature(name: &str, sig: &ast::FnSig, generics: &ast::Generics) -> String {
+    let mut s = format!("fn {}", name);
+    if !generics.params.is_empty() {
+        let params: Vec<_> = generics
+            .params
+            .iter()
+            .map(|p| {
+                if p.bounds.is_empty() {
+                    p.ident.to_string()
+                } else {
+                    format!("{}: {}", p.ident, pprust::bounds_to_string(&p.bounds))
+                }
+            })
+            .collect();
+        s.push_str(&format!("<{}>", params.join(", ")));
+    }
+    let args: Vec<_> = sig
+        .decl
+        .inputs
+        .iter()
+        .map(|p| pprust::ty_to_string(&p.ty))
+        .collect();
+    s.push_str(&format!("({})", args.join(", ")));
+    if let ast::FnRetTy::Ty(ref ty) = sig.decl.output {
+        s.push_str(&format!(" -> {}", pprust::ty_to_string(ty)));
+    }
+    // Tabs and newlines would break the inventory.
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub struct ApiVisitor {
+    // The path of the module (and type, in an impl) we're in.
+    path: Vec<String>,
+    entries: BTreeMap<String, Entry>,
+}
+
+impl ApiVisitor {
+    pub fn new(crate_name: &str) -> ApiVisitor {
+        ApiVisitor {
+            path: vec![crate_name.to_owned()],
+            entries: BTreeMap::new(),
+        }
+    }
+
+    fn record(
+        &mut self,
+        ident: Ident,
+        vis: &ast::Visibility,
+        sig: &ast::FnSig,
+        generics: &ast::Generics,
+    ) {
+        if !vis.kind.is_pub() {
+            return;
+        }
+        let name = ident.to_string();
+        let type_params = generics
+            .params
+            .iter()
+            .filter(|p| matches!(p.kind, ast::GenericParamKind::Type { .. }))
+            .count();
+        let mut path = self.path.clone();
+        path.push(name.clone());
+        self.entries.insert(
+            path.join("::"),
+            Entry {
+                visibility: "pub".to_owned(),
+                args: sig.decl.inputs.len(),
+                type_params,
+                signature: signature(&name, sig, generics),
+            },
+        );
+    }
+
+    pub fn write_inventory(&self, out: &mut String) -> fmt::Result {
+        for (path, e) in &self.entries {
+            writeln!(
+                out,
+                "{}\t{}\t{}\t{}\t{}",
+                path, e.visibility, e.args, e.type_params, e.signature
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> visit::Visitor<'a> for ApiVisitor {
+    fn visit_item(&mut self, i: &'a ast::Item) {
+        match i.kind {
+            // Items inside functions aren't part of the API.
+            ast::ItemKind::Fn(box FnKind(_, ref sig, ref generics, _)) => {
+                self.record(i.ident, &i.vis, sig, generics);
+                return;
+            }
+            ast::ItemKind::Mod(..) => {
+                self.path.push(i.ident.to_string());
+                visit::walk_item(self, i);
+                self.path.pop();
+                return;
+            }
+            ast::ItemKind::Impl(box ast::ImplKind {
+                of_trait: None,
+                ref self_ty,
+                ref items,
+                ..
+            }) => {
+                self.path.push(pprust::ty_to_string(self_ty));
+                for item in items {
+                    if let ast::AssocItemKind::Fn(box FnKind(_, ref sig, ref generics, _)) =
+                        item.kind
+                    {
+                        self.record(item.ident, &item.vis, sig, generics);
+                    }
+                }
+                self.path.pop();
+                return;
+            }
+            // Trait impls follow the trait.
+            ast::ItemKind::Impl(..) => return,
+            _ => {}
+        }
+
+        visit::walk_item(self, i)
+    }
+}
+
+// Compares two inventories, listing the functions which were removed, added,
+// or changed. Returns whether anything was removed or changed, which might
+// break users.
+pub fn diff(old: &str, new: &str, out: &mut String) -> Result<bool, fmt::Error> {
+    let read = |inventory: &str| -> BTreeMap<_, _> {
+        inventory
+            .lines()
+            .filter_map(Entry::parse)
+            .filter(|&(_, ref e)| is_public(e))
+            .collect()
+    };
+    let (old, new) = (read(old), read(new));
+
+    let removed: Vec<_> = old.iter().filter(|&(path, _)| !new.contains_key(path)).collect();
+    let added: Vec<_> = new.iter().filter(|&(path, _)| !old.contains_key(path)).collect();
+    let mut changed = vec![];
+    for (path, o) in &old {
+        let n = match new.get(path) {
+            Some(n) => n,
+            None => continue,
+        };
+        let mut changes = vec![];
+        if o.args != n.args {
+            changes.push(format!("{} -> {} arguments", o.args, n.args));
+        }
+        if o.type_params != n.type_params {
+            changes.push(format!("{} -> {} type parameters", o.type_params, n.type_params));
+        }
+        if changes.is_empty() && o.signature != n.signature {
+            changes.push("signature".to_owned());
+        }
+        if !changes.is_empty() {
+            changed.push((path, o, n, changes));
+        }
+    }
+
+    writeln!(
+        out,
+        "{} functions removed, {} added, and {} changed;",
+        removed.len(),
+        added.len(),
+        changed.len()
+    )?;
+    if !removed.is_empty() {
+        writeln!(out, "Removed:")?;
+        for (path, e) in &removed {
+            writeln!(out, "    `{}`: `{}`", path, e.signature)?;
+        }
+    }
+    if !added.is_empty() {
+        writeln!(out, "Added:")?;
+        for (path, e) in &added {
+            writeln!(out, "    `{}`: `{}`", path, e.signature)?;
+        }
+    }
+    if !changed.is_empty() {
+        writeln!(out, "Changed:")?;
+        for (path, o, n, changes) in &changed {
+            writeln!(out, "    `{}` ({}):", path, changes.join(", "))?;
+            writeln!(out, "        was `{} {}`", o.visibility, o.signature)?;
+            writeln!(out, "        now `{} {}`", n.visibility, n.signature)?;
+        }
+    }
+    Ok(!removed.is_empty() || !changed.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "foo::a\tpub\t1\t0\tfn a(u32)\n\
+                       foo::b\tpub\t0\t0\tfn b()\n\
+                       foo::c\tpub\t1\t1\tfn c<T>(T) -> T\n\
+                       foo::d\tpub\t0\t0\tfn d() -> u8\n\
+                       foo::e\tpub(crate)\t0\t0\tfn e()\n";
+
+    fn run(old: &str, new: &str) -> (bool, String) {
+        let mut out = String::new();
+        let breaking = diff(old, new, &mut out).unwrap();
+        (breaking, out)
+    }
+
+    #[test]
+    fn no_changes() {
+        let (breaking, out) = run(OLD, OLD);
+        assert!(!breaking);
+        assert_eq!(out, "0 functions removed, 0 added, and 0 changed;\n");
+    }
+
+    #[test]
+    fn removed_added_and_changed() {
+        let new = "foo::a\tpub\t2\t0\tfn a(u32, u32)\n\
+                   foo::c\tpub\t1\t1\tfn c<T: Clone>(T) -> T\n\
+                   foo::d\tpub\t0\t0\tfn d() -> u8\n\
+                   foo::f\tpub\t0\t0\tfn f()\n";
+        let (breaking, out) = run(OLD, new);
+        assert!(breaking);
+        assert!(out.starts_with("1 functions removed, 1 added, and 2 changed;\n"));
+        assert!(out.contains("Removed:\n    `foo::b`: `fn b()`\n"));
+        assert!(out.contains("Added:\n    `foo::f`: `fn f()`\n"));
+        assert!(out.contains("    `foo::a` (1 -> 2 arguments):\n"));
+        assert!(out.contains("    `foo::c` (signature):\n"));
+    }
+
+    #[test]
+    fn making_public_is_an_addition() {
+        let new = format!("{}foo::e\tpub\t0\t0\tfn e()\n", OLD);
+        let (breaking, out) = run(OLD, &new);
+        assert!(!breaking);
+        assert!(out.contains("Added:\n    `foo::e`: `fn e()`\n"));
+    }
+
+    #[test]
+    fn making_private_is_a_removal() {
+        let new: String = OLD
+            .lines()
+            .filter(|l| !l.starts_with("foo::d"))
+            .map(|l| l.to_owned() + "\n")
+            .collect();
+        let (breaking, out) = run(OLD, &new);
+        assert!(breaking);
+        assert!(out.contains("Removed:\n    `foo::d`: `fn d() -> u8`\n"));
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How many arguments functions take: the most common number, how many take four
+// or more, the mean, median, and 90th percentile, and the whole distribution
+// as a histogram. Methods (with `self` counted as an argument) are included, and
+// can be broken out from free functions. With `STUPID_STATS_MAX_ARGS`, those
+// with more arguments than that are findings.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit, FnKind};
+use rustc_span::Span;
+use rustc_span::symbol::Ident;
+
+use counter::Histogram;
+use crate_stats::CrateStats;
+use findings::{Finding, Findings, Severity};
+use messages::Message;
+use metrics::{Metric, ReportContext};
+
+pub struct ArgsMetric {
+    // Whether to report free functions and methods separately.
+    by_kind: bool,
+    // Count of each number of args, e.g., arg_counts gives 2 -> the number of
+    // functions with two arguments.
+    arg_counts: Histogram,
+    fn_arg_counts: Histogram,
+    method_arg_counts: Histogram,
+    max_args: Option<usize>,
+    // The name, span, and number of arguments of each function with more than
+    // `max_args`.
+    over_max: Vec<(String, Span, usize)>,
+}
+
+impl ArgsMetric {
+    pub fn new(by_kind: bool, max_args: Option<usize>) -> ArgsMetric {
+        ArgsMetric {
+            by_kind,
+            arg_counts: Histogram::new(),
+            fn_arg_counts: Histogram::new(),
+            method_arg_counts: Histogram::new(),
+            max_args,
+            over_max: vec![],
+        }
+    }
+
+    fn increment_args(&mut self, args: usize, method: bool, name: Ident, span: Span) {
+        if self.max_args.map_or(false, |max| args > max) {
+            self.over_max.push((name.to_string(), span, args));
+        }
+        self.arg_counts.record(args);
+        if method {
+            self.method_arg_counts.record(args);
+        } else {
+            self.fn_arg_counts.record(args);
+        }
+    }
+}
+
+// The widest bar in the histogram of argument counts, in characters.
+const BAR_WIDTH: usize = 40;
+
+struct ArgStats {
+    // The most common number of args and the % of fns with that number.
+    common: usize,
+    common_percent: f64,
+    // The % of fns with four or more args.
+    four_or_more_percent: f64,
+    mean: f64,
+    median: usize,
+    p90: usize,
+    max: usize,
+}
+
+fn compute_arg_stats(arg_counts: &Histogram) -> ArgStats {
+    // We sum as floats so that the totals can't overflow.
+    let mut total = 0.0;
+    let mut four_or_more = 0.0;
+    let mut common = 0;
+    let mut common_index = 0;
+    for (i, c) in arg_counts.iter() {
+        total += c as f64;
+        if i >= 4 {
+            four_or_more += c as f64;
+        }
+        if c > common {
+            common = c;
+            common_index = i;
+        }
+    }
+
+    // A crate with no functions would otherwise give us NaN percentages.
+    if total == 0.0 {
+        return ArgStats {
+            common: 0,
+            common_percent: 0.0,
+            four_or_more_percent: 0.0,
+            mean: 0.0,
+            median: 0,
+            p90: 0,
+            max: 0,
+        };
+    }
+
+    ArgStats {
+        common: common_index,
+        common_percent: 100.0 * common as f64 / total,
+        four_or_more_percent: 100.0 * four_or_more / total,
+        mean: arg_counts.mean(),
+        median: arg_counts.percentile(50.0),
+        p90: arg_counts.percentile(90.0),
+        max: arg_counts.max(),
+    }
+}
+
+// Draws the histogram as bars, one line per number of args, scaled so the
+// biggest bar is `BAR_WIDTH` long.
+fn write_bars(arg_counts: &Histogram, out: &mut String) -> fmt::Result {
+    let biggest = arg_counts.iter().map(|(_, c)| c).max().unwrap_or(0);
+    if biggest == 0 {
+        return Ok(());
+    }
+    let clamped = arg_counts.is_approximate();
+    for (args, count) in arg_counts.iter() {
+        let label = if clamped && args == Histogram::max_bucket() {
+            format!("{}+", args)
+        } else {
+            args.to_string()
+        };
+        // Round up, so that every count gets at least one character.
+        let width = (count as f64 * BAR_WIDTH as f64 / biggest as f64).ceil() as usize;
+        writeln!(out, "    {:>4} | {} {}", label, "#".repeat(width), count)?;
+    }
+    Ok(())
+}
+
+impl Metric for ArgsMetric {
+    fn id(&self) -> &'static str {
+        "args"
+    }
+
+    // We found an item, could be a function.
+    fn visit_item(&mut self, i: &ast::Item) {
+        if let ast::ItemKind::Fn(box FnKind(_, ref decl, _, _)) = i.kind {
+            // record the number of args
+            self.increment_args(decl.decl.inputs.len(), false, i.ident, i.span);
+        }
+    }
+
+    // We found an item in an impl or trait, could be a method or associated
+    // function. Trait methods without a default body are only declarations,
+    // so we skip them and count the impls instead.
+    fn visit_assoc_item(&mut self, i: &ast::AssocItem, _: visit::AssocCtxt) {
+        if let ast::AssocItemKind::Fn(box FnKind(_, ref sig, _, Some(_))) = i.kind {
+            self.increment_args(sig.decl.inputs.len(), true, i.ident, i.span);
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let lang = cx.lang;
+        let stats = compute_arg_stats(&self.arg_counts);
+        writeln!(out, "{}", lang.render(Message::CommonArgs(stats.common, stats.common_percent)))?;
+        writeln!(out, "{}", lang.render(Message::FourOrMoreArgs(stats.four_or_more_percent)))?;
+        if self.by_kind {
+            let s = compute_arg_stats(&self.fn_arg_counts);
+            writeln!(
+                out,
+                "{}",
+                lang.render(Message::FreeFnArgs(s.common, s.common_percent, s.four_or_more_percent))
+            )?;
+            let s = compute_arg_stats(&self.method_arg_counts);
+            writeln!(
+                out,
+                "{}",
+                lang.render(Message::MethodArgs(s.common, s.common_percent, s.four_or_more_percent))
+            )?;
+        }
+        writeln!(
+            out,
+            "{}",
+            lang.render(Message::ArgSpread(stats.mean, stats.median, stats.p90, stats.max))
+        )?;
+        write_bars(&self.arg_counts, out)?;
+        if self.arg_counts.is_approximate() {
+            writeln!(out, "{}", lang.render(Message::ApproximateArgs(Histogram::max_bucket())))?;
+        }
+        Ok(())
+    }
+
+    fn findings(&self, findings: &mut Findings) {
+        let max = match self.max_args {
+            Some(max) => max,
+            None => return,
+        };
+        for &(ref name, span, args) in &self.over_max {
+            findings.push(Finding {
+                id: "too-many-arguments",
+                severity: Severity::Warning,
+                span,
+                message: format!("`{}` takes {} arguments (over {})", name, args, max),
+                value: Some(args),
+            });
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(ArgsMetric::new(self.by_kind, self.max_args))
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.arg_counts = self.arg_counts.clone();
+        set_arg_stats(stats);
+    }
+}
+
+// Works out the summary of `stats.arg_counts`, e.g., after merging several
+// crates' counts.
+pub fn set_arg_stats(stats: &mut CrateStats) {
+    let arg_stats = compute_arg_stats(&stats.arg_counts);
+    stats.common_args = arg_stats.common;
+    stats.common_args_percent = arg_stats.common_percent;
+    stats.four_or_more_args_percent = arg_stats.four_or_more_percent;
+    stats.mean_args = arg_stats.mean;
+    stats.median_args = arg_stats.median;
+    stats.p90_args = arg_stats.p90;
+    stats.max_args = arg_stats.max;
+}
@@ -0,0 +1,244 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// A rule that complex public functions must be checked: a public function with
+// a cognitive complexity above some limit needs a minimum number of assertions
+// in its body plus tests which call it. We also look for tests which can't
+// fail because they don't check anything.
+//
+// Tests are found by their `#[test]` attribute and are matched with functions
+// by name only, so a test calling any function called `parse` counts for every
+// `parse`.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit, FnKind};
+use rustc_span::Span;
+use rustc_span::symbol::{sym, Ident};
+
+use cognitive::cognitive_complexity;
+use counter::Counter;
+use findings::{Finding, Findings, Severity};
+use metrics::{Metric, ReportContext};
+
+const ASSERT_MACROS: &[&str] = &[
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+];
+
+// Counts the assertion macros in a function body, and the other ways it can
+// fail a test: `panic!`, `unreachable!`, and `expect`.
+struct AssertionCounter {
+    count: usize,
+    panics: usize,
+}
+
+impl<'a> visit::Visitor<'a> for AssertionCounter {
+    fn visit_mac_call(&mut self, mac: &'a ast::MacCall) {
+        if let Some(segment) = mac.path.segments.last() {
+            let name = segment.ident.as_str();
+            if ASSERT_MACROS.contains(&&*name) {
+                self.count += 1;
+            } else if &*name == "panic" || &*name == "unreachable" {
+                self.panics += 1;
+            }
+        }
+        visit::walk_mac(self, mac)
+    }
+
+    fn visit_expr(&mut self, e: &'a ast::Expr) {
+        if let ast::ExprKind::MethodCall(ref segment, ..) = e.kind {
+            if &*segment.ident.as_str() == "expect" {
+                self.panics += 1;
+            }
+        }
+        visit::walk_expr(self, e)
+    }
+}
+
+// Collects the names of the functions and methods a test calls.
+struct CalleeCollector {
+    names: Vec<String>,
+}
+
+impl<'a> visit::Visitor<'a> for CalleeCollector {
+    fn visit_expr(&mut self, e: &'a ast::Expr) {
+        match e.kind {
+            ast::ExprKind::Call(ref f, _) => {
+                if let ast::ExprKind::Path(None, ref path) = f.kind {
+                    if let Some(segment) = path.segments.last() {
+                        self.names.push(segment.ident.to_string());
+                    }
+                }
+            }
+            ast::ExprKind::MethodCall(ref segment, ..) => self.names.push(segment.ident.to_string()),
+            _ => {}
+        }
+        visit::walk_expr(self, e)
+    }
+}
+
+struct PubFn {
+    name: String,
+    span: Span,
+    complexity: usize,
+    assertions: usize,
+}
+
+pub struct AssertionsMetric {
+    complexity: Option<usize>,
+    min_assertions: usize,
+    pub_fns: Vec<PubFn>,
+    // How many tests call a function with each name.
+    tests: HashMap<String, Counter>,
+    // Tests which can't fail: no assertions, panics, or `expect`s, and they
+    // don't return a `Result` or expect to panic.
+    no_op_tests: Vec<(String, Span)>,
+}
+
+impl AssertionsMetric {
+    pub fn new(complexity: Option<usize>, min_assertions: usize) -> AssertionsMetric {
+        AssertionsMetric {
+            complexity,
+            min_assertions,
+            pub_fns: vec![],
+            tests: HashMap::new(),
+            no_op_tests: vec![],
+        }
+    }
+
+    fn check_fn(
+        &mut self,
+        attrs: &[ast::Attribute],
+        vis: &ast::Visibility,
+        ident: Ident,
+        span: Span,
+        sig: &ast::FnSig,
+        body: &ast::Block,
+    ) {
+        let mut counter = AssertionCounter {
+            count: 0,
+            panics: 0,
+        };
+        visit::walk_block(&mut counter, body);
+
+        if attrs.iter().any(|a| a.has_name(sym::test)) {
+            let can_fail = counter.count > 0 ||
+                counter.panics > 0 ||
+                !matches!(sig.decl.output, ast::FnRetTy::Default(_)) ||
+                attrs.iter().any(|a| a.has_name(sym::should_panic));
+            if !can_fail {
+                self.no_op_tests.push((ident.to_string(), span));
+            }
+
+            let mut collector = CalleeCollector { names: vec![] };
+            visit::walk_block(&mut collector, body);
+            collector.names.sort();
+            collector.names.dedup();
+            for name in collector.names {
+                self.tests.entry(name).or_default().increment();
+            }
+        } else if let ast::VisibilityKind::Public = vis.kind {
+            self.pub_fns.push(PubFn {
+                name: ident.to_string(),
+                span,
+                complexity: cognitive_complexity(ident, body),
+                assertions: counter.count,
+            });
+        }
+    }
+
+    fn violations(&self, complexity: usize) -> Vec<(&PubFn, usize)> {
+        self.pub_fns
+            .iter()
+            .filter(|f| f.complexity > complexity)
+            .map(|f| {
+                let tests = self.tests.get(&f.name).cloned().unwrap_or_default();
+                (f, f.assertions.saturating_add(tests.get()))
+            })
+            .filter(|&(_, checks)| checks < self.min_assertions)
+            .collect()
+    }
+}
+
+impl Metric for AssertionsMetric {
+    fn id(&self) -> &'static str {
+        "assertions"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        if let ast::ItemKind::Fn(box FnKind(_, ref sig, _, Some(ref body))) = i.kind {
+            self.check_fn(&i.attrs, &i.vis, i.ident, i.span, sig, body);
+        }
+    }
+
+    fn visit_assoc_item(&mut self, i: &ast::AssocItem, _: visit::AssocCtxt) {
+        if let ast::AssocItemKind::Fn(box FnKind(_, ref sig, _, Some(ref body))) = i.kind {
+            self.check_fn(&i.attrs, &i.vis, i.ident, i.span, sig, body);
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(out, "{} tests have no assertions;", self.no_op_tests.len())?;
+
+        let complexity = match self.complexity {
+            Some(complexity) => complexity,
+            None => return Ok(()),
+        };
+
+        writeln!(
+            out,
+            "{} public functions with a cognitive complexity over {} have fewer than {} assertions and tests;",
+            self.violations(complexity).len(),
+            complexity,
+            self.min_assertions
+        )
+    }
+
+    fn findings(&self, findings: &mut Findings) {
+        for &(ref name, span) in &self.no_op_tests {
+            findings.push(Finding {
+                id: "test-without-assertions",
+                severity: Severity::Warning,
+                span,
+                message: format!("test `{}` can't fail: it has no assertions", name),
+                value: None,
+            });
+        }
+
+        let complexity = match self.complexity {
+            Some(complexity) => complexity,
+            None => return,
+        };
+
+        for (f, checks) in self.violations(complexity) {
+            findings.push(Finding {
+                id: "assertion-density",
+                severity: Severity::Warning,
+                span: f.span,
+                message: format!(
+                    "`{}` has a cognitive complexity of {} but only {} assertions and tests (needs {})",
+                    f.name,
+                    f.complexity,
+                    checks,
+                    self.min_assertions
+                ),
+                value: Some(checks),
+            });
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(AssertionsMetric::new(self.complexity, self.min_assertions))
+    }
+}
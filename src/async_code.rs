@@ -0,0 +1,98 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How much of the crate is async: how many functions are `async fn`s (out of
+// the same functions as `args` count), and how many async blocks, async
+// closures, and `.await`s there are. Run after run, that tracks the progress
+// of moving a crate to async.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_span::Span;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+pub struct AsyncMetric {
+    functions: Counter,
+    async_fns: Counter,
+    async_blocks: Counter,
+    async_closures: Counter,
+    awaits: Counter,
+}
+
+impl AsyncMetric {
+    pub fn new() -> AsyncMetric {
+        AsyncMetric {
+            functions: Counter::new(),
+            async_fns: Counter::new(),
+            async_blocks: Counter::new(),
+            async_closures: Counter::new(),
+            awaits: Counter::new(),
+        }
+    }
+}
+
+impl Metric for AsyncMetric {
+    fn id(&self) -> &'static str {
+        "async"
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(_, _, sig, _, Some(_)) = fk {
+            self.functions.increment();
+            if let ast::Async::Yes { .. } = sig.header.asyncness {
+                self.async_fns.increment();
+            }
+        }
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        match e.kind {
+            ast::ExprKind::Async(..) => self.async_blocks.increment(),
+            ast::ExprKind::Closure(_, ast::Async::Yes { .. }, ..) => {
+                self.async_closures.increment()
+            }
+            ast::ExprKind::Await(..) => self.awaits.increment(),
+            _ => {}
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let percent = if self.functions.get() == 0 {
+            0.0
+        } else {
+            100.0 * self.async_fns.get() as f64 / self.functions.get() as f64
+        };
+        writeln!(
+            out,
+            "{} of {} functions ({:.1}%) are `async`;",
+            self.async_fns,
+            self.functions,
+            percent
+        )?;
+        writeln!(
+            out,
+            "Found {} async blocks, {} async closures, and {} `.await`s.",
+            self.async_blocks,
+            self.async_closures,
+            self.awaits
+        )
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(AsyncMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("async.fns", self.async_fns);
+        stats.set_count("async.awaits", self.awaits);
+    }
+}
@@ -0,0 +1,143 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How attributes are used across the crate (on items, fields, expressions,
+// and so on, as well as the crate itself): how often each attribute appears,
+// which traits are derived, which lints are allowed or denied, and how many
+// `#[inline]`, `#[cfg]`, and `#[test]` attributes there are. We look before
+// expansion, while derives and tests are still attributes. Doc comments don't
+// count.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+use rustc_ast_pretty::pprust;
+use rustc_span::symbol::sym;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+// How many of the most used attributes to list.
+const TOP_ATTRIBUTES: usize = 15;
+
+// Highest count first, then by name, so the report is stable.
+fn sorted(counts: &HashMap<String, Counter>) -> Vec<(&String, &Counter)> {
+    let mut counts: Vec<_> = counts.iter().collect();
+    counts.sort_by(|a, b| b.1.get().cmp(&a.1.get()).then(a.0.cmp(b.0)));
+    counts
+}
+
+
+pub struct AttributesMetric {
+    attributes: HashMap<String, Counter>,
+    derives: HashMap<String, Counter>,
+    // The lint level and the lint, e.g., `allow(dead_code)`.
+    lints: HashMap<String, Counter>,
+    // How many `#[inline]`, `#[inline(always)]`, and `#[inline(never)]`s.
+    inline: [Counter; 3],
+    cfgs: Counter,
+    tests: Counter,
+}
+
+impl AttributesMetric {
+    pub fn new() -> AttributesMetric {
+        AttributesMetric {
+            attributes: HashMap::new(),
+            derives: HashMap::new(),
+            lints: HashMap::new(),
+            inline: [Counter::new(); 3],
+            cfgs: Counter::new(),
+            tests: Counter::new(),
+        }
+    }
+}
+
+impl Metric for AttributesMetric {
+    fn id(&self) -> &'static str {
+        "attributes"
+    }
+
+    fn visit_attribute(&mut self, attr: &ast::Attribute) {
+        let path = match attr.kind {
+            ast::AttrKind::Normal(ref item, ..) => pprust::path_to_string(&item.path),
+            ast::AttrKind::DocComment(..) => return,
+        };
+        self.attributes.entry(path).or_default().increment();
+
+        let name = match attr.ident() {
+            Some(ident) => ident.name,
+            None => return,
+        };
+        let items = attr.meta_item_list().unwrap_or_default();
+        if name == sym::derive {
+            for item in items {
+                let derived = pprust::meta_list_item_to_string(&item);
+                self.derives.entry(derived).or_default().increment();
+            }
+        } else if [sym::allow, sym::warn, sym::deny, sym::forbid].contains(&name) {
+            for item in items {
+                let lint = format!("{}({})", name, pprust::meta_list_item_to_string(&item));
+                self.lints.entry(lint).or_default().increment();
+            }
+        } else if name == sym::inline {
+            if items.is_empty() {
+                self.inline[0].increment();
+            } else if items.iter().any(|i| i.has_name(sym::always)) {
+                self.inline[1].increment();
+            } else if items.iter().any(|i| i.has_name(sym::never)) {
+                self.inline[2].increment();
+            }
+        } else if name == sym::cfg {
+            self.cfgs.increment();
+        } else if name == sym::test {
+            self.tests.increment();
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(out, "Found {} attributes; the most used are:", Counter::sum(self.attributes.values()))?;
+        for (name, count) in sorted(&self.attributes).into_iter().take(TOP_ATTRIBUTES) {
+            writeln!(out, "    `#[{}]`: {}", name, count)?;
+        }
+
+        if !self.derives.is_empty() {
+            writeln!(out, "Derived traits:")?;
+        }
+        for (name, count) in sorted(&self.derives) {
+            writeln!(out, "    `{}`: {}", name, count)?;
+        }
+
+        if !self.lints.is_empty() {
+            writeln!(out, "Lint levels:")?;
+        }
+        for (lint, count) in sorted(&self.lints) {
+            writeln!(out, "    `{}`: {}", lint, count)?;
+        }
+
+        writeln!(
+            out,
+            "`#[inline]`: {}, `#[inline(always)]`: {}, `#[inline(never)]`: {};",
+            self.inline[0],
+            self.inline[1],
+            self.inline[2]
+        )?;
+        writeln!(out, "`#[cfg]`: {}, `#[test]`: {}.", self.cfgs, self.tests)
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(AttributesMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("attributes.total", Counter::sum(self.attributes.values()));
+        stats.set_count("attributes.cfgs", self.cfgs);
+        stats.set_count("attributes.tests", self.tests);
+    }
+}
@@ -0,0 +1,164 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Baselines: the summary stats saved as JSON (`--save-baseline`), so that a
+// later build can be compared with them (`--compare-baseline`) to see what
+// went up or down, e.g., `+12 println!` or `max args 6 -> 9`, along with the
+// metrics' counts, e.g., `+2 unsafe.blocks`. Some stats are tracked: if one of
+// those gets worse, that's a regression, which can fail the build.
+
+use std::fmt::{self, Write};
+
+use crate_stats::CrateStats;
+use gates::stat;
+
+// The stats we compare, for `gates::stat`, with how to describe them and
+// whether going up is a regression.
+const STATS: &[(&str, &str, bool)] = &[
+    ("println_count", "println!", true),
+    ("functions", "functions", false),
+    ("common_args", "common args", false),
+    ("four_or_more_args_percent", "% with 4+ args", true),
+    ("mean_args", "mean args", true),
+    ("median_args", "median args", true),
+    ("p90_args", "p90 args", true),
+    ("max_args", "max args", true),
+    ("generated_lines_excluded", "generated lines", false),
+];
+
+// The metrics' counts (`CrateStats::counts`) for which going up is a
+// regression, the ones with a threshold.
+const TRACKED_COUNTS: &[&str] = &["unsafe.blocks", "panics.todo"];
+
+// Whole numbers without a point, the rest to one decimal place.
+fn number(x: f64) -> String {
+    if x.fract() == 0.0 {
+        format!("{}", x)
+    } else {
+        format!("{:.1}", x)
+    }
+}
+
+// Writes out what changed between `old` and `new`. Returns a description of
+// each regression in a tracked stat.
+pub fn compare(
+    old: &CrateStats,
+    new: &CrateStats,
+    out: &mut String,
+) -> Result<Vec<String>, fmt::Error> {
+    let mut regressions = vec![];
+    let mut changes = vec![];
+    for &(name, label, tracked) in STATS {
+        let (before, after) = match (stat(old, name), stat(new, name)) {
+            (Some(before), Some(after)) => (before, after),
+            _ => continue,
+        };
+        if (after - before).abs() < 0.005 {
+            continue;
+        }
+        // Counts read better as a difference, the rest as before and after.
+        let change = if name == "println_count" || name == "functions" {
+            format!("{:+} {}", after - before, label)
+        } else {
+            format!("{} {} -> {}", label, number(before), number(after))
+        };
+        if tracked && after > before {
+            regressions.push(format!("`{}` since the baseline: {}", new.crate_name, change));
+        }
+        changes.push(change);
+    }
+    // A count which is only on one side is from a metric which wasn't
+    // collected on the other.
+    for (name, after) in &new.counts {
+        let (before, after) = match old.counts.get(name) {
+            Some(before) => (before.get() as i64, after.get() as i64),
+            None => continue,
+        };
+        if after == before {
+            continue;
+        }
+        let change = format!("{:+} {}", after - before, name);
+        if TRACKED_COUNTS.contains(&name.as_str()) && after > before {
+            regressions.push(format!("`{}` since the baseline: {}", new.crate_name, change));
+        }
+        changes.push(change);
+    }
+
+    if changes.is_empty() {
+        writeln!(out, "No change since the baseline.")?;
+    } else {
+        writeln!(out, "Since the baseline: {}.", changes.join(", "))?;
+    }
+    Ok(regressions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use args::set_arg_stats;
+
+    fn stats(printlns: usize, args: &[usize]) -> CrateStats {
+        let mut stats = CrateStats {
+            crate_name: "foo".to_owned(),
+            ..CrateStats::default()
+        };
+        stats.println_count.add(printlns);
+        for &n in args {
+            stats.arg_counts.record(n);
+        }
+        set_arg_stats(&mut stats);
+        stats
+    }
+
+    #[test]
+    fn no_change() {
+        let mut out = String::new();
+        let regressions = compare(&stats(1, &[2]), &stats(1, &[2]), &mut out).unwrap();
+        assert!(regressions.is_empty());
+        assert_eq!(out, "No change since the baseline.\n");
+    }
+
+    #[test]
+    fn regressions() {
+        let mut out = String::new();
+        let regressions = compare(&stats(3, &[1, 2]), &stats(1, &[1, 2, 6]), &mut out).unwrap();
+        assert_eq!(
+            out,
+            "Since the baseline: -2 println!, +1 functions, % with 4+ args 0 -> 33.3, \
+             mean args 1.5 -> 3, median args 1 -> 2, p90 args 2 -> 6, max args 2 -> 6.\n"
+        );
+        assert_eq!(
+            regressions,
+            vec![
+                "`foo` since the baseline: % with 4+ args 0 -> 33.3",
+                "`foo` since the baseline: mean args 1.5 -> 3",
+                "`foo` since the baseline: median args 1 -> 2",
+                "`foo` since the baseline: p90 args 2 -> 6",
+                "`foo` since the baseline: max args 2 -> 6",
+            ]
+        );
+    }
+
+    #[test]
+    fn counts() {
+        let mut old = stats(1, &[2]);
+        old.set_count("unsafe.blocks", 1);
+        old.set_count("closures.total", 4);
+        old.set_count("async.fns", 2);
+        let mut new = stats(1, &[2]);
+        new.set_count("unsafe.blocks", 3);
+        new.set_count("closures.total", 2);
+        new.set_count("async.fns", 2);
+        new.set_count("panics.todo", 5);
+
+        let mut out = String::new();
+        let regressions = compare(&old, &new, &mut out).unwrap();
+        assert_eq!(out, "Since the baseline: -2 closures.total, +2 unsafe.blocks.\n");
+        assert_eq!(regressions, vec!["`foo` since the baseline: +2 unsafe.blocks"]);
+    }
+}
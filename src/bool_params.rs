@@ -0,0 +1,137 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Functions which take bare `bool` parameters. At the call site,
+// `connect(true, false)` says nothing about what the flags mean, so we list
+// the functions with the most of them and suggest an enum for each. We count
+// the same functions as `args` (so not trait methods without a default body),
+// and only go by the name `bool`, since we don't have types before expansion.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_span::Span;
+
+use counter::{Counter, Histogram};
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+// How many of the worst functions to list.
+const TOP_FUNCTIONS: usize = 10;
+
+fn is_bool(ty: &ast::Ty) -> bool {
+    match ty.kind {
+        ast::TyKind::Path(None, ref path) => {
+            path.segments.len() == 1 && &*path.segments[0].ident.as_str() == "bool"
+        }
+        _ => false,
+    }
+}
+
+// `use_cache` becomes `UseCache`.
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+pub struct BoolParamsMetric {
+    functions: Counter,
+    // How many `bool` parameters the functions which have any take.
+    bools_per_fn: Histogram,
+    // The name and span of each function with `bool` parameters, and the names
+    // of those parameters (where they're simple bindings).
+    offenders: Vec<(String, Span, usize, Vec<String>)>,
+}
+
+impl BoolParamsMetric {
+    pub fn new() -> BoolParamsMetric {
+        BoolParamsMetric {
+            functions: Counter::new(),
+            bools_per_fn: Histogram::new(),
+            offenders: vec![],
+        }
+    }
+}
+
+impl Metric for BoolParamsMetric {
+    fn id(&self) -> &'static str {
+        "bool-params"
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, s: Span) {
+        if let visit::FnKind::Fn(_, ident, sig, _, Some(_)) = fk {
+            self.functions.increment();
+            let bools: Vec<_> = sig.decl.inputs.iter().filter(|p| is_bool(&p.ty)).collect();
+            if !bools.is_empty() {
+                self.bools_per_fn.record(bools.len());
+                let names = bools
+                    .iter()
+                    .filter_map(|p| match p.pat.kind {
+                        ast::PatKind::Ident(_, name, None) => {
+                            Some(name.to_string().trim_start_matches('_').to_owned())
+                        }
+                        _ => None,
+                    })
+                    .filter(|name| !name.is_empty())
+                    .collect();
+                self.offenders.push((ident.to_string(), s, bools.len(), names));
+            }
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let percent = if self.functions.get() == 0 {
+            0.0
+        } else {
+            100.0 * self.offenders.len() as f64 / self.functions.get() as f64
+        };
+        writeln!(
+            out,
+            "{} functions ({:.1}%) take `bool` parameters;",
+            self.offenders.len(),
+            percent
+        )?;
+        for (bools, count) in self.bools_per_fn.iter().filter(|&(_, count)| count > 0) {
+            writeln!(out, "    taking {}: {}", bools, count)?;
+        }
+
+        let mut offenders: Vec<_> = self.offenders.iter().collect();
+        offenders.sort_by(|a, b| b.2.cmp(&a.2));
+        if !offenders.is_empty() {
+            writeln!(out, "The functions with the most `bool` parameters are:")?;
+        }
+        for &&(ref name, span, bools, ref params) in offenders.iter().take(TOP_FUNCTIONS) {
+            writeln!(out, "    `{}`: {} at {}", name, bools, cx.source_map.span_to_string(span))?;
+            if let Some(param) = params.first() {
+                writeln!(
+                    out,
+                    "        e.g., `{}: bool` could be `enum {} {{ Yes, No }}`",
+                    param,
+                    camel_case(param)
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(BoolParamsMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("bool-params.functions", self.offenders.len());
+    }
+}
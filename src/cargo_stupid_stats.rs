@@ -0,0 +1,172 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// `cargo stupid-stats`: runs `cargo check` with `stupid` as the rustc wrapper
+// for the workspace's crates, then prints a table of their summary stats and
+// whether each passes the `--fail-if` conditions (with `--reports`, the full
+// reports grouped by package come first). Cargo works out all the `--extern`
+// and `--cfg` flags, so this works on any Cargo project. Other arguments go to
+// `cargo check`, so with `--all-targets` the packages' examples, integration
+// tests, and benchmarks are checked too, and reported in sections of their own
+// (Cargo also checks the libraries and binaries again as unit tests, which
+// `stupid` leaves out).
+//
+// The conditions are checked here against the whole table, rather than by
+// `stupid` for each crate, so that one failing crate doesn't stop the build
+// before we've seen the others.
+//
+// We build in our own target directory, so that a normal `cargo check` doesn't
+// leave crates fresh without us having seen them. Reports are kept in that
+// directory too: if a crate is fresh it hasn't changed, so its last report is
+// still right.
+
+#![feature(rustc_private)]
+
+extern crate stupid_stats;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use stupid_stats::merge;
+use stupid_stats::options::Options;
+
+fn main() {
+    // Cargo runs us as `cargo-stupid-stats stupid-stats <args>`.
+    let mut args: Vec<_> = env::args().skip(1).skip_while(|a| a == "stupid-stats").collect();
+    let mut conditions = Options::from_env().gates();
+    let mut reports = false;
+    let mut i = 0;
+    while i < args.len() {
+        if args[i] == "--reports" {
+            reports = true;
+            args.remove(i);
+        } else if args[i].starts_with("--fail-if=") {
+            conditions.push(args[i]["--fail-if=".len()..].to_owned());
+            args.remove(i);
+        } else if args[i] == "--fail-if" && i + 1 < args.len() {
+            conditions.push(args[i + 1].clone());
+            args.drain(i..i + 2);
+        } else {
+            i += 1;
+        }
+    }
+
+    let stupid = match env::current_exe() {
+        Ok(exe) => exe.with_file_name(format!("stupid{}", env::consts::EXE_SUFFIX)),
+        Err(e) => fail(&format!("can't find the `stupid` driver: {}", e)),
+    };
+    let target_dir = match target_dir() {
+        Some(dir) => dir.join("stupid-stats"),
+        None => fail("can't find the target directory, is this a Cargo project?"),
+    };
+    let report_dir = target_dir.join("reports");
+    let json_dir = target_dir.join("json");
+
+    let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()))
+        .arg("check")
+        .args(&args)
+        .env("RUSTC_WORKSPACE_WRAPPER", &stupid)
+        .env("CARGO_TARGET_DIR", &target_dir)
+        .env("STUPID_STATS_REPORT_DIR", &report_dir)
+        .env("STUPID_STATS_JSON_DIR", &json_dir)
+        .env("STUPID_STATS_BINARY_STATS", "1")
+        .env("STUPID_STATS_FAIL_IF", "")
+        .env("STUPID_STATS_THRESHOLD_GATES", "0")
+        .status();
+    let status = match status {
+        Ok(status) => status,
+        Err(e) => fail(&format!("can't run cargo: {}", e)),
+    };
+
+    if reports {
+        if let Err(e) = print_reports(&report_dir) {
+            fail(&format!("can't read the reports: {}", e));
+        }
+    }
+    let crates = if json_dir.exists() {
+        merge::read_dir(&json_dir).unwrap_or_else(|e| fail(&format!("can't read the stats: {}", e)))
+    } else {
+        vec![]
+    };
+    let mut out = String::new();
+    let failed = merge::write_summary(&crates, &conditions, &mut out).unwrap();
+    print!("{}", out);
+
+    match status.code() {
+        Some(0) if failed > 0 => process::exit(1),
+        code => process::exit(code.unwrap_or(1)),
+    }
+}
+
+// The workspace's target directory, from `cargo metadata`.
+fn target_dir() -> Option<PathBuf> {
+    let output = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()))
+        .args(&["metadata", "--format-version", "1", "--no-deps"])
+        .output()
+        .ok()?;
+    let metadata = String::from_utf8(output.stdout).ok()?;
+    // Not worth a JSON parser.
+    let key = "\"target_directory\":\"";
+    let start = metadata.find(key)? + key.len();
+    let end = start + metadata[start..].find('"')?;
+    Some(PathBuf::from(metadata[start..end].replace("\\\\", "\\")))
+}
+
+// Prints every package's reports, in order of package then crate, with its
+// examples, tests, and benchmarks (in subdirectories) after its own crates.
+fn print_reports(report_dir: &Path) -> io::Result<()> {
+    if !report_dir.exists() {
+        return Ok(());
+    }
+    for package in sorted_entries(report_dir)? {
+        if !package.is_dir() {
+            continue;
+        }
+        let name = package.file_name().map(|n| n.to_string_lossy().into_owned());
+        let name = name.unwrap_or_default();
+        println!("Package {}:\n", name);
+        print_section(&package)?;
+        for section in ["examples", "tests", "benches"].iter() {
+            let dir = package.join(section);
+            if dir.is_dir() {
+                println!("Package {}, {}:\n", name, section);
+                print_section(&dir)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Prints the reports in `dir`. Each report has a manifest of how it was made
+// next to it, which isn't printed.
+fn print_section(dir: &Path) -> io::Result<()> {
+    for report in sorted_entries(dir)? {
+        if report.is_dir() || report.to_string_lossy().ends_with(".manifest.json") {
+            continue;
+        }
+        print!("{}", fs::read_to_string(report)?);
+        println!();
+    }
+    Ok(())
+}
+
+fn sorted_entries(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
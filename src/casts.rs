@@ -0,0 +1,165 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// `as` casts between integer types, classified as widening, narrowing (i.e.,
+// possibly truncating), or sign-changing.
+//
+// We have no type information, so we only know what is being cast when it is
+// obvious from the syntax: a suffixed literal, another cast, or a call to a
+// method which conventionally returns `usize`, like `len`. `usize` and `isize`
+// are treated as 64 bits.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+use rustc_span::Span;
+
+use counter::Counter;
+use findings::{Finding, Findings, Severity};
+use metrics::{Metric, ReportContext};
+
+// Methods whose result we assume is a `usize`.
+const USIZE_METHODS: &[&str] = &["len", "count", "capacity"];
+
+// An integer type, as (bits, signed).
+type IntTy = (u32, bool);
+
+fn int_ty(name: &str) -> Option<IntTy> {
+    Some(match name {
+        "u8" => (8, false),
+        "u16" => (16, false),
+        "u32" => (32, false),
+        "u64" => (64, false),
+        "u128" => (128, false),
+        "usize" => (64, false),
+        "i8" => (8, true),
+        "i16" => (16, true),
+        "i32" => (32, true),
+        "i64" => (64, true),
+        "i128" => (128, true),
+        "isize" => (64, true),
+        _ => return None,
+    })
+}
+
+// The integer type a type names, if it is one.
+fn ty_int_ty(ty: &ast::Ty) -> Option<IntTy> {
+    match ty.kind {
+        ast::TyKind::Path(None, ref path) if path.segments.len() == 1 => {
+            int_ty(&path.segments[0].ident.as_str())
+        }
+        _ => None,
+    }
+}
+
+// The integer type of an expression, if we can tell from the syntax alone.
+fn expr_int_ty(e: &ast::Expr) -> Option<IntTy> {
+    match e.kind {
+        ast::ExprKind::Lit(ref lit) => match lit.kind {
+            ast::LitKind::Int(_, ast::LitIntType::Signed(ty)) => int_ty(ty.name_str()),
+            ast::LitKind::Int(_, ast::LitIntType::Unsigned(ty)) => int_ty(ty.name_str()),
+            _ => None,
+        },
+        ast::ExprKind::Cast(_, ref ty) => ty_int_ty(ty),
+        ast::ExprKind::MethodCall(ref segment, ref args, _) if args.len() == 1 => {
+            if USIZE_METHODS.contains(&&*segment.ident.as_str()) {
+                int_ty("usize")
+            } else {
+                None
+            }
+        }
+        ast::ExprKind::Paren(ref e) => expr_int_ty(e),
+        _ => None,
+    }
+}
+
+pub struct CastsMetric {
+    widening: Counter,
+    sign_changing: Counter,
+    noop: Counter,
+    narrowing: Vec<Span>,
+    // Casts to an integer type from something we can't identify.
+    unknown: Counter,
+}
+
+impl CastsMetric {
+    pub fn new() -> CastsMetric {
+        CastsMetric {
+            widening: Counter::new(),
+            sign_changing: Counter::new(),
+            noop: Counter::new(),
+            narrowing: vec![],
+            unknown: Counter::new(),
+        }
+    }
+
+    fn check_cast(&mut self, from: IntTy, to: IntTy, span: Span) {
+        let ((from_bits, from_signed), (to_bits, to_signed)) = (from, to);
+        if from == to {
+            self.noop.increment();
+        } else if from_signed == to_signed {
+            if to_bits > from_bits {
+                self.widening.increment();
+            } else {
+                self.narrowing.push(span);
+            }
+        } else if to_bits > from_bits && !from_signed {
+            // E.g., `u8` to `i16` can't lose anything.
+            self.widening.increment();
+        } else if to_bits >= from_bits {
+            self.sign_changing.increment();
+        } else {
+            self.narrowing.push(span);
+        }
+    }
+}
+
+impl Metric for CastsMetric {
+    fn id(&self) -> &'static str {
+        "casts"
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        if let ast::ExprKind::Cast(ref inner, ref ty) = e.kind {
+            if let Some(to) = ty_int_ty(ty) {
+                match expr_int_ty(inner) {
+                    Some(from) => self.check_cast(from, to, e.span),
+                    None => self.unknown.increment(),
+                }
+            }
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "Found integer casts: {} widening, {} narrowing, {} sign-changing, {} to the same type, and {} where we can't tell;",
+            self.widening,
+            self.narrowing.len(),
+            self.sign_changing,
+            self.noop,
+            self.unknown
+        )
+    }
+
+    fn findings(&self, findings: &mut Findings) {
+        for &span in &self.narrowing {
+            findings.push(Finding {
+                id: "truncating-cast",
+                severity: Severity::Note,
+                span,
+                message: "integer cast may truncate".to_owned(),
+                value: None,
+            });
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(CastsMetric::new())
+    }
+}
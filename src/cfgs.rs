@@ -0,0 +1,172 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Statistics about conditional compilation. We look for sibling items which
+// share a name but are gated on different `cfg`s (e.g., a `unix` and a
+// `windows` version of the same function) and count how much of the crate is
+// duplicated for each platform.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_ast::ptr::P;
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+use rustc_span::symbol::{kw, sym, Symbol};
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext, Stage};
+use spans::line_count;
+
+// Returns the `cfg` predicate on an item as a string, or `None` if the item is
+// not conditionally compiled. Several `cfg` attributes on one item are joined
+// with commas, i.e., they are treated as if they were inside an `all(...)`.
+pub fn cfg_predicate(attrs: &[ast::Attribute]) -> Option<String> {
+    let predicates: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.has_name(sym::cfg))
+        .filter_map(|a| a.meta_item_list())
+        .flat_map(|list| list.into_iter())
+        .map(|item| pprust::meta_list_item_to_string(&item))
+        .collect();
+
+    if predicates.is_empty() {
+        None
+    } else {
+        Some(predicates.join(", "))
+    }
+}
+
+// Returns the names of all features mentioned in `cfg` attributes on an item,
+// including those nested inside `all`, `any`, and `not`.
+pub fn cfg_features(attrs: &[ast::Attribute]) -> Vec<Symbol> {
+    let mut features = vec![];
+    for list in attrs.iter().filter(|a| a.has_name(sym::cfg)).filter_map(|a| a.meta_item_list()) {
+        collect_features(&list, &mut features);
+    }
+    features
+}
+
+fn collect_features(list: &[ast::NestedMetaItem], features: &mut Vec<Symbol>) {
+    for item in list {
+        if let Some(mi) = item.meta_item() {
+            if mi.has_name(sym::feature) {
+                features.extend(mi.value_str());
+            } else if let Some(nested) = mi.meta_item_list() {
+                collect_features(nested, features);
+            }
+        }
+    }
+}
+
+pub struct CfgsMetric {
+    // Count of all items, so we can say how much of the crate is duplicated.
+    item_count: Counter,
+    // Spans of duplicated items, keyed by the `cfg` predicate they are
+    // compiled under.
+    duplicates: HashMap<String, Vec<Span>>,
+}
+
+impl CfgsMetric {
+    pub fn new() -> CfgsMetric {
+        CfgsMetric {
+            item_count: Counter::new(),
+            duplicates: HashMap::new(),
+        }
+    }
+
+    // Groups a list of sibling items by name. Where more than one item with the
+    // same name is present and they are gated on different `cfg`s, then we
+    // assume the `cfg`s are mutually exclusive (otherwise the crate wouldn't
+    // compile) and record each item as a duplicate.
+    fn check_siblings<K>(&mut self, items: &[P<ast::Item<K>>]) {
+        let mut by_name: HashMap<_, Vec<(String, Span)>> = HashMap::new();
+        for item in items {
+            if item.ident.name == kw::Empty {
+                continue;
+            }
+            if let Some(predicate) = cfg_predicate(&item.attrs) {
+                by_name.entry(item.ident.name).or_default().push((predicate, item.span));
+            }
+        }
+
+        for (_, versions) in by_name {
+            let first = &versions[0].0;
+            if versions.iter().all(|&(ref p, _)| p == first) {
+                continue;
+            }
+            for (predicate, span) in versions {
+                self.duplicates.entry(predicate).or_default().push(span);
+            }
+        }
+    }
+}
+
+impl Metric for CfgsMetric {
+    fn id(&self) -> &'static str {
+        "cfgs"
+    }
+
+    // Siblings are compared across the whole crate.
+    fn per_module(&self) -> bool {
+        false
+    }
+
+    // The crate root is not an item, so we have to check its children before we
+    // start walking.
+    fn check_crate(&mut self, krate: &ast::Crate, _: Stage) {
+        self.check_siblings(&krate.items);
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        self.item_count.increment();
+        match i.kind {
+            ast::ItemKind::Mod(_, ast::ModKind::Loaded(ref items, ..)) => self.check_siblings(items),
+            ast::ItemKind::Impl(box ast::ImplKind { ref items, .. }) => self.check_siblings(items),
+            _ => {}
+        }
+    }
+
+    fn visit_assoc_item(&mut self, _: &ast::AssocItem, _: visit::AssocCtxt) {
+        self.item_count.increment();
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let duplicated: usize = self.duplicates.values().map(|spans| spans.len()).sum();
+        let percent = if self.item_count.get() == 0 {
+            0.0
+        } else {
+            100.0 * duplicated as f64 / self.item_count.get() as f64
+        };
+        writeln!(
+            out,
+            "Found {} items duplicated under mutually exclusive `cfg`s ({:.0}% of all items);",
+            duplicated, percent
+        )?;
+
+        let mut predicates: Vec<_> = self.duplicates.iter().collect();
+        predicates.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(b.0)));
+        for (predicate, spans) in predicates {
+            let lines: usize = spans.iter().map(|&s| line_count(cx.source_map, s)).sum();
+            writeln!(out, "    `{}`: {} items, {} lines", predicate, spans.len(), lines)?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(CfgsMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        let duplicated: usize = self.duplicates.values().map(|spans| spans.len()).sum();
+        stats.set_count("cfgs.items", self.item_count);
+        stats.set_count("cfgs.duplicated_items", duplicated);
+    }
+}
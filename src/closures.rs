@@ -0,0 +1,95 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Closures are functions too, so we count them and how many arguments they
+// take (in a histogram like the one for functions), how many are `move`
+// closures and how many borrow what they capture, and how deeply they nest: a
+// closure which isn't inside another one has a depth of 1.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+
+use counter::{Counter, Histogram};
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+pub struct ClosuresMetric {
+    args: Histogram,
+    moves: Counter,
+    depths: Histogram,
+    // How many closures we're inside.
+    depth: usize,
+}
+
+impl ClosuresMetric {
+    pub fn new() -> ClosuresMetric {
+        ClosuresMetric {
+            args: Histogram::new(),
+            moves: Counter::new(),
+            depths: Histogram::new(),
+            depth: 0,
+        }
+    }
+}
+
+impl Metric for ClosuresMetric {
+    fn id(&self) -> &'static str {
+        "closures"
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        if let ast::ExprKind::Closure(capture, _, _, ref decl, _, _) = e.kind {
+            self.args.record(decl.inputs.len());
+            if let ast::CaptureBy::Value = capture {
+                self.moves.increment();
+            }
+            self.depth += 1;
+            self.depths.record(self.depth);
+        }
+    }
+
+    fn exit_expr(&mut self, e: &ast::Expr) {
+        if let ast::ExprKind::Closure(..) = e.kind {
+            self.depth -= 1;
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let closures = self.args.total().get();
+        writeln!(
+            out,
+            "Found {} closures ({} `move`, {} borrowing), taking {:.1} arguments on average;",
+            closures,
+            self.moves.get(),
+            closures - self.moves.get(),
+            self.args.mean()
+        )?;
+        for (args, count) in self.args.iter().filter(|&(_, count)| count > 0) {
+            writeln!(out, "    taking {}: {}", args, count)?;
+        }
+        if closures > 0 {
+            writeln!(
+                out,
+                "Closures are nested {:.1} deep on average, and at most {}.",
+                self.depths.mean(),
+                self.depths.max()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("closures.total", self.args.total());
+        stats.set_count("closures.moves", self.moves);
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(ClosuresMetric::new())
+    }
+}
@@ -0,0 +1,356 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Cognitive complexity of functions, following SonarSource's definition. Unlike
+// cyclomatic complexity, it punishes nesting: each `if`, `match`, or loop costs
+// one plus how deeply nested it is. `else` and `else if` cost one, labelled
+// `break`s and `continue`s cost one, and so does each run of the same boolean
+// operator (so `a && b && c` costs one, but `a && b || c` costs two), and each
+// call a function makes to itself (by name, `Self::name`, or `self.name()`).
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_span::symbol::{kw, Ident};
+use rustc_span::Span;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use findings::{Finding, Findings, Severity};
+use metrics::{Metric, ReportContext};
+use sample::estimate_count;
+
+// Computes the cognitive complexity of the body of the function `name`.
+pub fn cognitive_complexity(name: Ident, body: &ast::Block) -> usize {
+    let mut scorer = Scorer {
+        name,
+        nesting: 0,
+        score: Counter::new(),
+    };
+    visit::walk_block(&mut scorer, body);
+    scorer.score.get()
+}
+
+struct Scorer {
+    // The function we are scoring, to spot recursion.
+    name: Ident,
+    nesting: usize,
+    // Saturates, for machine-generated bodies nested absurdly deep.
+    score: Counter,
+}
+
+impl Scorer {
+    fn nested<F: FnOnce(&mut Scorer)>(&mut self, f: F) {
+        self.nesting += 1;
+        f(self);
+        self.nesting -= 1;
+    }
+
+    // Scores the `else` branch of an `if`. `else` and `else if` cost one
+    // whatever the nesting.
+    fn visit_else(&mut self, e: &ast::Expr) {
+        self.score.increment();
+        match e.kind {
+            ast::ExprKind::If(ref cond, ref then, ref els) => {
+                visit::Visitor::visit_expr(self, cond);
+                self.nested(|s| visit::Visitor::visit_block(s, then));
+                if let Some(ref els) = *els {
+                    self.visit_else(els);
+                }
+            }
+            _ => self.nested(|s| visit::Visitor::visit_expr(s, e)),
+        }
+    }
+
+    // Whether `e` is a call of the function we are scoring.
+    fn is_recursive(&self, e: &ast::Expr) -> bool {
+        match e.kind {
+            ast::ExprKind::Call(ref callee, _) => match callee.kind {
+                ast::ExprKind::Path(None, ref path) => match *path.segments {
+                    [ref f] => f.ident.name == self.name.name,
+                    [ref ty, ref f] => {
+                        ty.ident.name == kw::SelfUpper && f.ident.name == self.name.name
+                    }
+                    _ => false,
+                },
+                _ => false,
+            },
+            ast::ExprKind::MethodCall(ref method, ref args, _) => {
+                method.ident.name == self.name.name && is_self(&args[0])
+            }
+            _ => false,
+        }
+    }
+}
+
+fn is_self(e: &ast::Expr) -> bool {
+    match e.kind {
+        ast::ExprKind::Path(None, ref path) => {
+            path.segments.len() == 1 && path.segments[0].ident.name == kw::SelfLower
+        }
+        _ => false,
+    }
+}
+
+// Flattens a tree of `&&` and `||` into the operators in source order and the
+// operands in between.
+fn flatten_logical<'a>(
+    e: &'a ast::Expr,
+    ops: &mut Vec<ast::BinOpKind>,
+    operands: &mut Vec<&'a ast::Expr>,
+) {
+    match e.kind {
+        ast::ExprKind::Binary(op, ref lhs, ref rhs) if is_logical(op.node) => {
+            flatten_logical(lhs, ops, operands);
+            ops.push(op.node);
+            flatten_logical(rhs, ops, operands);
+        }
+        _ => operands.push(e),
+    }
+}
+
+fn is_logical(op: ast::BinOpKind) -> bool {
+    op == ast::BinOpKind::And || op == ast::BinOpKind::Or
+}
+
+impl<'a> visit::Visitor<'a> for Scorer {
+    fn visit_expr(&mut self, e: &'a ast::Expr) {
+        match e.kind {
+            ast::ExprKind::If(ref cond, ref then, ref els) => {
+                self.score.add(1 + self.nesting);
+                self.visit_expr(cond);
+                self.nested(|s| s.visit_block(then));
+                if let Some(ref els) = *els {
+                    self.visit_else(els);
+                }
+            }
+            ast::ExprKind::Match(ref scrutinee, ref arms) => {
+                self.score.add(1 + self.nesting);
+                self.visit_expr(scrutinee);
+                self.nested(|s| {
+                    for arm in arms {
+                        s.visit_arm(arm);
+                    }
+                });
+            }
+            ast::ExprKind::While(ref cond, ref body, _) => {
+                self.score.add(1 + self.nesting);
+                self.visit_expr(cond);
+                self.nested(|s| s.visit_block(body));
+            }
+            ast::ExprKind::ForLoop(ref pat, ref iter, ref body, _) => {
+                self.score.add(1 + self.nesting);
+                self.visit_pat(pat);
+                self.visit_expr(iter);
+                self.nested(|s| s.visit_block(body));
+            }
+            ast::ExprKind::Loop(ref body, _) => {
+                self.score.add(1 + self.nesting);
+                self.nested(|s| s.visit_block(body));
+            }
+            // Closures don't cost anything themselves, but their bodies are
+            // nested.
+            ast::ExprKind::Closure(..) => self.nested(|s| visit::walk_expr(s, e)),
+            ast::ExprKind::Break(Some(_), _) | ast::ExprKind::Continue(Some(_)) => {
+                self.score.increment();
+                visit::walk_expr(self, e);
+            }
+            ast::ExprKind::Binary(op, ..) if is_logical(op.node) => {
+                let mut ops = vec![];
+                let mut operands = vec![];
+                flatten_logical(e, &mut ops, &mut operands);
+                self.score.add(1 + ops.windows(2).filter(|w| w[0] != w[1]).count());
+                for operand in operands {
+                    self.visit_expr(operand);
+                }
+            }
+            ast::ExprKind::Call(..) | ast::ExprKind::MethodCall(..) => {
+                if self.is_recursive(e) {
+                    self.score.increment();
+                }
+                visit::walk_expr(self, e);
+            }
+            _ => visit::walk_expr(self, e),
+        }
+    }
+
+    // Nested items are scored as functions in their own right.
+    fn visit_item(&mut self, _: &'a ast::Item) {}
+}
+
+pub struct CognitiveMetric {
+    // Functions with a cognitive complexity above this are reported.
+    threshold: usize,
+    // The name, location, and complexity of every function.
+    functions: Vec<(String, Span, usize)>,
+}
+
+impl CognitiveMetric {
+    pub fn new(threshold: usize) -> CognitiveMetric {
+        CognitiveMetric {
+            threshold,
+            functions: vec![],
+        }
+    }
+}
+
+impl Metric for CognitiveMetric {
+    fn id(&self) -> &'static str {
+        "cognitive-complexity"
+    }
+
+    fn sampled(&self) -> bool {
+        true
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, s: Span) {
+        if let visit::FnKind::Fn(_, ident, _, _, Some(body)) = fk {
+            self.functions.push((ident.to_string(), s, cognitive_complexity(ident, body)));
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        if self.functions.is_empty() {
+            return Ok(());
+        }
+
+        let mut total = Counter::new();
+        for &(_, _, score) in &self.functions {
+            total.add(score);
+        }
+        writeln!(
+            out,
+            "The average cognitive complexity is {:.1};",
+            total.get() as f64 / self.functions.len() as f64
+        )?;
+
+        let over = self.functions.iter().filter(|f| f.2 > self.threshold).count();
+        if cx.sampled < cx.population {
+            let (over, margin) = estimate_count(over, cx.sampled, cx.population);
+            return writeln!(
+                out,
+                "About {:.0} (± {:.0}) functions have a cognitive complexity over {};",
+                over,
+                margin,
+                self.threshold
+            );
+        }
+        writeln!(out, "{} functions have a cognitive complexity over {};", over, self.threshold)
+    }
+
+    fn findings(&self, findings: &mut Findings) {
+        for &(ref name, span, score) in self.functions.iter().filter(|f| f.2 > self.threshold) {
+            findings.push(Finding {
+                id: "cognitive-complexity",
+                severity: Severity::Warning,
+                span,
+                message: format!(
+                    "`{}` has a cognitive complexity of {} (over {})",
+                    name, score, self.threshold
+                ),
+                value: Some(score),
+            });
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(CognitiveMetric::new(self.threshold))
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        let over = self.functions.iter().filter(|f| f.2 > self.threshold).count();
+        let max = self.functions.iter().map(|f| f.2).max().unwrap_or(0);
+        stats.set_count("cognitive-complexity.over_threshold", over);
+        stats.set_count("cognitive-complexity.max", max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use with_test_crate;
+
+    struct Functions(Vec<(String, usize)>);
+
+    impl<'a> visit::Visitor<'a> for Functions {
+        fn visit_fn(&mut self, fk: visit::FnKind<'a>, s: Span, _: ast::NodeId) {
+            if let visit::FnKind::Fn(_, ident, _, _, Some(body)) = fk {
+                self.0.push((ident.to_string(), cognitive_complexity(ident, body)));
+            }
+            visit::walk_fn(self, fk, s);
+        }
+    }
+
+    // The cognitive complexity of each function in `source`.
+    fn scores(source: &str) -> Vec<(String, usize)> {
+        with_test_crate(source, |krate| {
+            let mut functions = Functions(vec![]);
+            visit::walk_crate(&mut functions, krate);
+            functions.0
+        })
+    }
+
+    fn score(source: &str) -> usize {
+        scores(source)[0].1
+    }
+
+    #[test]
+    fn nesting() {
+        assert_eq!(score("fn f() {}"), 0);
+        assert_eq!(score("fn f(x: bool) { if x {} }"), 1);
+        // 1 + 2 + 3.
+        assert_eq!(score("fn f(x: bool) { if x { for _ in 0..1 { while x {} } } }"), 6);
+        // The `match` at nesting 1, the `loop` in its arm at nesting 2.
+        assert_eq!(score("fn f(x: bool) { loop { match x { _ => loop {} } } }"), 6);
+        // A closure's body is nested.
+        assert_eq!(score("fn f(x: bool) { let g = || if x {}; }"), 2);
+    }
+
+    #[test]
+    fn else_if() {
+        assert_eq!(score("fn f(a: bool, b: bool) { if a {} else if b {} else {} }"), 3);
+        // `else if` costs one however deep it is.
+        assert_eq!(score("fn f(a: bool, b: bool) { loop { if a {} else if b {} } }"), 4);
+        // An `if` inside an `else` is nested in it.
+        assert_eq!(score("fn f(a: bool, b: bool) { if a {} else { if b {} } }"), 4);
+    }
+
+    #[test]
+    fn boolean_operators() {
+        assert_eq!(score("fn f(a: bool, b: bool, c: bool) -> bool { a && b && c }"), 1);
+        assert_eq!(score("fn f(a: bool, b: bool, c: bool) -> bool { a && b || c }"), 2);
+        assert_eq!(score("fn f(a: bool, b: bool, c: bool) -> bool { a || b && c || a }"), 3);
+        // `a && (b || c)` is two runs as well.
+        assert_eq!(score("fn f(a: bool, b: bool, c: bool) -> bool { a && (b || c) }"), 2);
+        // And a condition costs as much as the operators in it.
+        assert_eq!(score("fn f(a: bool, b: bool) { if a && b {} }"), 2);
+    }
+
+    #[test]
+    fn recursion() {
+        let source = "
+            fn fact(n: u64) -> u64 { if n == 0 { 1 } else { n * fact(n - 1) } }
+            fn other(n: u64) -> u64 { fact(n) }
+            struct Tree(Vec<Tree>);
+            impl Tree {
+                fn size(&self) -> usize { 1 + self.0.iter().map(|t| t.size()).sum::<usize>() }
+                fn depth(&self) -> usize { Self::depth(self) + self.depth() }
+            }
+        ";
+        assert_eq!(
+            scores(source),
+            vec![
+                ("fact".to_owned(), 3),
+                ("other".to_owned(), 0),
+                // `t.size()` isn't called on `self`.
+                ("size".to_owned(), 0),
+                ("depth".to_owned(), 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// What calls to `Iterator::collect` collect into (e.g., `Vec`, `String`, or
+// `Result<Vec>` for a `Result<Vec<_>, _>`). Unlike most of our stats, this needs type information:
+// the target is usually inferred, so we walk the HIR of every body and ask
+// type checking what each `collect` resolved to and what type it returns.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+use rustc_hir as hir;
+use rustc_hir::intravisit::{self, NestedVisitorMap, Visitor};
+use rustc_middle::hir::map::Map;
+use rustc_middle::ty::{self, Ty, TyCtxt, TypeckResults};
+use rustc_span::symbol::sym;
+
+use counter::Counter;
+use metrics::{AnalysisContext, Metric, ReportContext, Stage};
+
+pub struct CollectTargets {
+    // Target types (generic arguments elided) to the number of calls which
+    // collect into them.
+    targets: BTreeMap<String, Counter>,
+}
+
+impl CollectTargets {
+    pub fn new() -> CollectTargets {
+        CollectTargets {
+            targets: BTreeMap::new(),
+        }
+    }
+}
+
+impl Metric for CollectTargets {
+    fn id(&self) -> &'static str {
+        "collect-targets"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Analysis]
+    }
+
+    fn check_analysis<'tcx>(&mut self, cx: &AnalysisContext<'tcx>) {
+        let mut finder = Finder {
+            tcx: cx.tcx,
+            results: None,
+            targets: &mut self.targets,
+        };
+        cx.visit_items(&mut finder);
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let mut targets: Vec<_> = self.targets.iter().collect();
+        targets.sort_by(|a, b| b.1.get().cmp(&a.1.get()));
+
+        writeln!(out, "Calls to `collect` collect into:")?;
+        for (target, count) in targets {
+            writeln!(out, "    `{}`: {}", target, count)?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(CollectTargets::new())
+    }
+}
+
+struct Finder<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    // The type checking results for the body we're in.
+    results: Option<&'tcx TypeckResults<'tcx>>,
+    targets: &'a mut BTreeMap<String, Counter>,
+}
+
+impl<'a, 'tcx> Finder<'a, 'tcx> {
+    fn is_collect(&self, results: &TypeckResults<'tcx>, e: &hir::Expr) -> bool {
+        let def_id = match results.type_dependent_def_id(e.hir_id) {
+            Some(def_id) => def_id,
+            None => return false,
+        };
+        let iterator = self.tcx.get_diagnostic_item(sym::Iterator);
+        &*self.tcx.item_name(def_id).as_str() == "collect" &&
+            iterator.is_some() &&
+            self.tcx.trait_of_item(def_id) == iterator
+    }
+
+    // The name of a type without its generic arguments, except for `Result` and
+    // `Option`, where what they hold is the interesting part.
+    fn name(&self, ty: Ty<'tcx>) -> String {
+        match *ty.kind() {
+            ty::Adt(def, substs) => {
+                let name = self.tcx.item_name(def.did).to_string();
+                if name == "Result" || name == "Option" {
+                    let inner = substs.types().next().map_or("_".to_owned(), |t| self.name(t));
+                    format!("{}<{}>", name, inner)
+                } else {
+                    name
+                }
+            }
+            _ => ty.to_string(),
+        }
+    }
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for Finder<'a, 'tcx> {
+    type Map = Map<'tcx>;
+
+    fn nested_visit_map(&mut self) -> NestedVisitorMap<Self::Map> {
+        NestedVisitorMap::OnlyBodies(self.tcx.hir())
+    }
+
+    fn visit_nested_body(&mut self, id: hir::BodyId) {
+        let old = self.results.replace(self.tcx.typeck_body(id));
+        let body = self.tcx.hir().body(id);
+        self.visit_body(body);
+        self.results = old;
+    }
+
+    fn visit_expr(&mut self, e: &'tcx hir::Expr<'tcx>) {
+        if let hir::ExprKind::MethodCall(..) = e.kind {
+            if let Some(results) = self.results {
+                if self.is_collect(results, e) {
+                    let target = self.name(results.expr_ty(e));
+                    self.targets.entry(target).or_insert_with(Counter::new).increment();
+                }
+            }
+        }
+
+        intravisit::walk_expr(self, e)
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Comment density of functions: the share of a function's lines which have a
+// comment on them. Comments aren't in the AST, so we scan the source text of
+// each function. Complicated functions with no comments at all are findings.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_span::Span;
+use rustc_span::source_map::SourceMap;
+
+use cognitive::cognitive_complexity;
+use findings::{Finding, Findings, Severity};
+use metrics::{Metric, ReportContext, Stage};
+
+// Counts the lines of `text` which have some comment on them. We skip over
+// string literals, but not char literals, so `'"'` will confuse us.
+fn comment_lines(text: &str) -> usize {
+    let mut count = 0;
+    let mut in_block = 0;
+    let mut in_string = false;
+    for line in text.lines() {
+        let mut commented = in_block > 0;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            if in_block > 0 {
+                match (c, chars.peek()) {
+                    ('*', Some(&'/')) => {
+                        chars.next();
+                        in_block -= 1;
+                    }
+                    // Block comments nest.
+                    ('/', Some(&'*')) => {
+                        chars.next();
+                        in_block += 1;
+                    }
+                    _ => {}
+                }
+            } else if in_string {
+                match c {
+                    '\\' => {
+                        chars.next();
+                    }
+                    '"' => in_string = false,
+                    _ => {}
+                }
+            } else {
+                match (c, chars.peek()) {
+                    ('"', _) => in_string = true,
+                    ('/', Some(&'/')) => {
+                        commented = true;
+                        break;
+                    }
+                    ('/', Some(&'*')) => {
+                        chars.next();
+                        commented = true;
+                        in_block += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if commented {
+            count += 1;
+        }
+    }
+    count
+}
+
+struct Function {
+    name: String,
+    span: Span,
+    lines: usize,
+    comment_lines: usize,
+    complexity: usize,
+}
+
+pub struct CommentsMetric {
+    // Functions more complex than this (see `cognitive`) need comments.
+    threshold: usize,
+    // The functions we've walked, with their lines filled in by
+    // `check_source`.
+    functions: Vec<Function>,
+}
+
+impl CommentsMetric {
+    pub fn new(threshold: usize) -> CommentsMetric {
+        CommentsMetric {
+            threshold,
+            functions: vec![],
+        }
+    }
+}
+
+impl Metric for CommentsMetric {
+    fn id(&self) -> &'static str {
+        "comments"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Parse, Stage::Source]
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, s: Span) {
+        if let visit::FnKind::Fn(_, ident, _, _, Some(body)) = fk {
+            self.functions.push(Function {
+                name: ident.to_string(),
+                span: s,
+                lines: 0,
+                comment_lines: 0,
+                complexity: cognitive_complexity(ident, body),
+            });
+        }
+    }
+
+    fn check_source(&mut self, source_map: &SourceMap) {
+        self.functions.retain(|f| source_map.span_to_snippet(f.span).is_ok());
+        for f in &mut self.functions {
+            let text = source_map.span_to_snippet(f.span).unwrap();
+            f.lines = text.lines().count();
+            f.comment_lines = comment_lines(&text);
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let lines: usize = self.functions.iter().map(|f| f.lines).sum();
+        let comment_lines: usize = self.functions.iter().map(|f| f.comment_lines).sum();
+        let percent = if lines == 0 {
+            0.0
+        } else {
+            100.0 * comment_lines as f64 / lines as f64
+        };
+        writeln!(
+            out,
+            "{:.0}% of lines in functions have comments; {} functions have none;",
+            percent,
+            self.functions.iter().filter(|f| f.comment_lines == 0).count()
+        )
+    }
+
+    fn findings(&self, findings: &mut Findings) {
+        for f in &self.functions {
+            if f.comment_lines == 0 && f.complexity > self.threshold {
+                findings.push(Finding {
+                    id: "uncommented-complex-function",
+                    severity: Severity::Note,
+                    span: f.span,
+                    message: format!(
+                        "`{}` has a cognitive complexity of {} and no comments in its {} lines",
+                        f.name, f.complexity, f.lines
+                    ),
+                    value: Some(f.complexity),
+                });
+            }
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(CommentsMetric::new(self.threshold))
+    }
+}
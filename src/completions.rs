@@ -0,0 +1,180 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// `stupid completions <shell>`: shell completion scripts for bash, zsh, and
+// fish, covering our subcommands and flags (see `Options::take_flags`), the
+// metric ids, and the values of the flags which have a fixed set. Flags with
+// values are completed in their `--flag=value` form, which they all accept.
+// Anything else on the command line is rustc's, so we complete file names.
+
+use std::fmt::{self, Write};
+
+use metrics::METRICS;
+
+enum Value {
+    None,
+    Choice(&'static [&'static str]),
+    // Comma separated metric ids.
+    Metrics,
+    File,
+    Text,
+}
+
+const SUBCOMMANDS: &[(&str, &str)] = &[
+    ("doctor", "check that stupid-stats can run"),
+    ("api-diff", "compare two API inventories"),
+    ("merge", "combine saved summary stats into one table"),
+    ("completions", "print a shell completion script"),
+];
+
+const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+const FLAGS: &[(&str, &str, Value)] = &[
+    ("stats-format", "how to write the report", Value::Choice(&["text", "junit", "json"])),
+    ("metrics", "the metrics to report", Value::Metrics),
+    ("module-filter", "the only module to analyse", Value::Text),
+    ("stats-output", "where to write the summary as HTML", Value::File),
+    ("per-function-csv", "where to write a CSV row for each function", Value::File),
+    ("low-memory", "write the per-function rows as we go", Value::None),
+    ("sink", "where to stream a JSON line for each function", Value::Text),
+    ("anonymize", "leave names out of the saved and sent stats", Value::None),
+    ("include-generated", "analyse generated code too", Value::None),
+    ("no-codegen", "stop once we have the stats", Value::None),
+    ("profile", "how picky the rules are", Value::Choice(&["strict", "default", "lenient"])),
+    ("fail-if", "a condition on the stats which fails the build", Value::Text),
+    ("save-baseline", "where to save the stats as a baseline", Value::File),
+    ("compare-baseline", "the baseline to compare the stats with", Value::File),
+    ("fail-on-regression", "fail if a tracked stat got worse", Value::None),
+    ("sample", "the percentage of functions to analyse", Value::Text),
+    ("lang", "the language of the summary", Value::Choice(&["en", "de"])),
+    ("describe-metrics", "describe every metric as JSON", Value::None),
+];
+
+// Writes the completion script for `shell`. Returns false if we don't know the
+// shell.
+pub fn write_script(shell: &str, out: &mut String) -> Result<bool, fmt::Error> {
+    match shell {
+        "bash" => write_bash(out)?,
+        "zsh" => write_zsh(out)?,
+        "fish" => write_fish(out)?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn metric_ids() -> Vec<&'static str> {
+    METRICS.iter().map(|m| m.id).collect()
+}
+
+fn write_bash(out: &mut String) -> fmt::Result {
+    writeln!(out, "_stupid() {{")?;
+    writeln!(out, "    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"")?;
+    writeln!(out, "    case \"$cur\" in")?;
+    for &(flag, _, ref value) in FLAGS {
+        let action = match *value {
+            Value::Choice(choices) => format!("-W \"{}\"", choices.join(" ")),
+            Value::Metrics => format!("-W \"{}\"", metric_ids().join(" ")),
+            Value::File => "-f".to_owned(),
+            Value::None | Value::Text => continue,
+        };
+        writeln!(out, "        --{}=*)", flag)?;
+        writeln!(
+            out,
+            "            COMPREPLY=($(compgen -P \"--{}=\" {} -- \"${{cur#*=}}\"))",
+            flag, action
+        )?;
+        writeln!(out, "            return;;")?;
+    }
+    writeln!(out, "    esac")?;
+    writeln!(
+        out,
+        "    if [ \"$COMP_CWORD\" -eq 2 ] && [ \"${{COMP_WORDS[1]}}\" = completions ]; then"
+    )?;
+    writeln!(
+        out,
+        "        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return",
+        SHELLS.join(" ")
+    )?;
+    writeln!(out, "    fi")?;
+    let flags: Vec<_> = FLAGS
+        .iter()
+        .map(|&(flag, _, ref value)| match *value {
+            Value::None => format!("--{}", flag),
+            _ => format!("--{}=", flag),
+        })
+        .collect();
+    writeln!(out, "    local words=\"{}\"", flags.join(" "))?;
+    writeln!(out, "    if [ \"$COMP_CWORD\" -eq 1 ]; then")?;
+    let subcommands: Vec<_> = SUBCOMMANDS.iter().map(|s| s.0).collect();
+    writeln!(out, "        words=\"{} $words\"", subcommands.join(" "))?;
+    writeln!(out, "    fi")?;
+    writeln!(
+        out,
+        "    COMPREPLY=($(compgen -W \"$words\" -- \"$cur\") $(compgen -f -- \"$cur\"))"
+    )?;
+    writeln!(out, "}}")?;
+    writeln!(out, "complete -o nospace -F _stupid stupid")
+}
+
+fn write_zsh(out: &mut String) -> fmt::Result {
+    writeln!(out, "#compdef stupid")?;
+    writeln!(out)?;
+    writeln!(out, "if (( CURRENT == 3 )) && [[ $words[2] == completions ]]; then")?;
+    writeln!(out, "    _values shell {}", SHELLS.join(" "))?;
+    writeln!(out, "    return")?;
+    writeln!(out, "fi")?;
+    writeln!(out, "_arguments \\")?;
+    for &(flag, description, ref value) in FLAGS {
+        let action = match *value {
+            Value::None => String::new(),
+            Value::Choice(choices) => format!(":{}:({})", flag, choices.join(" ")),
+            Value::Metrics => format!(":metrics:_values -s , metric {}", metric_ids().join(" ")),
+            Value::File => ":path:_files".to_owned(),
+            Value::Text => format!(":{}: ", flag),
+        };
+        // `fail-if` can be given more than once.
+        let repeat = if flag == "fail-if" { "*" } else { "" };
+        let equals = match *value {
+            Value::None => "",
+            _ => "=",
+        };
+        writeln!(out, "    '{}--{}{}[{}]{}' \\", repeat, flag, equals, description, action)?;
+    }
+    let subcommands: Vec<_> = SUBCOMMANDS
+        .iter()
+        .map(|&(name, description)| format!("{}\\:\"{}\"", name, description))
+        .collect();
+    writeln!(out, "    '1:: :(({}))' \\", subcommands.join(" "))?;
+    writeln!(out, "    '*:file:_files'")
+}
+
+fn write_fish(out: &mut String) -> fmt::Result {
+    for &(name, description) in SUBCOMMANDS {
+        writeln!(
+            out,
+            "complete -c stupid -n __fish_use_subcommand -a {} -d '{}'",
+            name, description
+        )?;
+    }
+    writeln!(
+        out,
+        "complete -c stupid -n '__fish_seen_subcommand_from completions' -x -a '{}'",
+        SHELLS.join(" ")
+    )?;
+    for &(flag, description, ref value) in FLAGS {
+        let values = match *value {
+            Value::None => String::new(),
+            Value::Choice(choices) => format!(" -x -a '{}'", choices.join(" ")),
+            Value::Metrics => format!(" -x -a '{}'", metric_ids().join(" ")),
+            Value::File => " -r -F".to_owned(),
+            Value::Text => " -x".to_owned(),
+        };
+        writeln!(out, "complete -c stupid -l {} -d '{}'{}", flag, description, values)?;
+    }
+    Ok(())
+}
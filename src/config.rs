@@ -0,0 +1,255 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The `stupid-stats.toml` config file, for the options which are a pain to set
+// in the environment of every build. Each key is the name of an environment
+// variable without the `STUPID_STATS_` prefix, in lower case, so
+// `metrics = ["args", "println"]` is `STUPID_STATS_METRICS=args,println`. The
+// environment wins over the file.
+//
+// We only understand the simple subset of TOML which options need: `key =
+// value` lines, where the value is a string, a number, a boolean, or an array
+// of those on one line. Lines we don't understand are reported and ignored.
+// Booleans are `1` and `0`, as they would be in the environment, so `false` in
+// the file still wins over a default which is on.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = "stupid-stats.toml";
+
+pub struct Config {
+    // The file we read, if there was one.
+    path: Option<PathBuf>,
+    // Values by the name of the environment variable they stand for.
+    values: HashMap<String, String>,
+}
+
+impl Config {
+    // Reads the file named by `STUPID_STATS_CONFIG`, or else the nearest
+    // `stupid-stats.toml` in the current directory or above it. Cargo runs
+    // rustc from the workspace root, so that's where it's found for a
+    // workspace.
+    pub fn load() -> Config {
+        let path = match env::var_os("STUPID_STATS_CONFIG") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => env::current_dir().ok().and_then(|dir| find(&dir)),
+        };
+        let path = match path {
+            Some(path) => path,
+            None => return Config::empty(),
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => Config::parse(&text, path),
+            Err(e) => {
+                eprintln!("stupid-stats: couldn't read `{}`: {}", path.display(), e);
+                Config::empty()
+            }
+        }
+    }
+
+    pub fn empty() -> Config {
+        Config {
+            path: None,
+            values: HashMap::new(),
+        }
+    }
+
+    fn parse(text: &str, path: PathBuf) -> Config {
+        let mut values = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let parsed = line.find('=').and_then(|eq| {
+                let key = line[..eq].trim();
+                let value = parse_value(line[eq + 1..].trim())?;
+                Some((key, value))
+            });
+            match parsed {
+                Some((key, value)) if !key.is_empty() => {
+                    let name = format!("STUPID_STATS_{}", key.to_uppercase().replace('-', "_"));
+                    values.insert(name, value);
+                }
+                _ => eprintln!(
+                    "stupid-stats: ignoring line {} of `{}`: {}",
+                    i + 1,
+                    path.display(),
+                    line
+                ),
+            }
+        }
+        Config {
+            path: Some(path),
+            values,
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_ref().map(|p| &**p)
+    }
+
+    // The value of the environment variable `name`, or else of the option in
+    // the file.
+    pub fn var(&self, name: &str) -> Option<String> {
+        env::var(name).ok().or_else(|| self.values.get(name).cloned())
+    }
+
+    // Whether the switch `name` is turned on or off in the environment or the
+    // file: `1` or `true` for on, `0`, `false`, or nothing for off. Anything
+    // else is reported and counts as not set.
+    pub fn flag(&self, name: &str) -> Option<bool> {
+        let value = self.var(name)?;
+        let flag = parse_flag(&value);
+        if flag.is_none() {
+            eprintln!("stupid-stats: ignoring `{}={}`, expected `1` or `0`", name, value);
+        }
+        flag
+    }
+
+    // Whether the switch `name` is turned on, see `flag`.
+    pub fn is_set(&self, name: &str) -> bool {
+        self.flag(name).unwrap_or(false)
+    }
+}
+
+fn parse_flag(value: &str) -> Option<bool> {
+    match &*value.trim().to_lowercase() {
+        "1" | "true" => Some(true),
+        "0" | "false" | "" => Some(false),
+        _ => None,
+    }
+}
+
+fn find(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors().map(|dir| dir.join(FILE_NAME)).find(|path| path.is_file())
+}
+
+// A `#` outside a string starts a comment.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+// Like `strip_comment`, a `,` in a string doesn't separate items.
+fn split_items(inner: &str) -> Vec<&str> {
+    let mut items = vec![];
+    let mut in_string = false;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                items.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&inner[start..]);
+    items
+}
+
+// A value as we'd find it in the environment: arrays are comma separated, and
+// booleans are `1` and `0`. So an item can't have a comma in it; we'd read it
+// back as two.
+fn parse_value(value: &str) -> Option<String> {
+    if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        let items = split_items(inner)
+            .into_iter()
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .map(|item| parse_scalar(item).filter(|item| !item.contains(',')))
+            .collect::<Option<Vec<_>>>()?;
+        return Some(items.join(","));
+    }
+    match value {
+        "true" => Some("1".to_owned()),
+        "false" => Some("0".to_owned()),
+        _ => parse_scalar(value),
+    }
+}
+
+fn parse_scalar(value: &str) -> Option<String> {
+    if let Some(s) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        return Some(s.replace("\\\"", "\"").replace("\\\\", "\\"));
+    }
+    if value.parse::<f64>().is_ok() {
+        return Some(value.to_owned());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> HashMap<String, String> {
+        Config::parse(text, PathBuf::from(FILE_NAME)).values
+    }
+
+    #[test]
+    fn values() {
+        let values = parse(
+            "# options\n\
+             metrics = [\"args\", \"println\"]  # just these\n\
+             format = \"json\"\n\
+             max-cyclomatic = 15\n\
+             per_module = true\n\
+             timing = false\n\
+             track = \"a#b\"\n",
+        );
+        assert_eq!(values["STUPID_STATS_METRICS"], "args,println");
+        assert_eq!(values["STUPID_STATS_FORMAT"], "json");
+        assert_eq!(values["STUPID_STATS_MAX_CYCLOMATIC"], "15");
+        assert_eq!(values["STUPID_STATS_PER_MODULE"], "1");
+        assert_eq!(values["STUPID_STATS_TIMING"], "0");
+        assert_eq!(values["STUPID_STATS_TRACK"], "a#b");
+        assert_eq!(values.len(), 6);
+    }
+
+    #[test]
+    fn bad_lines_are_ignored() {
+        let values = parse("metrics\n= 1\nformat = json\nsample = [1, x]\nlang = \"de\"\n");
+        assert_eq!(values.len(), 1);
+        assert_eq!(values["STUPID_STATS_LANG"], "de");
+    }
+
+    #[test]
+    fn arrays() {
+        assert_eq!(split_items("\"a,b\", \"c\""), vec!["\"a,b\"", " \"c\""]);
+        assert_eq!(parse_value("[\"a\", \"b # c\", 1]"), Some("a,b # c,1".to_owned()));
+        assert_eq!(parse_value("[]"), Some(String::new()));
+        // It would come back as two items.
+        assert_eq!(parse_value("[\"a,b\"]"), None);
+        let values = parse("track = [\"a,b\"]\nmetrics = [\"args\"]\n");
+        assert_eq!(values.len(), 1);
+        assert_eq!(values["STUPID_STATS_METRICS"], "args");
+    }
+
+    #[test]
+    fn flags() {
+        assert_eq!(parse_flag("1"), Some(true));
+        assert_eq!(parse_flag("true"), Some(true));
+        assert_eq!(parse_flag(" TRUE "), Some(true));
+        assert_eq!(parse_flag("0"), Some(false));
+        assert_eq!(parse_flag("false"), Some(false));
+        assert_eq!(parse_flag(""), Some(false));
+        assert_eq!(parse_flag("yes"), None);
+    }
+}
@@ -0,0 +1,240 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Counters which are safe to use on huge (e.g., generated) crates. A `Counter`
+// saturates rather than overflowing, and a `Histogram` only allocates for the
+// values it has actually seen and groups very large values together, so a
+// single function with a silly number of arguments can't blow up memory.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Counter {
+    value: usize,
+    // Set if we ever tried to count past `usize::MAX`.
+    overflowed: bool,
+}
+
+impl Counter {
+    pub fn new() -> Counter {
+        Counter::default()
+    }
+
+    pub fn increment(&mut self) {
+        self.add(1);
+    }
+
+    pub fn add(&mut self, n: usize) {
+        match self.value.checked_add(n) {
+            Some(value) => self.value = value,
+            None => {
+                self.value = usize::MAX;
+                self.overflowed = true;
+            }
+        }
+    }
+
+    pub fn get(&self) -> usize {
+        self.value
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    // Adds up `counts`; the total has overflowed if any of them had.
+    pub fn sum<'a, I: IntoIterator<Item = &'a Counter>>(counts: I) -> Counter {
+        let mut total = Counter::new();
+        for count in counts {
+            total.add(count.value);
+            total.overflowed |= count.overflowed;
+        }
+        total
+    }
+}
+
+impl From<usize> for Counter {
+    fn from(value: usize) -> Counter {
+        Counter {
+            value,
+            overflowed: false,
+        }
+    }
+}
+
+// An overflowed counter is displayed as, e.g., `18446744073709551615+`, so the
+// report shows that the real number is bigger.
+impl fmt::Display for Counter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.value, f)?;
+        if self.overflowed {
+            write!(f, "+")?;
+        }
+        Ok(())
+    }
+}
+
+// Values at or above this are all counted in the same bucket.
+const MAX_BUCKET: usize = 256;
+
+#[derive(Clone, Default, Debug)]
+pub struct Histogram {
+    buckets: BTreeMap<usize, Counter>,
+    // Set if any value had to be grouped into the `MAX_BUCKET` bucket.
+    clamped: bool,
+}
+
+impl Histogram {
+    pub fn new() -> Histogram {
+        Histogram::default()
+    }
+
+    pub fn record(&mut self, value: usize) {
+        self.record_many(value, 1);
+    }
+
+    // Records `count` of `value` at once, e.g., when reading a saved
+    // histogram back in.
+    pub fn record_many(&mut self, value: usize, count: usize) {
+        let bucket = if value >= MAX_BUCKET {
+            self.clamped = true;
+            MAX_BUCKET
+        } else {
+            value
+        };
+        self.buckets.entry(bucket).or_default().add(count);
+    }
+
+    pub fn merge(&mut self, other: &Histogram) {
+        for (value, count) in other.iter() {
+            self.record_many(value, count);
+        }
+        self.clamped |= other.clamped;
+    }
+
+    // Iterates over (value, count) pairs for the values we've seen, smallest
+    // first.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = (usize, usize)> + 'a {
+        self.buckets.iter().map(|(&value, count)| (value, count.get()))
+    }
+
+    pub fn total(&self) -> Counter {
+        let mut total = Counter::new();
+        for count in self.buckets.values() {
+            total.add(count.get());
+            total.overflowed |= count.overflowed();
+        }
+        total
+    }
+
+    // True if the histogram doesn't exactly reflect what was recorded, either
+    // because a count overflowed or because large values were grouped together.
+    pub fn is_approximate(&self) -> bool {
+        self.clamped || self.buckets.values().any(|c| c.overflowed())
+    }
+
+    // The mean of the recorded values, with clamped values counted as
+    // `MAX_BUCKET`.
+    pub fn mean(&self) -> f64 {
+        // As floats, so that the sums can't overflow.
+        let mut total = 0.0;
+        let mut sum = 0.0;
+        for (value, count) in self.iter() {
+            total += count as f64;
+            sum += value as f64 * count as f64;
+        }
+        if total == 0.0 { 0.0 } else { sum / total }
+    }
+
+    // The smallest value which at least `percent`% of the recorded values are
+    // at or below, e.g., `percentile(50.0)` is the median.
+    pub fn percentile(&self, percent: f64) -> usize {
+        let total: f64 = self.iter().map(|(_, count)| count as f64).sum();
+        let target = total * percent / 100.0;
+        let mut seen = 0.0;
+        for (value, count) in self.iter() {
+            seen += count as f64;
+            if seen >= target {
+                return value;
+            }
+        }
+        0
+    }
+
+    // The largest value recorded, or 0 if there are none.
+    pub fn max(&self) -> usize {
+        self.buckets.keys().next_back().cloned().unwrap_or(0)
+    }
+
+    pub fn max_bucket() -> usize {
+        MAX_BUCKET
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter_saturates() {
+        let mut counter = Counter::from(usize::MAX - 1);
+        counter.increment();
+        assert!(!counter.overflowed());
+        counter.increment();
+        assert!(counter.overflowed());
+        assert_eq!(counter.get(), usize::MAX);
+        assert_eq!(counter.to_string(), format!("{}+", usize::MAX));
+    }
+
+    #[test]
+    fn percentiles() {
+        let mut histogram = Histogram::new();
+        for value in 1..=10 {
+            histogram.record(value);
+        }
+        assert_eq!(histogram.percentile(50.0), 5);
+        assert_eq!(histogram.percentile(90.0), 9);
+        assert_eq!(histogram.percentile(100.0), 10);
+        assert_eq!(histogram.percentile(0.0), 1);
+        assert!((histogram.mean() - 5.5).abs() < 1e-9);
+        assert_eq!(histogram.max(), 10);
+
+        // Repeats count towards the percentile as often as they're recorded.
+        let mut skewed = Histogram::new();
+        skewed.record_many(0, 9);
+        skewed.record(7);
+        assert_eq!(skewed.percentile(50.0), 0);
+        assert_eq!(skewed.percentile(90.0), 0);
+        assert_eq!(skewed.percentile(95.0), 7);
+    }
+
+    #[test]
+    fn empty_histogram() {
+        let histogram = Histogram::new();
+        assert_eq!(histogram.percentile(50.0), 0);
+        assert!(histogram.mean().abs() < 1e-9);
+        assert_eq!(histogram.max(), 0);
+        assert_eq!(histogram.total().get(), 0);
+    }
+
+    #[test]
+    fn large_values_are_clamped() {
+        let mut histogram = Histogram::new();
+        histogram.record(3);
+        assert!(!histogram.is_approximate());
+        histogram.record(10_000);
+        assert!(histogram.is_approximate());
+        assert_eq!(histogram.max(), Histogram::max_bucket());
+
+        let mut merged = Histogram::new();
+        merged.merge(&histogram);
+        assert!(merged.is_approximate());
+        assert_eq!(merged.iter().collect::<Vec<_>>(), vec![(3, 1), (MAX_BUCKET, 1)]);
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Crate-level attributes: `#![feature]` gates (how much the crate depends on
+// nightly), lint levels set for the whole crate, and limit overrides such as
+// `#![recursion_limit]`.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+use rustc_ast_pretty::pprust;
+use rustc_span::symbol::sym;
+
+use metrics::{Metric, ReportContext, Stage};
+
+pub struct CrateAttrsMetric {
+    features: Vec<String>,
+    // The lint level (e.g., `deny`) and the lint.
+    lints: Vec<(String, String)>,
+    // The limit (e.g., `recursion_limit`) and its value.
+    limits: Vec<(String, String)>,
+}
+
+impl CrateAttrsMetric {
+    pub fn new() -> CrateAttrsMetric {
+        CrateAttrsMetric {
+            features: vec![],
+            lints: vec![],
+            limits: vec![],
+        }
+    }
+}
+
+impl Metric for CrateAttrsMetric {
+    fn id(&self) -> &'static str {
+        "crate-attrs"
+    }
+
+    fn per_module(&self) -> bool {
+        false
+    }
+
+    // The crate's attributes are the crate's.
+    fn whole_crate(&self) -> bool {
+        true
+    }
+
+    fn check_crate(&mut self, krate: &ast::Crate, _: Stage) {
+        for attr in &krate.attrs {
+            let name = match attr.ident() {
+                Some(ident) => ident.name,
+                None => continue,
+            };
+
+            if name == sym::feature {
+                for item in attr.meta_item_list().unwrap_or_default() {
+                    self.features.push(pprust::meta_list_item_to_string(&item));
+                }
+            } else if [sym::allow, sym::warn, sym::deny, sym::forbid].contains(&name) {
+                for item in attr.meta_item_list().unwrap_or_default() {
+                    self.lints.push((name.to_string(), pprust::meta_list_item_to_string(&item)));
+                }
+            } else if [sym::recursion_limit, sym::type_length_limit].contains(&name) {
+                if let Some(value) = attr.value_str() {
+                    self.limits.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let features: Vec<_> = self.features.iter().map(|f| format!("`{}`", f)).collect();
+        if features.is_empty() {
+            writeln!(out, "The crate uses no nightly features;")?;
+        } else {
+            writeln!(out, "The crate uses {} nightly features: {};", features.len(), features.join(", "))?;
+        }
+
+        if !self.lints.is_empty() {
+            writeln!(out, "Crate-level lint levels:")?;
+            for &(ref level, ref lint) in &self.lints {
+                writeln!(out, "    {}({})", level, lint)?;
+            }
+        }
+        for &(ref limit, ref value) in &self.limits {
+            writeln!(out, "The crate sets `{}` to {};", limit, value)?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(CrateAttrsMetric::new())
+    }
+}
@@ -0,0 +1,388 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The summary stats for a crate as plain data, so they can be written as JSON
+// for other tools rather than as text for people. We write the JSON by hand;
+// it is simple enough not to need a dependency. For merging a lot of crates'
+// stats, there is also a compact binary form, which is quicker to write and
+// read (also by hand: numbers as LEB128, strings and maps prefixed with their
+// length).
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::mem;
+
+use counter::{Counter, Histogram};
+
+// The start of the binary form, with its version.
+const BINARY_HEADER: &[u8] = b"stupid-stats\x01";
+
+#[derive(Default)]
+pub struct CrateStats {
+    pub crate_name: String,
+    // The Cargo package the crate is from (empty if not built by Cargo). A
+    // package can have several crates with the same name, e.g., a library
+    // and a binary.
+    pub package: String,
+    // Which of the package's targets the crate is: `example`, `test`, or
+    // `bench` (for those in `examples`, `tests`, and `benches`), or empty for
+    // its library and binaries. See `target_kind`.
+    pub target: String,
+    pub println_count: Counter,
+    // Uses of each formatting macro (`print`, `format`, etc.) which was used.
+    pub format_macros: BTreeMap<String, Counter>,
+    pub arg_counts: Histogram,
+    // The most common number of arguments and the percentage of functions with
+    // that many.
+    pub common_args: usize,
+    pub common_args_percent: f64,
+    pub four_or_more_args_percent: f64,
+    pub mean_args: f64,
+    pub median_args: usize,
+    pub p90_args: usize,
+    pub max_args: usize,
+    // Lines of generated code which the stats leave out.
+    pub generated_lines_excluded: usize,
+    // The other metrics' numbers, by the metric's id and what's counted, e.g.,
+    // `unsafe.blocks`.
+    pub counts: BTreeMap<String, Counter>,
+}
+
+impl CrateStats {
+    // Sets one of the metrics' counts, e.g., `set_count("unsafe.blocks", 3)`.
+    pub fn set_count<C: Into<Counter>>(&mut self, name: &str, count: C) {
+        self.counts.insert(name.to_owned(), count.into());
+    }
+
+    // Strips anything which could identify the code, for `--anonymize`, leaving
+    // the numbers: the crate and package names, and the tracked calls' counts
+    // (which are named after the APIs they track).
+    pub fn anonymize(&mut self) {
+        self.crate_name = "anonymous".to_owned();
+        self.package = String::new();
+        self.counts = mem::take(&mut self.counts)
+            .into_iter()
+            .filter(|&(ref name, _)| !name.starts_with("tracked-calls."))
+            .collect();
+    }
+
+    pub fn write_json(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"crate_name\": \"{}\",", escape(&self.crate_name))?;
+        writeln!(out, "  \"package\": \"{}\",", escape(&self.package))?;
+        writeln!(out, "  \"target\": \"{}\",", escape(&self.target))?;
+        writeln!(out, "  \"println_count\": {},", self.println_count.get())?;
+        let macros: Vec<_> = self
+            .format_macros
+            .iter()
+            .map(|(name, count)| format!("\"{}\": {}", escape(name), count.get()))
+            .collect();
+        writeln!(out, "  \"format_macros\": {{{}}},", macros.join(", "))?;
+        let histogram: Vec<_> = self
+            .arg_counts
+            .iter()
+            .map(|(args, count)| format!("\"{}\": {}", args, count))
+            .collect();
+        writeln!(out, "  \"arg_counts\": {{{}}},", histogram.join(", "))?;
+        writeln!(out, "  \"common_args\": {},", self.common_args)?;
+        writeln!(out, "  \"common_args_percent\": {:.2},", self.common_args_percent)?;
+        writeln!(out, "  \"four_or_more_args_percent\": {:.2},", self.four_or_more_args_percent)?;
+        writeln!(out, "  \"mean_args\": {:.2},", self.mean_args)?;
+        writeln!(out, "  \"median_args\": {},", self.median_args)?;
+        writeln!(out, "  \"p90_args\": {},", self.p90_args)?;
+        writeln!(out, "  \"max_args\": {},", self.max_args)?;
+        writeln!(out, "  \"generated_lines_excluded\": {},", self.generated_lines_excluded)?;
+        let counts: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(name, count)| format!("\"{}\": {}", escape(name), count.get()))
+            .collect();
+        writeln!(out, "  \"counts\": {{{}}},", counts.join(", "))?;
+        // Whether any of the above had to be clamped, see `Histogram`.
+        let approximate = self.println_count.overflowed() ||
+            self.format_macros.values().any(|c| c.overflowed()) ||
+            self.arg_counts.is_approximate() ||
+            self.counts.values().any(|c| c.overflowed());
+        writeln!(out, "  \"approximate\": {}", approximate)?;
+        writeln!(out, "}}")
+    }
+
+    // Reads back the counts from JSON written by `write_json`, e.g., to merge
+    // several crates' stats. This isn't a JSON parser, it only understands our
+    // own layout. The summaries worked out from the counts (the most common
+    // number of arguments, etc.) are left for the caller to recompute.
+    pub fn read_json(json: &str) -> Option<CrateStats> {
+        let mut stats = CrateStats::default();
+        for line in json.lines() {
+            let line = line.trim().trim_end_matches(',');
+            let colon = match line.find("\": ") {
+                Some(colon) => colon,
+                None => continue,
+            };
+            let key = line[..colon].trim_start_matches('"');
+            let value = &line[colon + 3..];
+            match key {
+                "crate_name" => stats.crate_name = unescape(value.trim_matches('"')),
+                "package" => stats.package = unescape(value.trim_matches('"')),
+                "target" => stats.target = unescape(value.trim_matches('"')),
+                "println_count" => stats.println_count.add(value.parse().ok()?),
+                "format_macros" => {
+                    for (name, count) in read_map(value)? {
+                        stats.format_macros.entry(name).or_default().add(count);
+                    }
+                }
+                "arg_counts" => {
+                    for (args, count) in read_map(value)? {
+                        stats.arg_counts.record_many(args.parse().ok()?, count);
+                    }
+                }
+                "generated_lines_excluded" => stats.generated_lines_excluded = value.parse().ok()?,
+                "counts" => {
+                    for (name, count) in read_map(value)? {
+                        stats.counts.entry(name).or_default().add(count);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if stats.crate_name.is_empty() {
+            return None;
+        }
+        Some(stats)
+    }
+
+    // Writes the counts which `read_json` reads back, in the binary form.
+    pub fn write_binary(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(BINARY_HEADER);
+        write_string(out, &self.crate_name);
+        write_string(out, &self.package);
+        write_string(out, &self.target);
+        write_number(out, self.println_count.get());
+        write_number(out, self.format_macros.len());
+        for (name, count) in &self.format_macros {
+            write_string(out, name);
+            write_number(out, count.get());
+        }
+        write_number(out, self.arg_counts.iter().count());
+        for (args, count) in self.arg_counts.iter() {
+            write_number(out, args);
+            write_number(out, count);
+        }
+        write_number(out, self.generated_lines_excluded);
+        write_number(out, self.counts.len());
+        for (name, count) in &self.counts {
+            write_string(out, name);
+            write_number(out, count.get());
+        }
+    }
+
+    // Reads back what `write_binary` wrote. As with `read_json`, the summaries
+    // are left for the caller to recompute.
+    pub fn read_binary(bytes: &[u8]) -> Option<CrateStats> {
+        let mut bytes = bytes.strip_prefix(BINARY_HEADER)?;
+        let bytes = &mut bytes;
+        let mut stats = CrateStats {
+            crate_name: read_string(bytes)?,
+            package: read_string(bytes)?,
+            target: read_string(bytes)?,
+            ..CrateStats::default()
+        };
+        stats.println_count.add(read_number(bytes)?);
+        for _ in 0..read_number(bytes)? {
+            let name = read_string(bytes)?;
+            stats.format_macros.entry(name).or_default().add(read_number(bytes)?);
+        }
+        for _ in 0..read_number(bytes)? {
+            let args = read_number(bytes)?;
+            stats.arg_counts.record_many(args, read_number(bytes)?);
+        }
+        stats.generated_lines_excluded = read_number(bytes)?;
+        for _ in 0..read_number(bytes)? {
+            let name = read_string(bytes)?;
+            stats.counts.entry(name).or_default().add(read_number(bytes)?);
+        }
+        if stats.crate_name.is_empty() || !bytes.is_empty() {
+            return None;
+        }
+        Some(stats)
+    }
+}
+
+fn write_number(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    write_number(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+// Reads a number from the start of `bytes`, and moves past it.
+fn read_number(bytes: &mut &[u8]) -> Option<usize> {
+    let mut n = 0usize;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        let bits = ((byte & 0x7f) as usize).checked_shl(shift)?;
+        if bits >> shift != (byte & 0x7f) as usize {
+            return None;
+        }
+        n |= bits;
+        if byte & 0x80 == 0 {
+            return Some(n);
+        }
+        shift += 7;
+    }
+}
+
+fn read_string(bytes: &mut &[u8]) -> Option<String> {
+    let len = read_number(bytes)?;
+    if len > bytes.len() {
+        return None;
+    }
+    let (s, rest) = bytes.split_at(len);
+    *bytes = rest;
+    String::from_utf8(s.to_vec()).ok()
+}
+
+// Reads a map like `{"a": 1, "b": 2}`, as written by `write_json`.
+fn read_map(value: &str) -> Option<Vec<(String, usize)>> {
+    let inner = value.trim().strip_prefix('{')?.strip_suffix('}')?;
+    inner
+        .split(", ")
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let mut parts = entry.splitn(2, ": ");
+            let name = unescape(parts.next()?.trim_matches('"'));
+            let count = parts.next()?.parse().ok()?;
+            Some((name, count))
+        })
+        .collect()
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("\\\"", "\"").replace("\\\\", "\\")
+}
+
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::new();
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let mut stats = CrateStats {
+            crate_name: "foo \"bar\"".to_owned(),
+            package: "foo".to_owned(),
+            target: "example".to_owned(),
+            generated_lines_excluded: 12,
+            ..CrateStats::default()
+        };
+        stats.println_count.add(3);
+        stats.format_macros.entry("format".to_owned()).or_default().add(2);
+        stats.arg_counts.record_many(0, 4);
+        stats.arg_counts.record(2);
+        stats.set_count("unsafe.blocks", 5);
+        stats.set_count("panics.todo", 0);
+
+        let mut json = String::new();
+        stats.write_json(&mut json).unwrap();
+        let read = CrateStats::read_json(&json).unwrap();
+        assert_eq!(read.crate_name, stats.crate_name);
+        assert_eq!(read.package, "foo");
+        assert_eq!(read.target, "example");
+        assert_eq!(read.println_count.get(), 3);
+        assert_eq!(read.format_macros["format"].get(), 2);
+        let histogram = |stats: &CrateStats| stats.arg_counts.iter().collect::<Vec<_>>();
+        assert_eq!(histogram(&read), histogram(&stats));
+        assert_eq!(read.generated_lines_excluded, 12);
+        assert_eq!(read.counts["unsafe.blocks"].get(), 5);
+        assert_eq!(read.counts["panics.todo"].get(), 0);
+
+        let mut again = String::new();
+        read.write_json(&mut again).unwrap();
+        assert_eq!(again, json);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let mut stats = CrateStats {
+            crate_name: "foo".to_owned(),
+            package: "föo".to_owned(),
+            generated_lines_excluded: 300,
+            ..CrateStats::default()
+        };
+        stats.println_count.add(usize::MAX);
+        stats.format_macros.entry("format".to_owned()).or_default().add(2);
+        stats.arg_counts.record_many(0, 4);
+        stats.arg_counts.record(200);
+        stats.set_count("unsafe.blocks", 5);
+
+        let mut bytes = vec![];
+        stats.write_binary(&mut bytes);
+        let read = CrateStats::read_binary(&bytes).unwrap();
+        let mut json = String::new();
+        stats.write_json(&mut json).unwrap();
+        let mut again = String::new();
+        read.write_json(&mut again).unwrap();
+        assert_eq!(again, json);
+
+        // Anything cut short, or with more after it, isn't ours.
+        for len in 0..bytes.len() {
+            assert!(CrateStats::read_binary(&bytes[..len]).is_none());
+        }
+        bytes.push(0);
+        assert!(CrateStats::read_binary(&bytes).is_none());
+        assert!(CrateStats::read_binary(b"{\n  \"crate_name\": \"foo\"\n}\n").is_none());
+    }
+
+    #[test]
+    fn anonymize() {
+        let mut stats = CrateStats {
+            crate_name: "secret_sauce".to_owned(),
+            package: "secret-sauce".to_owned(),
+            target: "test".to_owned(),
+            ..CrateStats::default()
+        };
+        stats.println_count.add(3);
+        stats.set_count("unsafe.blocks", 5);
+        stats.set_count("tracked-calls.SecretClient::connect", 2);
+        stats.anonymize();
+
+        let mut json = String::new();
+        stats.write_json(&mut json).unwrap();
+        assert!(!json.contains("secret") && !json.contains("Secret"));
+        assert_eq!(stats.crate_name, "anonymous");
+        assert_eq!(stats.target, "test");
+        assert_eq!(stats.println_count.get(), 3);
+        assert_eq!(stats.counts.keys().collect::<Vec<_>>(), vec!["unsafe.blocks"]);
+    }
+
+    #[test]
+    fn needs_a_crate_name() {
+        assert!(CrateStats::read_json("{\n  \"println_count\": 1\n}\n").is_none());
+        assert!(CrateStats::read_json("{\n  \"println_count\": x,\n}\n").is_none());
+    }
+}
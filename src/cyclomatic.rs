@@ -0,0 +1,236 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Cyclomatic complexity of functions: one plus the number of places control
+// flow can branch. Each `if`, loop, `&&`, `||`, and `?` adds one, and each
+// `match` adds one for every arm after the first. Closures count towards the
+// function they're in; nested items are functions in their own right. See
+// `cognitive` for a measure which also cares about nesting.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_span::Span;
+
+use counter::Histogram;
+use crate_stats::CrateStats;
+use findings::{Finding, Findings, Severity};
+use metrics::{Metric, ReportContext};
+use sample::estimate_count;
+
+// How many of the most complex functions to list.
+const TOP_FUNCTIONS: usize = 10;
+
+// The ranges of complexity in the distribution, as inclusive upper bounds.
+// These are the usual bands: simple, moderate, complex, and untestable.
+const BANDS: &[usize] = &[5, 10, 20, 50];
+
+pub fn cyclomatic_complexity(body: &ast::Block) -> usize {
+    let mut counter = BranchCounter { branches: 0 };
+    visit::walk_block(&mut counter, body);
+    1 + counter.branches
+}
+
+struct BranchCounter {
+    branches: usize,
+}
+
+impl<'a> visit::Visitor<'a> for BranchCounter {
+    fn visit_expr(&mut self, e: &'a ast::Expr) {
+        match e.kind {
+            ast::ExprKind::If(..) |
+            ast::ExprKind::While(..) |
+            ast::ExprKind::ForLoop(..) |
+            ast::ExprKind::Loop(..) |
+            ast::ExprKind::Try(..) => self.branches += 1,
+            ast::ExprKind::Match(_, ref arms) => self.branches += arms.len().saturating_sub(1),
+            ast::ExprKind::Binary(op, ..) => match op.node {
+                ast::BinOpKind::And | ast::BinOpKind::Or => self.branches += 1,
+                _ => {}
+            },
+            _ => {}
+        }
+        visit::walk_expr(self, e)
+    }
+
+    // Nested items are counted as functions in their own right.
+    fn visit_item(&mut self, _: &'a ast::Item) {}
+}
+
+pub struct CyclomaticMetric {
+    // Functions over this are findings, if it's set.
+    threshold: Option<usize>,
+    // The name, location, and complexity of every function.
+    functions: Vec<(String, Span, usize)>,
+}
+
+impl CyclomaticMetric {
+    pub fn new(threshold: Option<usize>) -> CyclomaticMetric {
+        CyclomaticMetric {
+            threshold,
+            functions: vec![],
+        }
+    }
+}
+
+impl Metric for CyclomaticMetric {
+    fn id(&self) -> &'static str {
+        "cyclomatic-complexity"
+    }
+
+    fn sampled(&self) -> bool {
+        true
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, s: Span) {
+        if let visit::FnKind::Fn(_, ident, _, _, Some(body)) = fk {
+            self.functions.push((ident.to_string(), s, cyclomatic_complexity(body)));
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        if self.functions.is_empty() {
+            return Ok(());
+        }
+
+        let mut histogram = Histogram::new();
+        for &(_, _, score) in &self.functions {
+            histogram.record(score);
+        }
+        writeln!(
+            out,
+            "The average cyclomatic complexity is {:.1} (median {}, at most {});",
+            histogram.mean(),
+            histogram.percentile(50.0),
+            histogram.max()
+        )?;
+        let mut lower = 1;
+        for &upper in BANDS.iter().chain(Some(&usize::MAX)) {
+            let count: usize = histogram
+                .iter()
+                .filter(|&(score, _)| score >= lower && score <= upper)
+                .map(|(_, count)| count)
+                .sum();
+            // Estimated for the whole crate if we sampled.
+            let count = if cx.sampled < cx.population {
+                let (estimate, margin) = estimate_count(count, cx.sampled, cx.population);
+                format!("about {:.0} (± {:.0})", estimate, margin)
+            } else {
+                count.to_string()
+            };
+            if upper == usize::MAX {
+                writeln!(out, "    over {}: {}", lower - 1, count)?;
+            } else {
+                writeln!(out, "    {}-{}: {}", lower, upper, count)?;
+            }
+            lower = upper.saturating_add(1);
+        }
+
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by(|a, b| b.2.cmp(&a.2));
+        writeln!(out, "The most complex functions are:")?;
+        for &&(ref name, span, score) in functions.iter().take(TOP_FUNCTIONS) {
+            writeln!(out, "    `{}`: {} at {}", name, score, cx.source_map.span_to_string(span))?;
+        }
+        Ok(())
+    }
+
+    fn findings(&self, findings: &mut Findings) {
+        let threshold = match self.threshold {
+            Some(threshold) => threshold,
+            None => return,
+        };
+        for &(ref name, span, score) in self.functions.iter().filter(|f| f.2 > threshold) {
+            findings.push(Finding {
+                id: "cyclomatic-complexity",
+                severity: Severity::Warning,
+                span,
+                message: format!(
+                    "`{}` has a cyclomatic complexity of {} (over {})",
+                    name, score, threshold
+                ),
+                value: Some(score),
+            });
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(CyclomaticMetric::new(self.threshold))
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        let max = self.functions.iter().map(|f| f.2).max().unwrap_or(0);
+        stats.set_count("cyclomatic-complexity.max", max);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrics::Stage;
+    use {with_test_crate, StupidVisitor};
+
+    fn score(source: &str) -> usize {
+        with_test_crate(source, |krate| match krate.items[0].kind {
+            ast::ItemKind::Fn(box ast::FnKind(_, _, _, Some(ref body))) => {
+                cyclomatic_complexity(body)
+            }
+            _ => panic!("`{}` isn't a function", source),
+        })
+    }
+
+    #[test]
+    fn straight_line() {
+        assert_eq!(score("fn f() {}"), 1);
+        assert_eq!(score("fn f(x: u32) -> u32 { let y = x + 1; y * 2 }"), 1);
+    }
+
+    #[test]
+    fn match_arms() {
+        assert_eq!(score("fn f(x: u32) { match x { _ => {} } }"), 1);
+        assert_eq!(score("fn f(x: u32) { match x { 0 => {} 1 | 2 => {} _ => {} } }"), 3);
+        // A guard doesn't add a path of its own.
+        assert_eq!(score("fn f(x: u32) { match x { n if n > 1 => {} _ => {} } }"), 2);
+    }
+
+    #[test]
+    fn question_mark() {
+        assert_eq!(score("fn f(x: Option<u32>) -> Option<u32> { Some(x? + x?) }"), 3);
+    }
+
+    #[test]
+    fn boolean_operators() {
+        // Unlike cognitive complexity, each operator counts.
+        assert_eq!(score("fn f(a: bool, b: bool, c: bool) -> bool { a && b && c }"), 3);
+        assert_eq!(score("fn f(a: bool, b: bool) { if a || b {} }"), 3);
+        assert_eq!(score("fn f(a: u32, b: u32) -> bool { a == b & 1 }"), 1);
+    }
+
+    #[test]
+    fn loops() {
+        assert_eq!(score("fn f() { loop {} }"), 2);
+        assert_eq!(score("fn f(x: bool) { while x {} for _ in 0..1 {} }"), 3);
+        // Closures count towards their function, nested items don't.
+        assert_eq!(score("fn f(x: bool) { let g = || while x {}; fn h(x: bool) { if x {} } }"), 2);
+    }
+
+    #[test]
+    fn findings() {
+        let source = "fn simple() {}\nfn branchy(x: u32) { if x > 1 {} if x > 2 {} }\n";
+        let (stats, findings) = with_test_crate(source, |krate| {
+            let mut visitor = StupidVisitor::new(vec![Box::new(CyclomaticMetric::new(Some(2)))]);
+            visitor.walk(krate, Stage::Parse);
+            let mut findings = Findings::new();
+            visitor.findings(&mut findings);
+            let findings: Vec<_> = findings.iter().map(|f| f.message.clone()).collect();
+            (visitor.crate_stats("foo"), findings)
+        });
+        assert_eq!(stats.counts["cyclomatic-complexity.max"].get(), 3);
+        assert_eq!(findings, vec!["`branchy` has a cyclomatic complexity of 3 (over 2)"]);
+    }
+}
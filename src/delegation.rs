@@ -0,0 +1,136 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Thin wrappers: functions whose whole body is a single call which forwards
+// all of the function's parameters, e.g., `fn len(&self) -> usize {
+// self.inner.len() }`. Lots of these suggest a facade layer.
+
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_ast::ptr::P;
+use rustc_span::Span;
+use rustc_span::symbol::Symbol;
+
+use counter::Counter;
+use metrics::{Metric, ReportContext};
+
+pub struct DelegationMetric {
+    functions: Counter,
+    wrappers: Vec<(String, Span)>,
+}
+
+impl DelegationMetric {
+    pub fn new() -> DelegationMetric {
+        DelegationMetric {
+            functions: Counter::new(),
+            wrappers: vec![],
+        }
+    }
+}
+
+// The single call a body consists of, as the callee's arguments (including
+// the receiver of a method call).
+fn single_call(body: &ast::Block) -> Option<&[P<ast::Expr>]> {
+    if body.stmts.len() != 1 {
+        return None;
+    }
+    let expr = match body.stmts[0].kind {
+        ast::StmtKind::Expr(ref e) | ast::StmtKind::Semi(ref e) => e,
+        _ => return None,
+    };
+    let expr = match expr.kind {
+        ast::ExprKind::Await(ref e) | ast::ExprKind::Try(ref e) => e,
+        _ => expr,
+    };
+    match expr.kind {
+        ast::ExprKind::Call(_, ref args) | ast::ExprKind::MethodCall(_, ref args, _) => Some(args),
+        _ => None,
+    }
+}
+
+// The parameter an argument passes on, allowing for borrowing, dereferencing,
+// and field access (e.g., `&self.inner` passes on `self`).
+fn forwarded_param(arg: &ast::Expr) -> Option<Symbol> {
+    match arg.kind {
+        ast::ExprKind::Path(None, ref path) if path.segments.len() == 1 => {
+            Some(path.segments[0].ident.name)
+        }
+        ast::ExprKind::AddrOf(_, _, ref e) |
+        ast::ExprKind::Unary(ast::UnOp::Deref, ref e) |
+        ast::ExprKind::Field(ref e, _) |
+        ast::ExprKind::Paren(ref e) => forwarded_param(e),
+        _ => None,
+    }
+}
+
+fn is_delegation(decl: &ast::FnDecl, body: &ast::Block) -> bool {
+    let args = match single_call(body) {
+        Some(args) => args,
+        None => return false,
+    };
+
+    let mut params = HashSet::new();
+    for param in &decl.inputs {
+        match param.pat.kind {
+            ast::PatKind::Ident(_, ident, None) => {
+                params.insert(ident.name);
+            }
+            _ => return false,
+        }
+    }
+
+    let mut forwarded = HashSet::new();
+    for arg in args {
+        match forwarded_param(arg) {
+            Some(name) if params.contains(&name) => {
+                forwarded.insert(name);
+            }
+            _ => return false,
+        }
+    }
+    forwarded == params
+}
+
+impl Metric for DelegationMetric {
+    fn id(&self) -> &'static str {
+        "delegation"
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, s: Span) {
+        if let visit::FnKind::Fn(_, ident, sig, _, Some(body)) = fk {
+            self.functions.increment();
+            if is_delegation(&sig.decl, body) {
+                self.wrappers.push((ident.to_string(), s));
+            }
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let percent = if self.functions.get() == 0 {
+            0.0
+        } else {
+            100.0 * self.wrappers.len() as f64 / self.functions.get() as f64
+        };
+        writeln!(
+            out,
+            "{} functions ({:.0}% of all functions) only forward their arguments to another function;",
+            self.wrappers.len(),
+            percent
+        )?;
+        for &(ref name, span) in &self.wrappers {
+            writeln!(out, "    `{}`: {}", name, cx.source_map.span_to_string(span))?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(DelegationMetric::new())
+    }
+}
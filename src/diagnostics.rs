@@ -0,0 +1,209 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Counting the warnings which rustc itself emits. The driver lets us replace
+// the destination for diagnostics with any writer, so we install one which
+// passes everything through to stderr (so the user still sees it) and picks
+// out warnings as they go by.
+//
+// Rendered text doesn't reliably say which lint a warning is for, so we have
+// rustc give us its diagnostics as JSON, count them by their level and code,
+// and write the text rendered with them to stderr, so the user sees what they
+// asked for. If they (or Cargo) asked for JSON anyway, we pass it through as
+// it is.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as FmtWrite};
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex};
+
+use rustc_errors::ColorConfig;
+use rustc_serialize::json::Json;
+use rustc_session::config::{ErrorOutputType, HumanReadableErrorType};
+
+use counter::Counter;
+use metrics::{Metric, ReportContext, Stage};
+
+#[derive(Default)]
+pub struct LintCounts {
+    // Warnings per lint name, or per message where there's no lint.
+    counts: BTreeMap<String, Counter>,
+}
+
+impl LintCounts {
+    pub fn report(&self, out: &mut String) -> fmt::Result {
+        let mut total = Counter::new();
+        for count in self.counts.values() {
+            total.add(count.get());
+        }
+        writeln!(out, "rustc emitted {} warnings;", total)?;
+        for (lint, count) in &self.counts {
+            writeln!(out, "    {}: {}", lint, count)?;
+        }
+        Ok(())
+    }
+
+    // Counts a diagnostic, if it's a warning.
+    fn count(&mut self, diagnostic: &Json) {
+        if diagnostic.find("level").and_then(|l| l.as_string()) != Some("warning") {
+            return;
+        }
+        let code = diagnostic.find_path(&["code", "code"]).and_then(|c| c.as_string());
+        let message = diagnostic.find("message").and_then(|m| m.as_string()).unwrap_or("");
+        // The "3 warnings emitted" at the end isn't a warning itself.
+        if code.is_none() && message.ends_with(" emitted") {
+            return;
+        }
+        let key = code.map_or_else(|| normalize(message), |c| c.to_owned());
+        self.counts.entry(key).or_default().increment();
+    }
+}
+
+// Removes the specifics from a message (identifiers are quoted in backticks),
+// so that, e.g., all warnings with the same message but different names are
+// counted together.
+fn normalize(message: &str) -> String {
+    message
+        .split('`')
+        .enumerate()
+        .filter(|&(i, _)| i % 2 == 0)
+        .map(|(_, s)| s)
+        .collect::<Vec<_>>()
+        .join("``")
+}
+
+// Makes rustc emit JSON, if it wasn't going to, rendering the text the user
+// would have seen in it. Returns whether we did, in which case the writer has
+// to print that text. The text is written to our writer rather than a
+// terminal, so we decide about colours here.
+pub fn ask_for_json(error_format: &mut ErrorOutputType) -> bool {
+    let colours = |colour: ColorConfig| match colour {
+        ColorConfig::Auto if io::stderr().is_terminal() => ColorConfig::Always,
+        ColorConfig::Auto => ColorConfig::Never,
+        colour => colour,
+    };
+    let rendered = match *error_format {
+        ErrorOutputType::HumanReadable(kind) => match kind {
+            HumanReadableErrorType::Default(colour) => {
+                HumanReadableErrorType::Default(colours(colour))
+            }
+            HumanReadableErrorType::AnnotateSnippet(colour) => {
+                HumanReadableErrorType::AnnotateSnippet(colours(colour))
+            }
+            HumanReadableErrorType::Short(colour) => HumanReadableErrorType::Short(colours(colour)),
+        },
+        ErrorOutputType::Json { .. } => return false,
+    };
+    *error_format = ErrorOutputType::Json {
+        pretty: false,
+        json_rendered: rendered,
+    };
+    true
+}
+
+// The metric for the counts, which are complete once analysis has finished.
+pub struct LintsMetric {
+    counts: Arc<Mutex<LintCounts>>,
+}
+
+impl LintsMetric {
+    pub fn new(counts: Arc<Mutex<LintCounts>>) -> LintsMetric {
+        LintsMetric { counts }
+    }
+}
+
+impl Metric for LintsMetric {
+    fn id(&self) -> &'static str {
+        "lints"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Analysis]
+    }
+
+    // Diagnostics aren't counted by module.
+    fn per_module(&self) -> bool {
+        false
+    }
+
+    // We count every warning rustc gives, wherever it is.
+    fn whole_crate(&self) -> bool {
+        true
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        self.counts.lock().unwrap().report(out)
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(LintsMetric::new(self.counts.clone()))
+    }
+}
+
+// The writer we give to the compiler in place of stderr. Each line is a
+// diagnostic in JSON.
+pub struct CountingWriter {
+    counts: Arc<Mutex<LintCounts>>,
+    // Whether we print the rendered text rather than the JSON (see
+    // `ask_for_json`).
+    render: bool,
+    line: Vec<u8>,
+}
+
+impl CountingWriter {
+    pub fn new(counts: Arc<Mutex<LintCounts>>, render: bool) -> CountingWriter {
+        CountingWriter {
+            counts,
+            render,
+            line: vec![],
+        }
+    }
+
+    fn process_line(&mut self) -> io::Result<()> {
+        let line = String::from_utf8_lossy(&self.line).into_owned();
+        self.line.clear();
+        let diagnostic = match Json::from_str(&line) {
+            Ok(diagnostic) => diagnostic,
+            // Not from the JSON emitter (e.g., an ICE's message), so we just
+            // pass it on.
+            Err(_) => {
+                if self.render {
+                    writeln!(io::stderr(), "{}", line)?;
+                }
+                return Ok(());
+            }
+        };
+        self.counts.lock().unwrap().count(&diagnostic);
+        if self.render {
+            if let Some(rendered) = diagnostic.find("rendered").and_then(|r| r.as_string()) {
+                io::stderr().write_all(rendered.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.render {
+            io::stderr().write_all(buf)?;
+        }
+        for &b in buf {
+            if b == b'\n' {
+                self.process_line()?;
+            } else {
+                self.line.push(b);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// `stupid doctor`: checks the environment stupid-stats needs and says how to
+// fix what's wrong. Almost every problem people have running stupid-stats is
+// with the toolchain or with Cargo's wiring rather than the tool itself.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use rustc_interface::util;
+use rustc_session::config;
+
+// Runs all the checks, printing the results. Returns whether everything was
+// ok.
+pub fn run(sysroot: Option<&str>) -> bool {
+    let mut ok = true;
+    let mut check = |name: &str, result: Result<String, String>| match result {
+        Ok(detail) => println!("ok:      {} ({})", name, detail),
+        Err(fix) => {
+            println!("problem: {}\n         {}", name, fix);
+            ok = false;
+        }
+    };
+
+    let sysroot = match sysroot {
+        Some(sysroot) => sysroot,
+        None => {
+            let fix = "can't find a sysroot; set `SYSROOT`, put rustc on your `PATH`, or rebuild \
+                       stupid-stats with `cargo +nightly build`";
+            check("sysroot", Err(fix.to_owned()));
+            return false;
+        }
+    };
+    let sysroot = Path::new(sysroot);
+    check("sysroot", if sysroot.is_dir() {
+        Ok(sysroot.display().to_string())
+    } else {
+        Err(format!(
+            "{} does not exist; install the toolchain stupid-stats was built with or rebuild it",
+            sysroot.display()
+        ))
+    });
+
+    check("nightly toolchain", check_version(sysroot));
+
+    let host_lib = sysroot.join("lib/rustlib").join(config::host_triple()).join("lib");
+    check("rustc-dev component", if has_file(&host_lib, "librustc_middle-") {
+        Ok(host_lib.display().to_string())
+    } else {
+        Err("run `rustup component add rustc-dev` for the toolchain".to_owned())
+    });
+
+    let host_bin = sysroot.join("lib/rustlib").join(config::host_triple()).join("bin");
+    check("llvm-tools component", if has_file(&host_bin, "llvm-") {
+        Ok(host_bin.display().to_string())
+    } else {
+        Err("run `rustup component add llvm-tools-preview` for the toolchain".to_owned())
+    });
+
+    check("RUSTC_WRAPPER", check_wrapper());
+
+    ok
+}
+
+// Whether `dir` has a file whose name starts with `prefix`.
+fn has_file(dir: &Path, prefix: &str) -> bool {
+    fs::read_dir(dir).map_or(false, |entries| {
+        entries
+            .filter_map(|e| e.ok())
+            .any(|e| e.file_name().to_string_lossy().starts_with(prefix))
+    })
+}
+
+// The toolchain's rustc must be a nightly, and the same version we were built
+// against.
+fn check_version(sysroot: &Path) -> Result<String, String> {
+    let rustc = sysroot.join("bin").join("rustc");
+    let output = Command::new(&rustc)
+        .arg("--version")
+        .output()
+        .map_err(|e| format!("couldn't run {}: {}", rustc.display(), e))?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+
+    if !version.contains("nightly") && !version.contains("-dev") {
+        return Err(format!(
+            "{} is not a nightly; stupid-stats needs a nightly toolchain, e.g., `rustup toolchain install nightly`",
+            version
+        ));
+    }
+    match util::version_str() {
+        Some(built) if !version.contains(built) => Err(format!(
+            "{} is not the version stupid-stats was built against ({}); rebuild stupid-stats",
+            version, built
+        )),
+        _ => Ok(version),
+    }
+}
+
+// If Cargo is set up to run us as a wrapper, the wrapper must exist.
+fn check_wrapper() -> Result<String, String> {
+    let wrapper = match env::var("RUSTC_WRAPPER") {
+        Ok(wrapper) => wrapper,
+        Err(_) => {
+            return Ok("not set; to use stupid-stats with Cargo, set it to the `stupid` binary".to_owned())
+        }
+    };
+
+    let path = Path::new(&wrapper);
+    let is_us = path.file_stem().map_or(false, |s| s == "stupid");
+    // A bare name is looked up on the `PATH`, so we can't easily check it.
+    let missing = path.components().count() > 1 && !path.is_file();
+    if !is_us {
+        Ok(format!("{}, which is not stupid-stats", wrapper))
+    } else if missing {
+        Err(format!("{} does not exist; build stupid-stats or fix the path", wrapper))
+    } else {
+        Ok(wrapper)
+    }
+}
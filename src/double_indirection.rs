@@ -0,0 +1,153 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Types with redundant double indirection, which cost an extra allocation and
+// pointer chase: a smart pointer around something which is already on the
+// heap (e.g., `Box<Vec<T>>`), a `Vec` of boxes (e.g., `Vec<Box<T>>`, except
+// for trait objects, which need the box), and boxed options inside options.
+// We go by how types are written, so aliases hide these.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+use rustc_span::Span;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+use nested_types::generic_parts;
+
+// How many of the places to list.
+const TOP_PLACES: usize = 10;
+
+const POINTERS: &[&str] = &["Box", "Rc", "Arc"];
+const HEAP: &[&str] = &["Box", "Rc", "Arc", "Vec", "String"];
+
+// If `ty` is double indirection, its shape, e.g., `Box<Vec<_>>`.
+fn double_indirection(ty: &ast::Ty) -> Option<String> {
+    let (outer, args) = generic_parts(ty)?;
+    let arg = args.first()?;
+    if let ast::TyKind::TraitObject(..) = arg.kind {
+        return None;
+    }
+    let (inner, inner_args) = generic_parts(arg)?;
+    let inner_shape = if inner == "String" { inner.clone() } else { format!("{}<_>", inner) };
+
+    if POINTERS.contains(&&*outer) && HEAP.contains(&&*inner) {
+        return Some(format!("{}<{}>", outer, inner_shape));
+    }
+    if outer == "Vec" && inner == "Box" {
+        let boxes_trait_object = inner_args.first().map_or(false, |a| match a.kind {
+            ast::TyKind::TraitObject(..) => true,
+            _ => false,
+        });
+        if !boxes_trait_object {
+            return Some("Vec<Box<_>>".to_owned());
+        }
+    }
+    if outer == "Option" && inner == "Box" {
+        let boxes_option = inner_args
+            .first()
+            .and_then(|a| generic_parts(a))
+            .map_or(false, |(name, _)| name == "Option");
+        if boxes_option {
+            return Some("Option<Box<Option<_>>>".to_owned());
+        }
+    }
+    None
+}
+
+pub struct DoubleIndirectionMetric {
+    // The number of occurrences of each shape.
+    shapes: BTreeMap<String, Counter>,
+    // How many are in struct, enum, or union fields.
+    in_fields: Counter,
+    places: Vec<(String, Span)>,
+    // The spans of the fields of the items we're in.
+    fields: Vec<Span>,
+    // How many fields there were before each item we're in added its own.
+    depths: Vec<usize>,
+}
+
+impl DoubleIndirectionMetric {
+    pub fn new() -> DoubleIndirectionMetric {
+        DoubleIndirectionMetric {
+            shapes: BTreeMap::new(),
+            in_fields: Counter::new(),
+            places: vec![],
+            fields: vec![],
+            depths: vec![],
+        }
+    }
+}
+
+impl Metric for DoubleIndirectionMetric {
+    fn id(&self) -> &'static str {
+        "double-indirection"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        self.depths.push(self.fields.len());
+        match i.kind {
+            ast::ItemKind::Struct(ref data, _) | ast::ItemKind::Union(ref data, _) => {
+                self.fields.extend(data.fields().iter().map(|f| f.span));
+            }
+            ast::ItemKind::Enum(ref def, _) => {
+                for variant in &def.variants {
+                    self.fields.extend(variant.data.fields().iter().map(|f| f.span));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn exit_item(&mut self, _: &ast::Item) {
+        let depth = self.depths.pop().unwrap();
+        self.fields.truncate(depth);
+    }
+
+    fn visit_ty(&mut self, t: &ast::Ty) {
+        if let Some(shape) = double_indirection(t) {
+            self.shapes.entry(shape.clone()).or_insert_with(Counter::new).increment();
+            if self.fields.iter().any(|f| f.contains(t.span)) {
+                self.in_fields.increment();
+            }
+            self.places.push((shape, t.span));
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let total: usize = self.shapes.values().map(|c| c.get()).sum();
+        writeln!(
+            out,
+            "{} types have double indirection ({} of them in fields);",
+            total, self.in_fields
+        )?;
+        for (shape, count) in &self.shapes {
+            writeln!(out, "    `{}`: {}", shape, count)?;
+        }
+        if !self.places.is_empty() {
+            writeln!(out, "Double indirection at:")?;
+            for &(ref shape, span) in self.places.iter().take(TOP_PLACES) {
+                writeln!(out, "    `{}` at {}", shape, cx.source_map.span_to_string(span))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(DoubleIndirectionMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        let total: usize = self.shapes.values().map(|c| c.get()).sum();
+        stats.set_count("double-indirection.types", total);
+        stats.set_count("double-indirection.in_fields", self.in_fields);
+    }
+}
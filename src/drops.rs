@@ -0,0 +1,117 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Explicit calls to `drop` (or `mem::drop`). These are usually there to end a
+// borrow or release a lock before the end of a scope, which is worth knowing
+// about when auditing lock scopes. A drop is 'early' if there are more
+// statements after it in its block.
+
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+// The paths by which `drop` is usually called. We can't resolve names, so a
+// local function called `drop` will be counted too.
+const DROP_PATHS: &[&str] = &["drop", "mem::drop", "std::mem::drop", "core::mem::drop"];
+
+pub struct DropsMetric {
+    // The names of the functions we are in, innermost last.
+    fns: Vec<String>,
+    // Each drop call with the function it is in.
+    drops: Vec<(Span, String)>,
+    // Drop calls which are followed by other statements in their block.
+    early: HashSet<Span>,
+}
+
+impl DropsMetric {
+    pub fn new() -> DropsMetric {
+        DropsMetric {
+            fns: vec![],
+            drops: vec![],
+            early: HashSet::new(),
+        }
+    }
+}
+
+fn is_drop_call(e: &ast::Expr) -> bool {
+    if let ast::ExprKind::Call(ref f, ref args) = e.kind {
+        if let ast::ExprKind::Path(None, ref path) = f.kind {
+            let path = pprust::path_to_string(path);
+            return args.len() == 1 && DROP_PATHS.contains(&path.trim_start_matches("::"));
+        }
+    }
+    false
+}
+
+impl Metric for DropsMetric {
+    fn id(&self) -> &'static str {
+        "drops"
+    }
+
+    // Closures are attributed to the function they're in.
+    fn visit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(_, ident, ..) = fk {
+            self.fns.push(ident.to_string());
+        }
+    }
+
+    fn exit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(..) = fk {
+            self.fns.pop();
+        }
+    }
+
+    fn visit_block(&mut self, b: &ast::Block) {
+        if let Some((_, init)) = b.stmts.split_last() {
+            for stmt in init {
+                match stmt.kind {
+                    ast::StmtKind::Semi(ref e) | ast::StmtKind::Expr(ref e) if is_drop_call(e) => {
+                        self.early.insert(e.span);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        if is_drop_call(e) {
+            let f = self.fns.last().map_or("<top level>".to_owned(), |f| f.clone());
+            self.drops.push((e.span, f));
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "Found {} explicit calls to `drop`, {} of which are early;",
+            self.drops.len(),
+            self.early.len()
+        )?;
+        for &(span, ref f) in &self.drops {
+            let early = if self.early.contains(&span) { " (early)" } else { "" };
+            writeln!(out, "    in `{}` at {}{}", f, cx.source_map.span_to_string(span), early)?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(DropsMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("drops.calls", self.drops.len());
+        stats.set_count("drops.early", self.early.len());
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How many different error types each function converts with `?`. `?` calls
+// `From::from` on the error it propagates, so we find those calls in the HIR
+// (they are marked as coming from `?`) and ask type checking what they convert
+// from and to. A function which converts lots of different errors has a
+// tangled error flow.
+//
+// This walks and type checks every body, so it can be limited to a sample of
+// functions (see `sample`).
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write};
+
+use rustc_hir as hir;
+use rustc_hir::intravisit::{self, NestedVisitorMap, Visitor};
+use rustc_middle::hir::map::Map;
+use rustc_middle::ty::{TyCtxt, TypeckResults};
+use rustc_span::DesugaringKind;
+use rustc_span::symbol::sym;
+
+use metrics::{AnalysisContext, Metric, ReportContext, Stage};
+use sample::{estimate_count, Sample};
+
+// Functions which convert at least this many error types are listed.
+const TANGLED: usize = 3;
+
+pub struct ErrorConversions {
+    // For each function in the sample which uses `?`, the error types it
+    // converts from (errors which are already the right type aren't included).
+    functions: BTreeMap<String, BTreeSet<String>>,
+    sample: Sample,
+    // How many functions there are, and how many of them are in the sample.
+    population: usize,
+    sampled: usize,
+}
+
+impl ErrorConversions {
+    pub fn new() -> ErrorConversions {
+        ErrorConversions {
+            functions: BTreeMap::new(),
+            sample: Sample::all(),
+            population: 0,
+            sampled: 0,
+        }
+    }
+}
+
+impl Metric for ErrorConversions {
+    fn id(&self) -> &'static str {
+        "error-conversions"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Analysis]
+    }
+
+    fn check_analysis<'tcx>(&mut self, cx: &AnalysisContext<'tcx>) {
+        let mut seen = BTreeSet::new();
+        let mut finder = Finder {
+            tcx: cx.tcx,
+            body: None,
+            sample: cx.sample,
+            seen: &mut seen,
+            functions: &mut self.functions,
+        };
+        cx.visit_items(&mut finder);
+        self.sample = cx.sample;
+        self.sampled = seen.iter().filter(|f| cx.sample.includes(f)).count();
+        self.population = seen.len();
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let converting = self.functions.values().filter(|f| !f.is_empty()).count();
+        let total: usize = self.functions.values().map(|f| f.len()).sum();
+        let average = if converting == 0 {
+            0.0
+        } else {
+            total as f64 / converting as f64
+        };
+        if self.sample.is_all() {
+            writeln!(
+                out,
+                "{} functions use `?`, {} of which convert errors ({:.1} error types on average);",
+                self.functions.len(),
+                converting,
+                average
+            )?;
+        } else {
+            let (using, using_margin) =
+                estimate_count(self.functions.len(), self.sampled, self.population);
+            let (converting, converting_margin) =
+                estimate_count(converting, self.sampled, self.population);
+            writeln!(
+                out,
+                "About {:.0} (± {:.0}) functions use `?`, about {:.0} (± {:.0}) of which convert \
+                 errors ({:.1} error types on average);",
+                using, using_margin, converting, converting_margin, average
+            )?;
+            writeln!(
+                out,
+                "    (estimated from {} of {} functions, a {:.0}% sample)",
+                self.sampled,
+                self.population,
+                self.sample.percent()
+            )?;
+        }
+
+        let mut tangled: Vec<_> = self.functions.iter().filter(|f| f.1.len() >= TANGLED).collect();
+        if tangled.is_empty() {
+            return Ok(());
+        }
+        tangled.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+        writeln!(out, "Functions converting {} or more error types:", TANGLED)?;
+        for (name, errors) in tangled {
+            let errors: Vec<_> = errors.iter().map(|e| format!("`{}`", e)).collect();
+            writeln!(out, "    `{}`: {}", name, errors.join(", "))?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(ErrorConversions::new())
+    }
+}
+
+struct Finder<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    // The function whose body (or closure in its body) we're in, and the type
+    // checking results for that body.
+    body: Option<(String, &'tcx TypeckResults<'tcx>)>,
+    sample: Sample,
+    // Every function we've seen, sampled or not.
+    seen: &'a mut BTreeSet<String>,
+    functions: &'a mut BTreeMap<String, BTreeSet<String>>,
+}
+
+impl<'a, 'tcx> Finder<'a, 'tcx> {
+    fn is_from(&self, results: &TypeckResults<'tcx>, callee: &hir::Expr) -> bool {
+        let def_id = match callee.kind {
+            hir::ExprKind::Path(ref qpath) => results.qpath_res(qpath, callee.hir_id).opt_def_id(),
+            _ => None,
+        };
+        let from = self.tcx.get_diagnostic_item(sym::From);
+        def_id.map_or(false, |def_id| from.is_some() && self.tcx.trait_of_item(def_id) == from)
+    }
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for Finder<'a, 'tcx> {
+    type Map = Map<'tcx>;
+
+    fn nested_visit_map(&mut self) -> NestedVisitorMap<Self::Map> {
+        NestedVisitorMap::OnlyBodies(self.tcx.hir())
+    }
+
+    fn visit_nested_body(&mut self, id: hir::BodyId) {
+        // Closures count towards the function they're in.
+        let owner = self.tcx.hir().body_owner_def_id(id).to_def_id();
+        let name = self.tcx.def_path_str(self.tcx.closure_base_def_id(owner));
+        let sampled = self.sample.includes(&name);
+        self.seen.insert(name.clone());
+        if !sampled {
+            return;
+        }
+        let old = self.body.replace((name, self.tcx.typeck_body(id)));
+        let body = self.tcx.hir().body(id);
+        self.visit_body(body);
+        self.body = old;
+    }
+
+    fn visit_expr(&mut self, e: &'tcx hir::Expr<'tcx>) {
+        if let hir::ExprKind::Call(ref callee, ref args) = e.kind {
+            if e.span.desugaring_kind() == Some(DesugaringKind::QuestionMark) && args.len() == 1 {
+                if let Some((ref name, results)) = self.body {
+                    if self.is_from(results, callee) {
+                        let errors = self.functions.entry(name.clone()).or_insert_with(BTreeSet::new);
+                        let from = results.expr_ty(&args[0]);
+                        if from != results.expr_ty(e) {
+                            errors.insert(from.to_string());
+                        }
+                    }
+                }
+            }
+        }
+
+        intravisit::walk_expr(self, e)
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How do public functions report errors? We classify each public function which
+// returns a `Result` by the type of its error. Without name resolution we go by
+// how the type is written, and treat an error type as crate-local if the crate
+// defines a type with that name. The methods of public traits are public
+// functions too; those in impls of traits aren't counted again.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit, FnKind};
+use rustc_ast_pretty::pprust;
+use rustc_span::symbol::Symbol;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+enum Strategy {
+    Anyhow,
+    BoxDynError,
+    String,
+    // A `Result` alias with the error filled in, e.g., `io::Result<T>`.
+    Alias(String),
+    // A named error type; whether it is local is decided when we report.
+    Concrete(Symbol),
+    Other,
+}
+
+pub struct ErrorTypesMetric {
+    // Names of all the structs and enums defined in the crate.
+    local_types: HashSet<Symbol>,
+    strategies: Vec<Strategy>,
+    // Whether each trait we're in is public, innermost last; their methods
+    // are public if they are.
+    traits: Vec<bool>,
+}
+
+impl ErrorTypesMetric {
+    pub fn new() -> ErrorTypesMetric {
+        ErrorTypesMetric {
+            local_types: HashSet::new(),
+            strategies: vec![],
+            traits: vec![],
+        }
+    }
+
+    fn check_sig(&mut self, sig: &ast::FnSig) {
+        if let ast::FnRetTy::Ty(ref ty) = sig.decl.output {
+            if let Some(strategy) = classify_return(ty) {
+                self.strategies.push(strategy);
+            }
+        }
+    }
+}
+
+fn type_args(segment: &ast::PathSegment) -> Vec<&ast::Ty> {
+    match segment.args.as_ref().map(|args| &**args) {
+        Some(&ast::GenericArgs::AngleBracketed(ref args)) => args
+            .args
+            .iter()
+            .filter_map(|arg| match *arg {
+                ast::AngleBracketedArg::Arg(ast::GenericArg::Type(ref ty)) => Some(&**ty),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+// Returns `None` if the function doesn't return a `Result`.
+fn classify_return(ty: &ast::Ty) -> Option<Strategy> {
+    let path = match ty.kind {
+        ast::TyKind::Path(_, ref path) => path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident.name.as_str() != "Result" {
+        return None;
+    }
+
+    let args = type_args(segment);
+    if args.len() == 2 {
+        return Some(classify_error(args[1]));
+    }
+
+    // An alias like `io::Result<T>`; we report it by its path, without the
+    // generic arguments.
+    let alias = path
+        .segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::");
+    if alias == "anyhow::Result" {
+        Some(Strategy::Anyhow)
+    } else {
+        Some(Strategy::Alias(alias))
+    }
+}
+
+fn classify_error(ty: &ast::Ty) -> Strategy {
+    match ty.kind {
+        ast::TyKind::Path(_, ref path) => {
+            let segment = path.segments.last().unwrap();
+            let name = segment.ident.name;
+            let path = pprust::path_to_string(path);
+            if path == "anyhow::Error" {
+                Strategy::Anyhow
+            } else if path == "String" || path == "std::string::String" {
+                Strategy::String
+            } else if name.as_str() == "Box" &&
+                      type_args(segment).first().map_or(false, |t| is_dyn_error(t)) {
+                Strategy::BoxDynError
+            } else {
+                Strategy::Concrete(name)
+            }
+        }
+        ast::TyKind::Rptr(_, ast::MutTy { ref ty, .. }) if pprust::ty_to_string(ty) == "str" => {
+            Strategy::String
+        }
+        _ => Strategy::Other,
+    }
+}
+
+// Is this `dyn Error`, possibly with some extra bounds like `Send + Sync`?
+fn is_dyn_error(ty: &ast::Ty) -> bool {
+    match ty.kind {
+        ast::TyKind::TraitObject(ref bounds, _) => bounds.iter().any(|b| match *b {
+            ast::GenericBound::Trait(ref poly, _) => poly
+                .trait_ref
+                .path
+                .segments
+                .last()
+                .map_or(false, |s| s.ident.name.as_str() == "Error"),
+            _ => false,
+        }),
+        ast::TyKind::Paren(ref ty) => is_dyn_error(ty),
+        _ => false,
+    }
+}
+
+impl Metric for ErrorTypesMetric {
+    fn id(&self) -> &'static str {
+        "error-types"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        match i.kind {
+            ast::ItemKind::Struct(..) | ast::ItemKind::Enum(..) => {
+                self.local_types.insert(i.ident.name);
+            }
+            ast::ItemKind::Fn(box FnKind(_, ref sig, _, _)) if i.vis.kind.is_pub() => {
+                self.check_sig(sig)
+            }
+            ast::ItemKind::Trait(..) => self.traits.push(i.vis.kind.is_pub()),
+            _ => {}
+        }
+    }
+
+    fn exit_item(&mut self, i: &ast::Item) {
+        if let ast::ItemKind::Trait(..) = i.kind {
+            self.traits.pop();
+        }
+    }
+
+    fn visit_assoc_item(&mut self, i: &ast::AssocItem, ctxt: visit::AssocCtxt) {
+        if let ast::AssocItemKind::Fn(box FnKind(_, ref sig, _, _)) = i.kind {
+            let public = match ctxt {
+                visit::AssocCtxt::Trait => self.traits.last() == Some(&true),
+                visit::AssocCtxt::Impl => i.vis.kind.is_pub(),
+            };
+            if public {
+                self.check_sig(sig);
+            }
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        if self.strategies.is_empty() {
+            return Ok(());
+        }
+
+        let mut counts: BTreeMap<String, Counter> = BTreeMap::new();
+        for strategy in &self.strategies {
+            let name = match *strategy {
+                Strategy::Anyhow => "`anyhow::Error`".to_owned(),
+                Strategy::BoxDynError => "`Box<dyn Error>`".to_owned(),
+                Strategy::String => "strings".to_owned(),
+                Strategy::Alias(ref path) => format!("`{}`", path),
+                Strategy::Concrete(name) if self.local_types.contains(&name) => {
+                    "crate-local types".to_owned()
+                }
+                Strategy::Concrete(_) => "other named types".to_owned(),
+                Strategy::Other => "something else".to_owned(),
+            };
+            counts.entry(name).or_default().increment();
+        }
+
+        writeln!(
+            out,
+            "{} public functions return a `Result`; their errors are:",
+            self.strategies.len()
+        )?;
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.get().cmp(&a.1.get()));
+        for (name, count) in counts {
+            writeln!(
+                out,
+                "    {}: {} ({:.0}%)",
+                name,
+                count,
+                100.0 * count.get() as f64 / self.strategies.len() as f64
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(ErrorTypesMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("error-types.result_fns", self.strategies.len());
+    }
+}
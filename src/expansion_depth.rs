@@ -0,0 +1,129 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How deeply macros expand into other macros. This walks the crate *after*
+// expansion; for each node which came from a macro we follow its expansion
+// back to the user's code and count the macros on the way. Deep towers of
+// macros tend to mean confusing error messages and slow builds.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+use rustc_span::Span;
+use rustc_span::hygiene::{ExpnKind, MacroKind};
+
+use metrics::{Metric, ReportContext, Stage};
+
+// How many of the deepest expansions to list.
+const TOP_TOWERS: usize = 5;
+
+// If `span` came from a macro, finds the macro call in the user's code it was
+// expanded from, and the macros which were expanded on the way, outermost
+// first.
+pub fn expansion_chain(span: Span) -> Option<(Span, Vec<String>)> {
+    if !span.from_expansion() {
+        return None;
+    }
+
+    let mut call_site = None;
+    let mut chain = vec![];
+    for data in span.macro_backtrace() {
+        if let ExpnKind::Macro(kind, name) = data.kind {
+            chain.push(match kind {
+                MacroKind::Bang => format!("{}!", name),
+                MacroKind::Attr => format!("#[{}]", name),
+                MacroKind::Derive => format!("#[derive({})]", name),
+            });
+            call_site = Some(data.call_site);
+        }
+    }
+    // The backtrace goes from the innermost expansion outwards.
+    chain.reverse();
+    call_site.map(|call_site| (call_site, chain))
+}
+
+pub struct ExpansionDepthMetric {
+    // For each macro call in the user's code, the deepest chain of macros
+    // expanded from it, outermost first.
+    towers: HashMap<Span, Vec<String>>,
+}
+
+impl ExpansionDepthMetric {
+    pub fn new() -> ExpansionDepthMetric {
+        ExpansionDepthMetric {
+            towers: HashMap::new(),
+        }
+    }
+
+    fn record(&mut self, span: Span) {
+        if let Some((call_site, chain)) = expansion_chain(span) {
+            let tower = self.towers.entry(call_site).or_default();
+            if chain.len() > tower.len() {
+                *tower = chain;
+            }
+        }
+    }
+}
+
+impl Metric for ExpansionDepthMetric {
+    fn id(&self) -> &'static str {
+        "expansion-depth"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Expansion]
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        self.record(i.span);
+    }
+
+    fn visit_stmt(&mut self, s: &ast::Stmt) {
+        self.record(s.span);
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        self.record(e.span);
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let total: usize = self.towers.values().map(|t| t.len()).sum();
+        let average = if self.towers.is_empty() {
+            0.0
+        } else {
+            total as f64 / self.towers.len() as f64
+        };
+        writeln!(
+            out,
+            "Macro calls expand to an average depth of {:.1} macros;",
+            average
+        )?;
+
+        let mut towers: Vec<_> = self.towers.iter().filter(|&(_, t)| t.len() > 1).collect();
+        towers.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.lo().cmp(&b.0.lo())));
+        if towers.is_empty() {
+            return Ok(());
+        }
+        writeln!(out, "The deepest macro expansions are:")?;
+        for (&span, tower) in towers.into_iter().take(TOP_TOWERS) {
+            writeln!(
+                out,
+                "    {} at {}: {}",
+                tower.len(),
+                cx.source_map.span_to_string(span),
+                tower.join(" -> ")
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(ExpansionDepthMetric::new())
+    }
+}
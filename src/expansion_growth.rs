@@ -0,0 +1,184 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How much of a crate is written by hand and how much is generated by macros.
+// We count items, functions, and expressions in the crate before expansion and
+// after it; after expansion, anything whose span came from a macro is
+// generated, and we put it down to the macro in the user's code it was
+// expanded from (e.g., `#[derive(Serialize)]`), so derive authors can see what
+// their macros cost.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_span::Span;
+
+use counter::Counter;
+use expansion_depth::expansion_chain;
+use metrics::{Metric, ReportContext, Stage};
+
+// How many of the macros which generate the most code to list.
+const TOP_MACROS: usize = 10;
+
+#[derive(Clone, Copy, Default)]
+struct Nodes {
+    items: Counter,
+    functions: Counter,
+    exprs: Counter,
+}
+
+impl Nodes {
+    fn total(&self) -> usize {
+        Counter::sum(&[self.items, self.functions, self.exprs]).get()
+    }
+}
+
+struct NodeCounter {
+    // Everything we see, before expansion; what was written by hand, after.
+    hand_written: Nodes,
+    // What was generated, by the macro in the user's code it came from.
+    generated: HashMap<String, Nodes>,
+}
+
+impl NodeCounter {
+    fn count(krate: &ast::Crate) -> NodeCounter {
+        let mut counter = NodeCounter {
+            hand_written: Nodes::default(),
+            generated: HashMap::new(),
+        };
+        visit::walk_crate(&mut counter, krate);
+        counter
+    }
+
+    fn nodes(&mut self, span: Span) -> &mut Nodes {
+        match expansion_chain(span) {
+            Some((_, mut chain)) if !chain.is_empty() => {
+                self.generated.entry(chain.swap_remove(0)).or_default()
+            }
+            _ => &mut self.hand_written,
+        }
+    }
+
+    fn generated_total(&self) -> Nodes {
+        let mut total = Nodes::default();
+        for nodes in self.generated.values() {
+            total.items.add(nodes.items.get());
+            total.functions.add(nodes.functions.get());
+            total.exprs.add(nodes.exprs.get());
+        }
+        total
+    }
+}
+
+impl<'a> visit::Visitor<'a> for NodeCounter {
+    fn visit_item(&mut self, i: &'a ast::Item) {
+        self.nodes(i.span).items.increment();
+        visit::walk_item(self, i)
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind<'a>, s: Span, _: ast::NodeId) {
+        if let visit::FnKind::Fn(..) = fk {
+            self.nodes(s).functions.increment();
+        }
+        visit::walk_fn(self, fk, s)
+    }
+
+    fn visit_expr(&mut self, e: &'a ast::Expr) {
+        self.nodes(e.span).exprs.increment();
+        visit::walk_expr(self, e)
+    }
+}
+
+pub struct ExpansionGrowth {
+    before: Nodes,
+    hand_written: Nodes,
+    generated: Nodes,
+    by_macro: Vec<(String, Nodes)>,
+}
+
+impl ExpansionGrowth {
+    pub fn new() -> ExpansionGrowth {
+        ExpansionGrowth {
+            before: Nodes::default(),
+            hand_written: Nodes::default(),
+            generated: Nodes::default(),
+            by_macro: vec![],
+        }
+    }
+}
+
+impl Metric for ExpansionGrowth {
+    fn id(&self) -> &'static str {
+        "expansion-growth"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Parse, Stage::Expansion]
+    }
+
+    // We count the whole crate at once, before and after expansion.
+    fn per_module(&self) -> bool {
+        false
+    }
+
+    fn check_crate(&mut self, krate: &ast::Crate, stage: Stage) {
+        let counter = NodeCounter::count(krate);
+        if stage == Stage::Parse {
+            self.before = counter.hand_written;
+            return;
+        }
+        self.hand_written = counter.hand_written;
+        self.generated = counter.generated_total();
+        self.by_macro = counter.generated.into_iter().collect();
+        self.by_macro.sort_by(|a, b| b.1.total().cmp(&a.1.total()).then_with(|| a.0.cmp(&b.0)));
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let percent = |generated: Counter, hand_written: Counter| {
+            let total = Counter::sum(&[generated, hand_written]).get();
+            if total == 0 { 0.0 } else { 100.0 * generated.get() as f64 / total as f64 }
+        };
+        writeln!(out, "Before and after macro expansion:")?;
+        for &(what, before, hand_written, generated) in &[
+            ("items", self.before.items, self.hand_written.items, self.generated.items),
+            (
+                "functions",
+                self.before.functions,
+                self.hand_written.functions,
+                self.generated.functions,
+            ),
+            ("expressions", self.before.exprs, self.hand_written.exprs, self.generated.exprs),
+        ] {
+            writeln!(
+                out,
+                "    {}: {} before, {} after, of which {} ({:.1}%) are generated",
+                what,
+                before,
+                Counter::sum(&[hand_written, generated]),
+                generated,
+                percent(generated, hand_written)
+            )?;
+        }
+        if !self.by_macro.is_empty() {
+            writeln!(out, "The macros which generate the most code are:")?;
+            for &(ref name, nodes) in self.by_macro.iter().take(TOP_MACROS) {
+                writeln!(
+                    out,
+                    "    `{}`: {} items, {} functions, {} expressions",
+                    name, nodes.items, nodes.functions, nodes.exprs
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(ExpansionGrowth::new())
+    }
+}
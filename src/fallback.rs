@@ -0,0 +1,121 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// When rustc can't parse a crate (usually because it uses syntax which is
+// newer than our nightly), we don't get any stats from the compiler at all. As
+// a fallback, we parse each `.rs` file under the crate root's directory on its
+// own, skip the ones we can't parse, and collect the metrics which only need
+// the parsed AST from the rest. Without the whole crate, there is nothing which
+// needs expansion or analysis, so the metrics which do are left out.
+
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rustc_session::parse::ParseSess;
+
+use crate_stats::CrateStats;
+use messages::{Lang, Message};
+use metrics::{Metric, ReportContext, Stage};
+use StupidVisitor;
+
+// Every `.rs` file in `dir` and its subdirectories, in order.
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            rust_files(&path, files)?;
+        } else if path.extension().map_or(false, |e| e == "rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Parses the files of the crate with root `root` one at a time and collects
+// `metrics` from those which parse. Returns the summary stats and the report,
+// which lists the files we skipped.
+pub fn analyze_files(
+    root: &Path,
+    crate_name: &str,
+    metrics: Vec<Box<dyn Metric>>,
+    lang: Lang,
+) -> (CrateStats, String) {
+    let mut files = vec![];
+    if let Some(dir) = root.parent() {
+        // A directory we can't read just has no files in it.
+        let _ = rust_files(dir, &mut files);
+    }
+
+    let metrics = metrics
+        .into_iter()
+        .filter(|m| m.stages().contains(&Stage::Parse) && !m.stages().contains(&Stage::Expansion))
+        .collect();
+    rustc_span::with_default_session_globals(|| {
+        // Nothing we parse is printed: errors mean we skip the file.
+        let sess = ParseSess::with_silent_emitter(None);
+        let mut visitor = StupidVisitor::new(metrics);
+        let mut skipped = vec![];
+        for file in &files {
+            let errors = sess.span_diagnostic.err_count();
+            match rustc_parse::parse_crate_from_file(file, &sess) {
+                Ok(ref krate) if sess.span_diagnostic.err_count() == errors => {
+                    visitor.walk(krate, Stage::Parse)
+                }
+                Ok(_) => skipped.push(file),
+                Err(mut e) => {
+                    e.cancel();
+                    skipped.push(file);
+                }
+            }
+        }
+
+        visitor.check_source(sess.source_map());
+
+        let (sampled, population) = visitor.sampled_functions();
+        let cx = ReportContext {
+            lang,
+            source_map: sess.source_map(),
+            sampled,
+            population,
+        };
+        let mut report = String::new();
+        write_report(&visitor, crate_name, &files, &skipped, &cx, &mut report).unwrap();
+        (visitor.crate_stats(crate_name), report)
+    })
+}
+
+fn write_report(
+    visitor: &StupidVisitor,
+    crate_name: &str,
+    files: &[PathBuf],
+    skipped: &[&PathBuf],
+    cx: &ReportContext,
+    out: &mut String,
+) -> fmt::Result {
+    let lang = cx.lang;
+    writeln!(out, "{}", lang.render(Message::InCrate(crate_name)))?;
+    writeln!(
+        out,
+        "(rustc couldn't parse the crate, so these stats are from {} of its {} files, parsed one at a time.)",
+        files.len() - skipped.len(),
+        files.len()
+    )?;
+    visitor.report(cx, out)?;
+    if !skipped.is_empty() {
+        writeln!(out, "Files we couldn't parse:")?;
+        for file in skipped {
+            writeln!(out, "    {}", file.display())?;
+        }
+    }
+    Ok(())
+}
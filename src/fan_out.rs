@@ -0,0 +1,202 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Fan-out is the number of distinct functions called from a function, a rough
+// measure of how coupled it is to the rest of the code.
+//
+// Once type checking has finished, callees are identified by what they
+// resolve to, so `foo::bar(..)` and `bar(..)` are the same callee and method
+// calls are told apart by their type. Calls which don't resolve to an item
+// (e.g., of closures in local variables) go by how they are written. If we
+// don't get that far, the AST is all we have and every callee goes by how it
+// is written, with all method calls with the same name treated as a single
+// callee.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{self, Write};
+use std::mem;
+
+use rustc_ast::{ast, visit};
+use rustc_ast_pretty::pprust;
+use rustc_hir as hir;
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::DefId;
+use rustc_hir::intravisit::{self, NestedVisitorMap, Visitor};
+use rustc_middle::hir::map::Map;
+use rustc_middle::ty::{TyCtxt, TypeckResults};
+use rustc_span::Span;
+
+use crate_stats::CrateStats;
+use metrics::{AnalysisContext, Metric, ReportContext, Stage};
+
+pub struct FanOutMetric {
+    // The callees of the functions we are currently inside, innermost last.
+    stack: Vec<HashSet<String>>,
+    // The name and fan-out of every function we've finished walking.
+    fan_outs: Vec<(String, usize)>,
+}
+
+impl FanOutMetric {
+    pub fn new() -> FanOutMetric {
+        FanOutMetric {
+            stack: vec![],
+            fan_outs: vec![],
+        }
+    }
+}
+
+impl Metric for FanOutMetric {
+    fn id(&self) -> &'static str {
+        "fan-out"
+    }
+
+    // The `Analysis` results replace the `Parse` ones when we have them.
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Parse, Stage::Analysis]
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        // Closures are counted as part of the function they are written in.
+        if let visit::FnKind::Fn(_, _, _, _, Some(_)) = fk {
+            self.stack.push(HashSet::new());
+        }
+    }
+
+    fn exit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(_, ident, _, _, Some(_)) = fk {
+            let callees = self.stack.pop().unwrap();
+            self.fan_outs.push((ident.to_string(), callees.len()));
+        }
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        let callee = match e.kind {
+            ast::ExprKind::Call(ref f, _) => match f.kind {
+                ast::ExprKind::Path(_, ref path) => Some(pprust::path_to_string(path)),
+                _ => None,
+            },
+            ast::ExprKind::MethodCall(ref segment, ..) => Some(format!(".{}", segment.ident)),
+            _ => None,
+        };
+        if let (Some(callee), Some(callees)) = (callee, self.stack.last_mut()) {
+            callees.insert(callee);
+        }
+    }
+
+    fn check_analysis<'tcx>(&mut self, cx: &AnalysisContext<'tcx>) {
+        let mut finder = Finder {
+            tcx: cx.tcx,
+            results: None,
+            function: None,
+            callees: BTreeMap::new(),
+        };
+        cx.visit_items(&mut finder);
+        self.fan_outs = finder
+            .callees
+            .iter()
+            .map(|(&def_id, callees)| (cx.tcx.item_name(def_id).to_string(), callees.len()))
+            .collect();
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        if self.fan_outs.is_empty() {
+            return Ok(());
+        }
+
+        let total: f64 = self.fan_outs.iter().map(|&(_, n)| n as f64).sum();
+        let average = total / self.fan_outs.len() as f64;
+        let &(ref name, max) = self.fan_outs.iter().max_by_key(|&&(_, n)| n).unwrap();
+        writeln!(
+            out,
+            "Functions call {:.1} distinct functions on average (at most {}, in `{}`);",
+            average, max, name
+        )
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(FanOutMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        let max = self.fan_outs.iter().map(|&(_, n)| n).max().unwrap_or(0);
+        stats.set_count("fan-out.max", max);
+    }
+}
+
+#[derive(PartialEq, Eq, Hash)]
+enum Callee {
+    Resolved(DefId),
+    Written(String),
+}
+
+struct Finder<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    // The type checking results for the body we're in, and the function it
+    // belongs to (closures belong to the function they're in).
+    results: Option<&'tcx TypeckResults<'tcx>>,
+    function: Option<DefId>,
+    // The callees of every function with a body.
+    callees: BTreeMap<DefId, HashSet<Callee>>,
+}
+
+impl<'tcx> Visitor<'tcx> for Finder<'tcx> {
+    type Map = Map<'tcx>;
+
+    fn nested_visit_map(&mut self) -> NestedVisitorMap<Self::Map> {
+        NestedVisitorMap::OnlyBodies(self.tcx.hir())
+    }
+
+    fn visit_nested_body(&mut self, id: hir::BodyId) {
+        let owner = self.tcx.hir().body_owner_def_id(id).to_def_id();
+        let function = self.tcx.closure_base_def_id(owner);
+        let function = match self.tcx.def_kind(function) {
+            DefKind::Fn | DefKind::AssocFn => Some(function),
+            _ => None,
+        };
+        if let Some(function) = function {
+            self.callees.entry(function).or_insert_with(HashSet::new);
+        }
+        let old_results = self.results.replace(self.tcx.typeck_body(id));
+        let old_function = mem::replace(&mut self.function, function);
+        let body = self.tcx.hir().body(id);
+        self.visit_body(body);
+        self.results = old_results;
+        self.function = old_function;
+    }
+
+    fn visit_expr(&mut self, e: &'tcx hir::Expr<'tcx>) {
+        let callee = match (self.results, &e.kind) {
+            (Some(results), hir::ExprKind::Call(ref f, _)) => match f.kind {
+                hir::ExprKind::Path(ref qpath) => match results.qpath_res(qpath, f.hir_id) {
+                    Res::Def(_, def_id) => Some(Callee::Resolved(def_id)),
+                    _ => match *qpath {
+                        hir::QPath::Resolved(_, path) => {
+                            let segments: Vec<_> =
+                                path.segments.iter().map(|s| s.ident.to_string()).collect();
+                            Some(Callee::Written(segments.join("::")))
+                        }
+                        _ => None,
+                    },
+                },
+                _ => None,
+            },
+            (Some(results), hir::ExprKind::MethodCall(segment, ..)) => {
+                match results.type_dependent_def_id(e.hir_id) {
+                    Some(def_id) => Some(Callee::Resolved(def_id)),
+                    None => Some(Callee::Written(format!(".{}", segment.ident))),
+                }
+            }
+            _ => None,
+        };
+        if let (Some(callee), Some(function)) = (callee, self.function) {
+            self.callees.entry(function).or_insert_with(HashSet::new).insert(callee);
+        }
+
+        intravisit::walk_expr(self, e)
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// A map of how Cargo features are used: for each feature mentioned in a
+// `#[cfg(feature = "...")]`, how many items and lines of code it gates, and
+// which features declared in Cargo.toml never appear in the code at all.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fmt::{self, Write};
+use std::fs;
+use std::path::Path;
+
+use rustc_ast::{ast, visit};
+use rustc_span::Span;
+
+use cfgs::cfg_features;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+use spans::line_count;
+
+pub struct FeaturesMetric {
+    // Spans of the items gated on each feature.
+    gated: BTreeMap<String, Vec<Span>>,
+}
+
+impl FeaturesMetric {
+    pub fn new() -> FeaturesMetric {
+        FeaturesMetric {
+            gated: BTreeMap::new(),
+        }
+    }
+
+    fn record(&mut self, attrs: &[ast::Attribute], span: Span) {
+        for feature in cfg_features(attrs) {
+            self.gated.entry(feature.to_string()).or_default().push(span);
+        }
+    }
+}
+
+// Reads the features declared in a Cargo.toml, including the implicit features
+// for optional dependencies. This is not a real TOML parser, but it copes with
+// the way manifests are usually written.
+fn declared_features(manifest: &Path) -> Option<BTreeSet<String>> {
+    let text = fs::read_to_string(manifest).ok()?;
+    let mut features = BTreeSet::new();
+    let mut section = String::new();
+    for line in text.lines().map(|l| l.trim()) {
+        if line.starts_with('[') {
+            section = line.trim_matches(|c| c == '[' || c == ']').trim().to_owned();
+            continue;
+        }
+        let key = match line.find('=') {
+            Some(i) => line[..i].trim().trim_matches('"'),
+            None => continue,
+        };
+        let is_optional = line.contains("optional") && line.contains("true");
+        if section == "features" {
+            features.insert(key.to_owned());
+        } else if section == "dependencies" && is_optional {
+            features.insert(key.to_owned());
+        } else if section.starts_with("dependencies.") && key == "optional" && is_optional {
+            features.insert(section["dependencies.".len()..].to_owned());
+        }
+    }
+    Some(features)
+}
+
+impl Metric for FeaturesMetric {
+    fn id(&self) -> &'static str {
+        "features"
+    }
+
+    // Whether a feature is used at all is a question about the whole crate.
+    fn per_module(&self) -> bool {
+        false
+    }
+
+    // Features are declared for the crate.
+    fn whole_crate(&self) -> bool {
+        true
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        self.record(&i.attrs, i.span);
+    }
+
+    fn visit_assoc_item(&mut self, i: &ast::AssocItem, _: visit::AssocCtxt) {
+        self.record(&i.attrs, i.span);
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        if !self.gated.is_empty() {
+            writeln!(out, "Features used in `cfg`s:")?;
+        }
+        for (feature, spans) in &self.gated {
+            let lines: usize = spans.iter().map(|&s| line_count(cx.source_map, s)).sum();
+            writeln!(out, "    `{}` gates {} items, {} lines", feature, spans.len(), lines)?;
+        }
+
+        // When we're run by Cargo, we can find the manifest and check for
+        // features which are declared but never used.
+        if let Some(declared) = env::var_os("CARGO_MANIFEST_DIR")
+            .and_then(|dir| declared_features(&Path::new(&dir).join("Cargo.toml")))
+        {
+            let unused: Vec<_> = declared
+                .iter()
+                .filter(|f| *f != "default" && !self.gated.contains_key(*f))
+                .map(|f| format!("`{}`", f))
+                .collect();
+            if !unused.is_empty() {
+                writeln!(out, "Features never referenced in code: {};", unused.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(FeaturesMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("features.features", self.gated.len());
+        stats.set_count("features.gated_items", self.gated.values().map(|s| s.len()).sum::<usize>());
+    }
+}
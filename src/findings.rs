@@ -0,0 +1,107 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Findings are the places where a crate breaks one of our rules (e.g., a
+// function which is too complex), as opposed to the stats, which describe the
+// crate as a whole. Every rule reports its findings here, so they can be
+// output (and acted on) the same way whichever rule they come from.
+
+use std::fmt::{self, Write};
+
+use rustc_span::Span;
+use rustc_span::source_map::SourceMap;
+
+// Every rule's id, so that output formats can list the rules which passed.
+pub const RULES: &[&str] = &[
+    "assertion-density",
+    "cognitive-complexity",
+    "cyclomatic-complexity",
+    "nested-signature-type",
+    "overexposed-visibility",
+    "spawned-capture",
+    "test-without-assertions",
+    "todo",
+    "too-many-arguments",
+    "truncating-cast",
+    "uncommented-complex-function",
+    "unsafe-block",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Note,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Finding {
+    // Identifies the rule, e.g., `cognitive-complexity`.
+    pub id: &'static str,
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+    // The measurement which broke the rule, if there is one.
+    pub value: Option<usize>,
+}
+
+pub struct Findings {
+    findings: Vec<Finding>,
+}
+
+impl Findings {
+    pub fn new() -> Findings {
+        Findings {
+            findings: vec![],
+        }
+    }
+
+    pub fn push(&mut self, finding: Finding) {
+        self.findings.push(finding);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Finding> {
+        self.findings.iter()
+    }
+
+    pub fn report(&self, source_map: &SourceMap, out: &mut String) -> fmt::Result {
+        if self.findings.is_empty() {
+            return Ok(());
+        }
+
+        // Worst first, then grouped by rule, with the biggest numbers first.
+        let mut findings: Vec<_> = self.findings.iter().collect();
+        findings.sort_by(|a, b| {
+            b.severity
+                .cmp(&a.severity)
+                .then(a.id.cmp(b.id))
+                .then(b.value.cmp(&a.value))
+        });
+
+        writeln!(out, "Findings:")?;
+        for f in findings {
+            writeln!(
+                out,
+                "    {}[{}]: {} at {}",
+                f.severity,
+                f.id,
+                f.message,
+                source_map.span_to_string(f.span)
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How long functions are, in lines of source from the signature to the closing
+// brace (comments and blank lines included). Functions and methods with
+// bodies are counted; closures are part of the function they're in.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::visit;
+use rustc_span::Span;
+
+use metrics::{Metric, ReportContext};
+use spans::line_count;
+
+// How many of the longest functions to list.
+const TOP_FUNCTIONS: usize = 10;
+
+pub struct FnLengthMetric {
+    // The name and span of every function.
+    functions: Vec<(String, Span)>,
+}
+
+impl FnLengthMetric {
+    pub fn new() -> FnLengthMetric {
+        FnLengthMetric {
+            functions: vec![],
+        }
+    }
+}
+
+impl Metric for FnLengthMetric {
+    fn id(&self) -> &'static str {
+        "fn-length"
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, s: Span) {
+        if let visit::FnKind::Fn(_, ident, _, _, Some(_)) = fk {
+            self.functions.push((ident.to_string(), s));
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        if self.functions.is_empty() {
+            return Ok(());
+        }
+
+        let mut lengths: Vec<_> = self
+            .functions
+            .iter()
+            .map(|&(ref name, span)| (line_count(cx.source_map, span), name, span))
+            .collect();
+        lengths.sort_by(|a, b| b.0.cmp(&a.0));
+
+        // As floats, so that the total can't overflow.
+        let total: f64 = lengths.iter().map(|l| l.0 as f64).sum();
+        writeln!(
+            out,
+            "Functions are {:.1} lines long on average (median {}, at most {});",
+            total / lengths.len() as f64,
+            lengths[lengths.len() / 2].0,
+            lengths[0].0
+        )?;
+        writeln!(out, "The longest functions are:")?;
+        for &(lines, name, span) in lengths.iter().take(TOP_FUNCTIONS) {
+            let start = cx.source_map.lookup_char_pos(span.lo());
+            writeln!(
+                out,
+                "    `{}`: {} lines at {}:{}",
+                name,
+                lines,
+                start.file.name,
+                start.line
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(FnLengthMetric::new())
+    }
+}
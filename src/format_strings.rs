@@ -0,0 +1,182 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Statistics about format strings in `format!`, `println!`, `write!`, etc.
+// We're looking at the AST before expansion, so the macros' arguments are still
+// just tokens; we split them on top-level commas and pick apart the format
+// string ourselves.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+use rustc_ast::token::{self, TokenKind};
+use rustc_ast::tokenstream::TokenTree;
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+// Format strings longer than this many characters are reported.
+const LONG_FORMAT_STRING: usize = 100;
+
+pub struct FormatStringsMetric {
+    // Number of formatting macros with a literal format string.
+    calls: Counter,
+    // Total number of placeholders in those format strings.
+    placeholders: Counter,
+    // Calls where the number of `{}`s doesn't match the number of arguments.
+    mismatched: Vec<Span>,
+    // Calls with very long format strings.
+    long: Vec<Span>,
+}
+
+impl FormatStringsMetric {
+    pub fn new() -> FormatStringsMetric {
+        FormatStringsMetric {
+            calls: Counter::new(),
+            placeholders: Counter::new(),
+            mismatched: vec![],
+            long: vec![],
+        }
+    }
+}
+
+// Splits a macro's tokens into arguments at top-level commas. Commas inside
+// brackets are part of a `Delimited` tree, so we don't see them here.
+fn split_args(mac: &ast::MacCall) -> Vec<Vec<TokenTree>> {
+    let mut args = vec![vec![]];
+    if let ast::MacArgs::Delimited(_, _, ref tts) = *mac.args {
+        for tt in tts.trees() {
+            match tt {
+                TokenTree::Token(token::Token { kind: TokenKind::Comma, .. }) => args.push(vec![]),
+                tt => args.last_mut().unwrap().push(tt),
+            }
+        }
+    }
+    // Allow for a trailing comma.
+    if args.last().map_or(false, |a| a.is_empty()) {
+        args.pop();
+    }
+    args
+}
+
+// Counts the placeholders in a format string, returning (total, implicit),
+// where implicit placeholders are those like `{}` and `{:?}` which take the
+// next argument, rather than naming one.
+fn count_placeholders(s: &str) -> (usize, usize) {
+    let mut total = 0;
+    let mut implicit = 0;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+            }
+            '{' => {
+                total += 1;
+                let mut inner = String::new();
+                while let Some(c) = chars.next() {
+                    if c == '}' {
+                        break;
+                    }
+                    inner.push(c);
+                }
+                if inner.split(':').next().unwrap().trim().is_empty() {
+                    implicit += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    (total, implicit)
+}
+
+impl Metric for FormatStringsMetric {
+    fn id(&self) -> &'static str {
+        "format-strings"
+    }
+
+    fn visit_mac_call(&mut self, mac: &ast::MacCall) {
+        let name = pprust::path_to_string(&mac.path);
+        // `write!` and `writeln!` take the destination before the format string.
+        let skip = match &*name {
+            "format" | "format_args" | "print" | "println" | "eprint" | "eprintln" => Some(0),
+            "write" | "writeln" => Some(1),
+            _ => None,
+        };
+
+        if let Some(skip) = skip {
+            let args = split_args(mac);
+            let format_string = match args.get(skip).map(|a| &a[..]) {
+                Some(&[TokenTree::Token(token::Token {
+                    kind: TokenKind::Literal(token::Lit { kind: token::LitKind::Str, symbol, .. }),
+                    span,
+                })]) |
+                Some(&[TokenTree::Token(token::Token {
+                    kind: TokenKind::Literal(token::Lit { kind: token::LitKind::StrRaw(_), symbol, .. }),
+                    span,
+                })]) => Some((symbol, span)),
+                _ => None,
+            };
+
+            if let Some((symbol, span)) = format_string {
+                let (total, implicit) = count_placeholders(&symbol.as_str());
+                let arg_count = args.len() - skip - 1;
+                self.calls.increment();
+                self.placeholders.add(total);
+                // Only a guess: with positional or named placeholders there is
+                // no simple relationship between placeholders and arguments.
+                if total == implicit && total != arg_count {
+                    self.mismatched.push(mac.span());
+                }
+                if symbol.as_str().chars().count() > LONG_FORMAT_STRING {
+                    self.long.push(span);
+                }
+            }
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        if self.calls.get() == 0 {
+            return Ok(());
+        }
+
+        writeln!(
+            out,
+            "Found {} calls to formatting macros with {:.1} placeholders on average;",
+            self.calls,
+            self.placeholders.get() as f64 / self.calls.get() as f64
+        )?;
+        writeln!(out, "{} look like they have the wrong number of arguments;", self.mismatched.len())?;
+        for &span in &self.mismatched {
+            writeln!(out, "    {}", cx.source_map.span_to_string(span))?;
+        }
+        writeln!(
+            out,
+            "{} have format strings longer than {} characters;",
+            self.long.len(),
+            LONG_FORMAT_STRING
+        )?;
+        for &span in &self.long {
+            writeln!(out, "    {}", cx.source_map.span_to_string(span))?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(FormatStringsMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("format-strings.calls", self.calls);
+        stats.set_count("format-strings.mismatched", self.mismatched.len());
+        stats.set_count("format-strings.long", self.long.len());
+    }
+}
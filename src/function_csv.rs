@@ -0,0 +1,247 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// A CSV file with a row for every function with a body, for loading into a
+// spreadsheet or a dataframe: its name (with the type or trait, for methods),
+// its module, how many arguments it takes, how many lines it is, its
+// cyclomatic complexity, and whether it's unsafe (an `unsafe fn`, or one with
+// an `unsafe` block in it). If the path ends in `.ndjson` or `.jsonl`, each
+// row is a JSON object on its own line instead.
+//
+// Rows are written out as we go rather than collected. Usually they go into a
+// buffer which is written to the file at the end; with `--low-memory`, they go
+// straight to the file, so the memory we need doesn't grow with the number of
+// functions (but a failed build can leave part of a file behind).
+//
+// The rows can also be streamed, as NDJSON, to a sink (see `connect`), e.g., the
+// ingestion endpoint of a data warehouse, without going through a file at all.
+// A sink is anything we can `Write` to, so adding another kind is a matter of
+// connecting to it.
+
+use std::io::{self, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+use rustc_ast::{ast, visit};
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+use rustc_span::source_map::SourceMap;
+
+use crate_stats::escape;
+use cyclomatic::cyclomatic_complexity;
+use spans::line_count;
+
+// Whether a body has an unsafe block in it, not counting nested items.
+struct UnsafeFinder {
+    found: bool,
+}
+
+impl<'a> visit::Visitor<'a> for UnsafeFinder {
+    fn visit_block(&mut self, b: &'a ast::Block) {
+        if let ast::BlockCheckMode::Unsafe(ast::UnsafeSource::UserProvided) = b.rules {
+            self.found = true;
+        }
+        visit::walk_block(self, b)
+    }
+
+    fn visit_item(&mut self, _: &'a ast::Item) {}
+}
+
+struct Row {
+    name: String,
+    module: String,
+    args: usize,
+    lines: usize,
+    complexity: usize,
+    is_unsafe: bool,
+}
+
+struct FunctionCsvVisitor<'a, W: Write> {
+    source_map: &'a SourceMap,
+    // The path of the module we're in.
+    module: Vec<String>,
+    // The type or trait whose methods we're in, if we are.
+    owner: Option<String>,
+    ndjson: bool,
+    out: W,
+    // The first error writing a row; we don't write any more after one.
+    error: Option<io::Error>,
+}
+
+// Quotes a field if it needs it, e.g., `<T as Into<U>>::into` doesn't, but
+// `HashMap<K, V>::new` does.
+fn field(s: &str) -> String {
+    if s.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+// Connects to the sink at `address`: `tcp://<host>:<port>`, or
+// `unix://<path>` for a UNIX socket.
+pub fn connect(address: &str) -> io::Result<Box<dyn Write>> {
+    if let Some(host) = address.strip_prefix("tcp://") {
+        return Ok(Box::new(TcpStream::connect(host)?));
+    }
+    #[cfg(unix)]
+    {
+        if let Some(path) = address.strip_prefix("unix://") {
+            return Ok(Box::new(UnixStream::connect(path)?));
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("`{}` isn't a `tcp://` or `unix://` address", address),
+    ))
+}
+
+// Writes a row for each function in `krate` to `out`, as CSV or as NDJSON.
+// Returns `out` once they're all written.
+pub fn write_rows<W: Write>(
+    source_map: &SourceMap,
+    crate_name: &str,
+    krate: &ast::Crate,
+    ndjson: bool,
+    mut out: W,
+) -> io::Result<W> {
+    if !ndjson {
+        writeln!(out, "name,module,args,lines,complexity,unsafe")?;
+    }
+    let mut visitor = FunctionCsvVisitor {
+        source_map,
+        module: vec![crate_name.to_owned()],
+        owner: None,
+        ndjson,
+        out,
+        error: None,
+    };
+    visit::walk_crate(&mut visitor, krate);
+    match visitor.error {
+        Some(e) => Err(e),
+        None => {
+            visitor.out.flush()?;
+            Ok(visitor.out)
+        }
+    }
+}
+
+impl<'a, W: Write> FunctionCsvVisitor<'a, W> {
+    fn write_row(&mut self, row: Row) {
+        if self.error.is_some() {
+            return;
+        }
+        let result = if self.ndjson {
+            writeln!(
+                self.out,
+                "{{\"name\": \"{}\", \"module\": \"{}\", \"args\": {}, \"lines\": {}, \
+                 \"complexity\": {}, \"unsafe\": {}}}",
+                escape(&row.name),
+                escape(&row.module),
+                row.args,
+                row.lines,
+                row.complexity,
+                row.is_unsafe
+            )
+        } else {
+            writeln!(
+                self.out,
+                "{},{},{},{},{},{}",
+                field(&row.name),
+                field(&row.module),
+                row.args,
+                row.lines,
+                row.complexity,
+                row.is_unsafe
+            )
+        };
+        self.error = result.err();
+    }
+
+    fn with_owner<F: FnOnce(&mut Self)>(&mut self, owner: String, f: F) {
+        let outer = self.owner.replace(owner);
+        f(self);
+        self.owner = outer;
+    }
+}
+
+impl<'a, 'ast, W: Write> visit::Visitor<'ast> for FunctionCsvVisitor<'a, W> {
+    fn visit_item(&mut self, i: &'ast ast::Item) {
+        match i.kind {
+            ast::ItemKind::Mod(..) => {
+                self.module.push(i.ident.to_string());
+                visit::walk_item(self, i);
+                self.module.pop();
+            }
+            ast::ItemKind::Impl(box ast::ImplKind { ref self_ty, .. }) => {
+                self.with_owner(pprust::ty_to_string(self_ty), |v| visit::walk_item(v, i))
+            }
+            ast::ItemKind::Trait(..) => {
+                self.with_owner(i.ident.to_string(), |v| visit::walk_item(v, i))
+            }
+            // Functions in functions belong to the module, not a type.
+            ast::ItemKind::Fn(..) => {
+                let outer = self.owner.take();
+                visit::walk_item(self, i);
+                self.owner = outer;
+            }
+            _ => visit::walk_item(self, i),
+        }
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind<'ast>, s: Span, _: ast::NodeId) {
+        if let visit::FnKind::Fn(_, ident, sig, _, Some(body)) = fk {
+            let mut finder = UnsafeFinder { found: false };
+            visit::walk_block(&mut finder, body);
+            let is_unsafe = finder.found || matches!(sig.header.unsafety, ast::Unsafe::Yes(_));
+            self.write_row(Row {
+                name: match self.owner {
+                    Some(ref owner) => format!("{}::{}", owner, ident),
+                    None => ident.to_string(),
+                },
+                module: self.module.join("::"),
+                args: sig.decl.inputs.len(),
+                lines: line_count(self.source_map, s),
+                complexity: cyclomatic_complexity(body),
+                is_unsafe,
+            });
+        }
+
+        visit::walk_fn(self, fk, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn tcp_sink() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("tcp://{}", listener.local_addr().unwrap());
+        let received = thread::spawn(move || {
+            let mut received = String::new();
+            listener.accept().unwrap().0.read_to_string(&mut received).unwrap();
+            received
+        });
+        let mut sink = connect(&address).unwrap();
+        writeln!(sink, "{{\"name\": \"main\"}}").unwrap();
+        drop(sink);
+        assert_eq!(received.join().unwrap(), "{\"name\": \"main\"}\n");
+    }
+
+    #[test]
+    fn unknown_sinks() {
+        let error = connect("http://localhost:9000").err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Quality gates for CI: conditions on the summary stats, like `max_args>7` or
+// `println_count>0`, which make `stupid` exit with an error if they hold. The
+// names are the keys in the JSON summary (see `CrateStats`), plus `functions`
+// and the metrics' counts, e.g., `unsafe.blocks`.
+
+use crate_stats::CrateStats;
+
+#[derive(Clone, Copy)]
+enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+// Longest first, so that `>=` isn't read as `>`.
+const OPS: &[(&str, Op)] = &[
+    (">=", Op::Ge),
+    ("<=", Op::Le),
+    ("==", Op::Eq),
+    ("!=", Op::Ne),
+    (">", Op::Gt),
+    ("<", Op::Lt),
+];
+
+// The value of the summary stat called `name`, as a float so that they can all
+// be compared the same way.
+pub fn stat(stats: &CrateStats, name: &str) -> Option<f64> {
+    Some(match name {
+        "println_count" => stats.println_count.get() as f64,
+        "functions" => stats.arg_counts.total().get() as f64,
+        "common_args" => stats.common_args as f64,
+        "common_args_percent" => stats.common_args_percent,
+        "four_or_more_args_percent" => stats.four_or_more_args_percent,
+        "mean_args" => stats.mean_args,
+        "median_args" => stats.median_args as f64,
+        "p90_args" => stats.p90_args as f64,
+        "max_args" => stats.max_args as f64,
+        "generated_lines_excluded" => stats.generated_lines_excluded as f64,
+        _ => return stats.counts.get(name).map(|c| c.get() as f64),
+    })
+}
+
+// Checks `stats` against each condition, e.g., `max_args>7`. Returns a
+// message for each condition which holds, or which we can't make sense of (a
+// gate which silently does nothing would be worse than one which fails).
+pub fn check(conditions: &[String], stats: &CrateStats) -> Vec<String> {
+    let mut failures = vec![];
+    for condition in conditions {
+        let parsed = OPS.iter().find_map(|&(symbol, op)| {
+            condition.find(symbol).map(|i| {
+                let name = condition[..i].trim();
+                let limit = condition[i + symbol.len()..].trim().parse::<f64>().ok();
+                (name, op, limit)
+            })
+        });
+        let (name, op, limit) = match parsed {
+            Some((name, op, Some(limit))) => (name, op, limit),
+            _ => {
+                failures.push(format!("can't understand the condition `{}`", condition));
+                continue;
+            }
+        };
+        let value = match stat(stats, name) {
+            Some(value) => value,
+            None => {
+                failures.push(format!("unknown stat `{}` in `{}`", name, condition));
+                continue;
+            }
+        };
+        let holds = match op {
+            Op::Gt => value > limit,
+            Op::Ge => value >= limit,
+            Op::Lt => value < limit,
+            Op::Le => value <= limit,
+            Op::Eq => (value - limit).abs() < f64::EPSILON,
+            Op::Ne => (value - limit).abs() >= f64::EPSILON,
+        };
+        if holds {
+            failures.push(format!(
+                "`{}` in crate `{}`: {} is {}",
+                condition, stats.crate_name, name, value
+            ));
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> CrateStats {
+        let mut stats = CrateStats {
+            crate_name: "foo".to_owned(),
+            max_args: 7,
+            mean_args: 2.5,
+            ..CrateStats::default()
+        };
+        stats.println_count.add(2);
+        stats.arg_counts.record_many(1, 3);
+        stats.set_count("unsafe.blocks", 1);
+        stats
+    }
+
+    fn check_one(condition: &str) -> Vec<String> {
+        check(&[condition.to_owned()], &stats())
+    }
+
+    #[test]
+    fn comparisons() {
+        assert!(check_one("max_args>7").is_empty());
+        assert_eq!(check_one("max_args>=7"), vec!["`max_args>=7` in crate `foo`: max_args is 7"]);
+        assert_eq!(check_one("max_args < 8").len(), 1);
+        assert!(check_one("max_args<=6").is_empty());
+        assert_eq!(check_one("println_count==2").len(), 1);
+        assert!(check_one("println_count!=2").is_empty());
+        assert_eq!(check_one("mean_args>2.25").len(), 1);
+        assert_eq!(check_one("functions>2").len(), 1);
+        assert_eq!(
+            check_one("unsafe.blocks>0"),
+            vec!["`unsafe.blocks>0` in crate `foo`: unsafe.blocks is 1"]
+        );
+    }
+
+    #[test]
+    fn bad_conditions_fail() {
+        assert_eq!(check_one("max_args"), vec!["can't understand the condition `max_args`"]);
+        assert_eq!(
+            check_one("max_args>lots"),
+            vec!["can't understand the condition `max_args>lots`"]
+        );
+        assert_eq!(
+            check_one("panics.todo>0"),
+            vec!["unknown stat `panics.todo` in `panics.todo>0`"]
+        );
+    }
+}
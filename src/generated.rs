@@ -0,0 +1,90 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Generated code (from build scripts, bindgen, protobuf compilers, etc.) says
+// nothing about how the crate's authors write Rust, and there can be a lot of
+// it, so by default we leave it out (`--include-generated` puts it back). Like
+// `--module-filter`, we do that by making a copy of the crate without it.
+//
+// A file is generated if it says so near the top (`@generated`, or bindgen's
+// header), if it is in the build script's `OUT_DIR`, or if its name says so.
+
+use std::env;
+use std::path::Path;
+
+use rustc_ast::ast;
+use rustc_ast::ptr::P;
+use rustc_span::source_map::SourceMap;
+use rustc_span::{FileName, SourceFile};
+
+// How far into a file to look for a marker.
+const HEADER_LINES: usize = 10;
+
+const MARKERS: &[&str] = &["@generated", "automatically generated by rust-bindgen"];
+
+fn is_generated(file: &SourceFile) -> bool {
+    if let FileName::Real(ref name) = file.name {
+        let path = name.local_path();
+        // Cargo sets `OUT_DIR` for crates with build scripts.
+        if let Some(out_dir) = env::var_os("OUT_DIR") {
+            if path.starts_with(Path::new(&out_dir)) {
+                return true;
+            }
+        }
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if file_name.ends_with("_generated.rs") || file_name.ends_with(".generated.rs") {
+            return true;
+        }
+    }
+    file.src.as_ref().map_or(false, |src| {
+        src.lines().take(HEADER_LINES).any(|line| MARKERS.iter().any(|m| line.contains(m)))
+    })
+}
+
+// The number of lines of generated code the compiler has read for the crate.
+pub fn generated_lines(source_map: &SourceMap) -> usize {
+    source_map
+        .files()
+        .iter()
+        .filter(|f| is_generated(f))
+        .map(|f| f.count_lines())
+        .sum()
+}
+
+// A copy of `krate` without the items from generated files, or `None` if
+// there aren't any.
+pub fn without_generated(krate: &ast::Crate, source_map: &SourceMap) -> Option<ast::Crate> {
+    if !source_map.files().iter().any(|f| is_generated(f)) {
+        return None;
+    }
+    Some(ast::Crate {
+        items: filter_items(&krate.items, source_map),
+        ..krate.clone()
+    })
+}
+
+fn filter_items(items: &[P<ast::Item>], source_map: &SourceMap) -> Vec<P<ast::Item>> {
+    items
+        .iter()
+        .filter(|i| !is_generated(&source_map.lookup_char_pos(i.span.lo()).file))
+        .map(|i| match i.kind {
+            // A module declared in a hand-written file can still be in a
+            // generated one.
+            ast::ItemKind::Mod(unsafety, ast::ModKind::Loaded(ref items, inline, span)) => {
+                P(ast::Item {
+                    kind: ast::ItemKind::Mod(
+                        unsafety,
+                        ast::ModKind::Loaded(filter_items(items, source_map), inline, span),
+                    ),
+                    ..(**i).clone()
+                })
+            }
+            _ => i.clone(),
+        })
+        .collect()
+}
@@ -0,0 +1,202 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// An estimate of what each generic function costs in monomorphization: the
+// number of distinct instantiations the crate asks for (the type arguments at
+// each call site, according to type checking) times the function's size in
+// lines. Instantiations from other crates, and ones reached through another
+// generic function with different arguments, aren't counted, so this is a
+// ranking rather than a measurement.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{self, Write};
+
+use rustc_hir as hir;
+use rustc_hir::def::Res;
+use rustc_hir::def_id::DefId;
+use rustc_hir::intravisit::{self, NestedVisitorMap, Visitor};
+use rustc_middle::hir::map::Map;
+use rustc_middle::ty::{TyCtxt, TypeckResults};
+
+use html::write_treemap;
+use metrics::{AnalysisContext, Metric, ReportContext, Stage};
+use spans::line_count;
+
+// How many of the most costly functions to list.
+const TOP_FUNCTIONS: usize = 10;
+// How many generic functions to show in the treemap.
+const TREEMAP_FUNCTIONS: usize = 50;
+
+pub struct GenericFn {
+    pub name: String,
+    pub type_params: usize,
+    pub instantiations: usize,
+    pub lines: usize,
+}
+
+impl GenericFn {
+    pub fn cost(&self) -> usize {
+        self.instantiations * self.lines
+    }
+}
+
+pub struct GenericBloat {
+    // Most costly first.
+    pub functions: Vec<GenericFn>,
+}
+
+impl GenericBloat {
+    pub fn new() -> GenericBloat {
+        GenericBloat { functions: vec![] }
+    }
+}
+
+impl Metric for GenericBloat {
+    fn id(&self) -> &'static str {
+        "generic-bloat"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Analysis]
+    }
+
+    fn check_analysis<'tcx>(&mut self, cx: &AnalysisContext<'tcx>) {
+        let tcx = cx.tcx;
+        let mut finder = Finder {
+            tcx,
+            results: None,
+            instantiations: HashMap::new(),
+        };
+        tcx.hir().krate().visit_all_item_likes(&mut finder.as_deep_visitor());
+
+        // `def_span` is just a function's signature, we want its body too.
+        let lines = |def_id: DefId| {
+            let hir_id = tcx.hir().local_def_id_to_hir_id(def_id.expect_local());
+            line_count(tcx.sess.source_map(), tcx.hir().span_with_body(hir_id))
+        };
+        let mut functions: Vec<_> = finder
+            .instantiations
+            .iter()
+            .filter(|&(&def_id, _)| cx.includes(def_id.expect_local()))
+            .map(|(&def_id, substs)| GenericFn {
+                name: tcx.def_path_str(def_id),
+                type_params: tcx.generics_of(def_id).own_counts().types,
+                instantiations: substs.len(),
+                lines: lines(def_id),
+            })
+            .collect();
+        functions.sort_by(|a, b| b.cost().cmp(&a.cost()).then(a.name.cmp(&b.name)));
+        self.functions = functions;
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let total: usize = self.functions.iter().map(|f| f.cost()).sum();
+        writeln!(
+            out,
+            "{} generic functions are instantiated, with an estimated monomorphization cost of {} lines;",
+            self.functions.len(),
+            total
+        )?;
+        for f in self.functions.iter().take(TOP_FUNCTIONS) {
+            writeln!(
+                out,
+                "    `{}`: {} type params, {} instantiations of {} lines, cost {}",
+                f.name,
+                f.type_params,
+                f.instantiations,
+                f.lines,
+                f.cost()
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(GenericBloat::new())
+    }
+
+    // A treemap of the most costly functions.
+    fn write_html(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "<h2>Monomorphization cost</h2>")?;
+        writeln!(
+            out,
+            "<p>Each box is a generic function, sized by its estimated cost: \
+             instantiations times lines.</p>"
+        )?;
+        let boxes: Vec<_> = self
+            .functions
+            .iter()
+            .filter(|f| f.cost() > 0)
+            .take(TREEMAP_FUNCTIONS)
+            .map(|f| {
+                let title = format!(
+                    "{}: {} instantiations of {} lines",
+                    f.name, f.instantiations, f.lines
+                );
+                (f.name.clone(), title, f.cost())
+            })
+            .collect();
+        writeln!(out, "<div class=\"treemap\">")?;
+        write_treemap(&boxes, (0.0, 0.0, 100.0, 100.0), out)?;
+        writeln!(out, "</div>")?;
+        Ok(())
+    }
+}
+
+struct Finder<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    // The type checking results for the body we're in.
+    results: Option<&'tcx TypeckResults<'tcx>>,
+    // For each local generic function, the distinct type arguments it's
+    // called with.
+    instantiations: HashMap<DefId, BTreeSet<String>>,
+}
+
+impl<'tcx> Finder<'tcx> {
+    fn record(&mut self, results: &TypeckResults<'tcx>, def_id: DefId, hir_id: hir::HirId) {
+        if !def_id.is_local() || self.tcx.generics_of(def_id).own_counts().types == 0 {
+            return;
+        }
+        let substs = results.node_substs(hir_id);
+        self.instantiations.entry(def_id).or_insert_with(BTreeSet::new).insert(substs.to_string());
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for Finder<'tcx> {
+    type Map = Map<'tcx>;
+
+    fn nested_visit_map(&mut self) -> NestedVisitorMap<Self::Map> {
+        NestedVisitorMap::OnlyBodies(self.tcx.hir())
+    }
+
+    fn visit_nested_body(&mut self, id: hir::BodyId) {
+        let old = self.results.replace(self.tcx.typeck_body(id));
+        let body = self.tcx.hir().body(id);
+        self.visit_body(body);
+        self.results = old;
+    }
+
+    fn visit_expr(&mut self, e: &'tcx hir::Expr<'tcx>) {
+        if let Some(results) = self.results {
+            match e.kind {
+                hir::ExprKind::MethodCall(..) => {
+                    if let Some(def_id) = results.type_dependent_def_id(e.hir_id) {
+                        self.record(results, def_id, e.hir_id);
+                    }
+                }
+                hir::ExprKind::Path(ref qpath) => {
+                    if let Res::Def(_, def_id) = results.qpath_res(qpath, e.hir_id) {
+                        self.record(results, def_id, e.hir_id);
+                    }
+                }
+                _ => {}
+            }
+        }
+        intravisit::walk_expr(self, e)
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How much each function depends on global state: the crate's statics
+// (including `lazy_static!` ones and statics holding a `OnceLock` or similar)
+// and the process environment. A function's coupling is the number of
+// distinct globals it uses, counting the environment as one; functions which
+// use a lot are hard to test.
+//
+// We find statics by name, so a local with the same name as a static counts as
+// a use of it.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_ast::token::{self, TokenKind};
+use rustc_ast::tokenstream::TokenTree;
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+use rustc_span::symbol::kw;
+
+use metrics::{Metric, ReportContext, Stage};
+
+// How many of the most coupled functions to list.
+const TOP_FUNCTIONS: usize = 10;
+
+const ENV_FUNCTIONS: &[&str] = &["var", "var_os", "vars", "vars_os", "set_var", "remove_var"];
+
+// Collects the names of the crate's statics.
+struct StaticsCollector {
+    statics: HashSet<String>,
+}
+
+impl<'a> visit::Visitor<'a> for StaticsCollector {
+    fn visit_item(&mut self, i: &'a ast::Item) {
+        if let ast::ItemKind::Static(..) = i.kind {
+            self.statics.insert(i.ident.to_string());
+        }
+        visit::walk_item(self, i)
+    }
+
+    // `lazy_static! { static ref NAME: Type = ...; }`
+    fn visit_mac_call(&mut self, mac: &'a ast::MacCall) {
+        if mac.path.segments.last().map_or(false, |s| &*s.ident.as_str() == "lazy_static") {
+            if let ast::MacArgs::Delimited(_, _, ref tts) = *mac.args {
+                let mut after_ref = false;
+                for tt in tts.trees() {
+                    if let TokenTree::Token(token::Token { kind: TokenKind::Ident(name, _), .. }) = tt {
+                        if after_ref {
+                            self.statics.insert(name.to_string());
+                        }
+                        after_ref = name == kw::Ref;
+                    } else {
+                        after_ref = false;
+                    }
+                }
+            }
+        }
+        visit::walk_mac(self, mac)
+    }
+}
+
+struct Function {
+    name: String,
+    span: Span,
+    // The globals it reads and writes.
+    reads: BTreeSet<String>,
+    writes: BTreeSet<String>,
+}
+
+pub struct GlobalStateMetric {
+    statics: HashSet<String>,
+    // The functions we're in, innermost last.
+    stack: Vec<Function>,
+    functions: Vec<Function>,
+}
+
+impl GlobalStateMetric {
+    pub fn new() -> GlobalStateMetric {
+        GlobalStateMetric {
+            statics: HashSet::new(),
+            stack: vec![],
+            functions: vec![],
+        }
+    }
+
+    // The global a path names, if it names one.
+    fn global(&self, path: &ast::Path) -> Option<String> {
+        let last = path.segments.last()?.ident.to_string();
+        if self.statics.contains(&last) {
+            return Some(last);
+        }
+        let path = pprust::path_to_string(path);
+        let path = path.trim_start_matches("::").trim_start_matches("std::");
+        if path.starts_with("env::") && ENV_FUNCTIONS.contains(&&path["env::".len()..]) {
+            return Some("the environment".to_owned());
+        }
+        None
+    }
+}
+
+impl Metric for GlobalStateMetric {
+    fn id(&self) -> &'static str {
+        "global-state"
+    }
+
+    // We need all the crate's statics.
+    fn per_module(&self) -> bool {
+        false
+    }
+
+    fn check_crate(&mut self, krate: &ast::Crate, _: Stage) {
+        let mut collector = StaticsCollector {
+            statics: HashSet::new(),
+        };
+        visit::walk_crate(&mut collector, krate);
+        self.statics = collector.statics;
+    }
+
+    // Closures count towards the function they're in.
+    fn visit_fn(&mut self, fk: visit::FnKind, s: Span) {
+        if let visit::FnKind::Fn(_, ident, ..) = fk {
+            self.stack.push(Function {
+                name: ident.to_string(),
+                span: s,
+                reads: BTreeSet::new(),
+                writes: BTreeSet::new(),
+            });
+        }
+    }
+
+    fn exit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(..) = fk {
+            let f = self.stack.pop().unwrap();
+            self.functions.push(f);
+        }
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        match e.kind {
+            ast::ExprKind::Assign(ref lhs, ..) | ast::ExprKind::AssignOp(_, ref lhs, _) => {
+                if let ast::ExprKind::Path(None, ref path) = lhs.kind {
+                    if let Some(global) = self.global(path) {
+                        if let Some(f) = self.stack.last_mut() {
+                            f.writes.insert(global);
+                        }
+                    }
+                }
+            }
+            ast::ExprKind::Path(None, ref path) => {
+                if let Some(global) = self.global(path) {
+                    if let Some(f) = self.stack.last_mut() {
+                        f.reads.insert(global);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let coupled: Vec<_> = self
+            .functions
+            .iter()
+            .filter(|f| !f.reads.is_empty() || !f.writes.is_empty())
+            .collect();
+        let percent = if self.functions.is_empty() {
+            0.0
+        } else {
+            100.0 * coupled.len() as f64 / self.functions.len() as f64
+        };
+        writeln!(
+            out,
+            "{} functions ({:.0}% of all functions) use global state;",
+            coupled.len(),
+            percent
+        )?;
+
+        let mut coupled: Vec<_> = coupled
+            .into_iter()
+            .map(|f| (f.reads.union(&f.writes).count(), f))
+            .collect();
+        coupled.sort_by(|a, b| b.0.cmp(&a.0));
+        for (coupling, f) in coupled.into_iter().take(TOP_FUNCTIONS) {
+            let writes: Vec<_> = f.writes.iter().map(|w| format!("`{}`", w)).collect();
+            let writes = if writes.is_empty() {
+                String::new()
+            } else {
+                format!(", writes {}", writes.join(", "))
+            };
+            writeln!(
+                out,
+                "    `{}` uses {} globals{}: {}",
+                f.name,
+                coupling,
+                writes,
+                cx.source_map.span_to_string(f.span)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(GlobalStateMetric::new())
+    }
+}
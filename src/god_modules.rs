@@ -0,0 +1,242 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// A heuristic for finding "god modules", modules which do too much. We score
+// each module on its size in lines, its number of items, its fan-in (how many
+// other modules import from it), and the total cognitive complexity of its
+// functions. Each measure is scaled relative to the biggest module in the crate
+// and the score is their average, out of 100.
+//
+// This walks the crate after expansion, when out-of-line modules have been
+// loaded (god modules are rarely inline), skipping the code from macro
+// expansions, which nobody wrote in the module.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_span::Span;
+use rustc_span::symbol::kw;
+
+use cognitive::cognitive_complexity;
+use counter::Counter;
+use metrics::{Metric, ReportContext, Stage};
+use spans::line_count;
+
+// The number of modules to list in the report.
+const TOP_MODULES: usize = 5;
+
+#[derive(Default)]
+struct Module {
+    span: Option<Span>,
+    items: Counter,
+    complexity: Counter,
+}
+
+pub struct GodModulesMetric {
+    // The path of the module we're in, e.g., `["crate", "foo", "bar"]`.
+    path: Vec<String>,
+    modules: BTreeMap<String, Module>,
+    // The module each `use` is in and the path it imports.
+    uses: Vec<(String, Vec<String>)>,
+}
+
+impl GodModulesMetric {
+    pub fn new() -> GodModulesMetric {
+        GodModulesMetric {
+            path: vec!["crate".to_owned()],
+            modules: BTreeMap::new(),
+            uses: vec![],
+        }
+    }
+
+    fn current(&mut self) -> &mut Module {
+        let path = self.path.join("::");
+        self.modules.entry(path).or_default()
+    }
+
+    fn record_uses(&mut self, prefix: &[String], tree: &ast::UseTree) {
+        let mut path = prefix.to_owned();
+        path.extend(
+            tree.prefix
+                .segments
+                .iter()
+                .filter(|s| s.ident.name != kw::PathRoot)
+                .map(|s| s.ident.to_string()),
+        );
+        match tree.kind {
+            ast::UseTreeKind::Nested(ref trees) => {
+                for &(ref tree, _) in trees {
+                    self.record_uses(&path, tree);
+                }
+            }
+            _ => {
+                let from = self.path.join("::");
+                self.uses.push((from, path));
+            }
+        }
+    }
+
+    // Works out which module each `use` imports from (the longest prefix of the
+    // imported path which names a module of this crate), and so which modules
+    // import from each module.
+    fn importers(&self) -> HashMap<&str, HashSet<&str>> {
+        let mut importers: HashMap<_, HashSet<_>> = HashMap::new();
+        for &(ref from, ref path) in &self.uses {
+            let mut absolute: Vec<String> = from.split("::").map(|s| s.to_owned()).collect();
+            let mut segments = &path[..];
+            match segments.first().map(|s| &**s) {
+                Some("crate") => {
+                    absolute.truncate(1);
+                    segments = &segments[1..];
+                }
+                Some("self") => segments = &segments[1..],
+                Some("super") => {
+                    while segments.first().map_or(false, |s| s == "super") {
+                        absolute.pop();
+                        segments = &segments[1..];
+                    }
+                }
+                // In the 2015 edition, paths in `use`s start at the crate root.
+                _ => absolute.truncate(1),
+            }
+            absolute.extend(segments.iter().cloned());
+
+            let target = (1..=absolute.len())
+                .rev()
+                .filter_map(|n| self.modules.get_key_value(&absolute[..n].join("::")))
+                .next();
+            if let Some((target, _)) = target {
+                if target != from {
+                    importers.entry(&**target).or_default().insert(&**from);
+                }
+            }
+        }
+        importers
+    }
+}
+
+fn scale(value: usize, max: usize) -> f64 {
+    if max == 0 {
+        0.0
+    } else {
+        value as f64 / max as f64
+    }
+}
+
+impl Metric for GodModulesMetric {
+    fn id(&self) -> &'static str {
+        "god-modules"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Expansion]
+    }
+
+    // Modules are compared with each other.
+    fn per_module(&self) -> bool {
+        false
+    }
+
+    fn check_crate(&mut self, krate: &ast::Crate, _: Stage) {
+        self.modules.insert("crate".to_owned(), Module {
+            span: Some(krate.span),
+            ..Module::default()
+        });
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        let generated = i.span.from_expansion();
+        if !generated {
+            self.current().items.increment();
+        }
+        match i.kind {
+            ast::ItemKind::Mod(_, ref kind) => {
+                self.path.push(i.ident.to_string());
+                // A file module's lines are its file's, not its `mod foo;`.
+                let span = match *kind {
+                    ast::ModKind::Loaded(_, ast::Inline::No, inner) => inner,
+                    _ => i.span,
+                };
+                self.current().span = Some(span);
+            }
+            ast::ItemKind::Use(ref tree) if !generated => self.record_uses(&[], tree),
+            _ => {}
+        }
+    }
+
+    fn exit_item(&mut self, i: &ast::Item) {
+        if let ast::ItemKind::Mod(..) = i.kind {
+            self.path.pop();
+        }
+    }
+
+    // Functions and methods anywhere in a module count towards its complexity.
+    fn visit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(_, ident, _, _, Some(body)) = fk {
+            if body.span.from_expansion() {
+                return;
+            }
+            self.current().complexity.add(cognitive_complexity(ident, body));
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        if self.modules.len() < 2 {
+            return Ok(());
+        }
+
+        let lines: Vec<usize> = self
+            .modules
+            .values()
+            .map(|m| m.span.map_or(0, |s| line_count(cx.source_map, s)))
+            .collect();
+        let max_lines = lines.iter().cloned().max().unwrap_or(0);
+        let max_items = self.modules.values().map(|m| m.items.get()).max().unwrap_or(0);
+        let importers = self.importers();
+        let fan_in = |name: &str| importers.get(name).map_or(0, |i| i.len());
+        let max_fan_in = self.modules.keys().map(|name| fan_in(name)).max().unwrap_or(0);
+        let max_complexity = self.modules.values().map(|m| m.complexity.get()).max().unwrap_or(0);
+
+        let mut scores: Vec<_> = self
+            .modules
+            .iter()
+            .zip(lines)
+            .map(|((name, m), lines)| {
+                let score = (scale(lines, max_lines) +
+                             scale(m.items.get(), max_items) +
+                             scale(fan_in(name), max_fan_in) +
+                             scale(m.complexity.get(), max_complexity)) * 25.0;
+                (score, name, lines, m)
+            })
+            .collect();
+        scores.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+
+        writeln!(
+            out,
+            "Modules most likely to be god modules (score: lines, items, fan-in, complexity):"
+        )?;
+        for &(score, name, lines, m) in scores.iter().take(TOP_MODULES) {
+            writeln!(
+                out,
+                "    `{}` {:.0}: {}, {}, {}, {}",
+                name,
+                score,
+                lines,
+                m.items,
+                fan_in(name),
+                m.complexity
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(GodModulesMetric::new())
+    }
+}
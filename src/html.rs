@@ -0,0 +1,143 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The summary stats as a self-contained HTML page (`--stats-output`), to attach
+// to a PR or keep as a CI artifact. Charts are bars drawn with CSS, so there is
+// nothing to load.
+
+use std::fmt::{self, Write};
+
+use crate_stats::CrateStats;
+use junit::escape;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+td, th { padding: 0.2em 0.8em; text-align: left; }
+.bar { background: #4a7ebb; height: 1em; }
+.treemap { position: relative; width: 60em; height: 30em; }
+.treemap div { position: absolute; box-sizing: border-box; overflow: hidden;
+    border: 1px solid white; background: #4a7ebb; color: white; font-size: small; }";
+
+pub fn write_html(
+    stats: &CrateStats,
+    modules: &[(String, CrateStats)],
+    sections: &str,
+    out: &mut String,
+) -> fmt::Result {
+    let name = escape(&stats.crate_name);
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html><head><meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>stupid-stats: {}</title>", name)?;
+    writeln!(out, "<style>\n{}\n</style></head><body>", STYLE)?;
+    writeln!(out, "<h1>{}</h1>", name)?;
+
+    writeln!(out, "<h2>Arguments</h2>")?;
+    writeln!(
+        out,
+        "<p>The most common number of arguments is {} ({:.0}% of all functions); \
+         {:.0}% of functions have four or more.</p>",
+        stats.common_args, stats.common_args_percent, stats.four_or_more_args_percent
+    )?;
+    let bars: Vec<_> = stats.arg_counts.iter().map(|(args, count)| (args.to_string(), count)).collect();
+    write_bars(&bars, "Arguments", "Functions", out)?;
+
+    writeln!(out, "<h2>Formatting macros</h2>")?;
+    let bars: Vec<_> =
+        stats.format_macros.iter().map(|(name, count)| (format!("{}!", name), count.get())).collect();
+    write_bars(&bars, "Macro", "Uses", out)?;
+
+    if !modules.is_empty() {
+        writeln!(out, "<h2>By module</h2>")?;
+        writeln!(out, "<table>")?;
+        writeln!(
+            out,
+            "<tr><th>Module</th><th>Functions</th><th>Most common arguments</th>\
+             <th>Four or more arguments</th><th><code>println!</code></th></tr>"
+        )?;
+        for &(ref path, ref stats) in modules {
+            writeln!(
+                out,
+                "<tr><td><code>{}</code></td><td>{}</td><td>{}</td><td>{:.0}%</td><td>{}</td></tr>",
+                escape(path),
+                stats.arg_counts.total(),
+                stats.common_args,
+                stats.four_or_more_args_percent,
+                stats.println_count
+            )?;
+        }
+        writeln!(out, "</table>")?;
+    }
+
+    // Sections for the metrics which have one, e.g., `generic-bloat`'s treemap.
+    out.push_str(sections);
+
+    writeln!(out, "</body></html>")
+}
+
+// Lays out `boxes` (name, title, and size, biggest first) in the rectangle
+// `(x, y, width, height)`, in percentages of the treemap. We split the boxes
+// into two groups of about the same total size and split the rectangle the
+// same way along its longer side, until each box has a rectangle of its own.
+pub fn write_treemap(
+    boxes: &[(String, String, usize)],
+    (x, y, width, height): (f64, f64, f64, f64),
+    out: &mut String,
+) -> fmt::Result {
+    if boxes.len() == 1 {
+        let (ref name, ref title, _) = boxes[0];
+        return writeln!(
+            out,
+            "<div style=\"left: {:.2}%; top: {:.2}%; width: {:.2}%; height: {:.2}%\" \
+             title=\"{}\">{}</div>",
+            x,
+            y,
+            width,
+            height,
+            escape(title),
+            escape(name)
+        );
+    }
+    if boxes.is_empty() {
+        return Ok(());
+    }
+
+    let total: usize = boxes.iter().map(|b| b.2).sum();
+    let mut split = 1;
+    let mut first = boxes[0].2;
+    while split < boxes.len() - 1 && 2 * first < total {
+        first += boxes[split].2;
+        split += 1;
+    }
+    let share = first as f64 / total as f64;
+    // The treemap is twice as wide as it is tall.
+    let (a, b) = if 2.0 * width >= height {
+        ((x, y, width * share, height), (x + width * share, y, width * (1.0 - share), height))
+    } else {
+        ((x, y, width, height * share), (x, y + height * share, width, height * (1.0 - share)))
+    };
+    write_treemap(&boxes[..split], a, out)?;
+    write_treemap(&boxes[split..], b, out)
+}
+
+// A table with a bar for each row, scaled to the biggest count.
+fn write_bars(bars: &[(String, usize)], label: &str, unit: &str, out: &mut String) -> fmt::Result {
+    let max = bars.iter().map(|b| b.1).max().unwrap_or(0).max(1);
+    writeln!(out, "<table>")?;
+    writeln!(out, "<tr><th>{}</th><th>{}</th><th></th></tr>", label, unit)?;
+    for &(ref name, count) in bars {
+        writeln!(
+            out,
+            "<tr><td>{}</td><td>{}</td><td style=\"width: 30em\">\
+             <div class=\"bar\" style=\"width: {:.1}%\"></div></td></tr>",
+            escape(name),
+            count,
+            100.0 * count as f64 / max as f64
+        )?;
+    }
+    writeln!(out, "</table>")
+}
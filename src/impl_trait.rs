@@ -0,0 +1,115 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// `impl Trait` in argument position versus named type parameters. The two are
+// mostly interchangeable, so which a crate prefers is a matter of style.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit, FnKind};
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+// Counts `impl Trait` types, wherever they are nested in a type.
+struct ImplTraitCounter {
+    count: usize,
+}
+
+impl<'a> visit::Visitor<'a> for ImplTraitCounter {
+    fn visit_ty(&mut self, ty: &'a ast::Ty) {
+        if let ast::TyKind::ImplTrait(..) = ty.kind {
+            self.count += 1;
+        }
+        visit::walk_ty(self, ty)
+    }
+}
+
+pub struct ImplTraitMetric {
+    impl_trait_args: Counter,
+    type_params: Counter,
+    // Functions using only `impl Trait`, only named parameters, or both.
+    impl_trait_only: Counter,
+    type_params_only: Counter,
+    mixed: Counter,
+}
+
+impl ImplTraitMetric {
+    pub fn new() -> ImplTraitMetric {
+        ImplTraitMetric {
+            impl_trait_args: Counter::new(),
+            type_params: Counter::new(),
+            impl_trait_only: Counter::new(),
+            type_params_only: Counter::new(),
+            mixed: Counter::new(),
+        }
+    }
+
+    fn check_fn(&mut self, sig: &ast::FnSig, generics: &ast::Generics) {
+        let mut counter = ImplTraitCounter { count: 0 };
+        for param in &sig.decl.inputs {
+            visit::Visitor::visit_ty(&mut counter, &param.ty);
+        }
+        let impl_traits = counter.count;
+        let type_params = generics
+            .params
+            .iter()
+            .filter(|p| matches!(p.kind, ast::GenericParamKind::Type { .. }))
+            .count();
+
+        self.impl_trait_args.add(impl_traits);
+        self.type_params.add(type_params);
+        match (impl_traits > 0, type_params > 0) {
+            (true, false) => self.impl_trait_only.increment(),
+            (false, true) => self.type_params_only.increment(),
+            (true, true) => self.mixed.increment(),
+            (false, false) => {}
+        }
+    }
+}
+
+impl Metric for ImplTraitMetric {
+    fn id(&self) -> &'static str {
+        "impl-trait"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        if let ast::ItemKind::Fn(box FnKind(_, ref sig, ref generics, _)) = i.kind {
+            self.check_fn(sig, generics);
+        }
+    }
+
+    fn visit_assoc_item(&mut self, i: &ast::AssocItem, _: visit::AssocCtxt) {
+        if let ast::AssocItemKind::Fn(box FnKind(_, ref sig, ref generics, _)) = i.kind {
+            self.check_fn(sig, generics);
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "Found {} `impl Trait` arguments and {} named type parameters on functions;",
+            self.impl_trait_args, self.type_params
+        )?;
+        writeln!(
+            out,
+            "{} generic functions use only `impl Trait`, {} only named type parameters, and {} both;",
+            self.impl_trait_only, self.type_params_only, self.mixed
+        )
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(ImplTraitMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("impl-trait.impl_trait_args", self.impl_trait_args);
+        stats.set_count("impl-trait.type_params", self.type_params);
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Platform-dependent numeric types (`usize` and `isize`) versus fixed-width
+// integers and floats in public function signatures and public struct fields.
+// Platform-dependent types in an API or a serialized struct can behave
+// differently on 32- and 64-bit targets.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit, FnKind};
+
+use counter::Counter;
+use metrics::{Metric, ReportContext};
+
+#[derive(Default)]
+struct NumericCounts {
+    platform: Counter,
+    fixed: Counter,
+    float: Counter,
+}
+
+impl<'a> visit::Visitor<'a> for NumericCounts {
+    fn visit_ty(&mut self, ty: &'a ast::Ty) {
+        if let ast::TyKind::Path(None, ref path) = ty.kind {
+            if path.segments.len() == 1 {
+                match &*path.segments[0].ident.as_str() {
+                    "usize" | "isize" => self.platform.increment(),
+                    "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" |
+                    "i128" => self.fixed.increment(),
+                    "f32" | "f64" => self.float.increment(),
+                    _ => {}
+                }
+            }
+        }
+        visit::walk_ty(self, ty)
+    }
+}
+
+impl NumericCounts {
+    fn report(&self, place: &str, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "In {}: {} platform-dependent integers, {} fixed-width integers, and {} floats;",
+            place, self.platform, self.fixed, self.float
+        )
+    }
+}
+
+pub struct IntTypesMetric {
+    signatures: NumericCounts,
+    fields: NumericCounts,
+}
+
+impl IntTypesMetric {
+    pub fn new() -> IntTypesMetric {
+        IntTypesMetric {
+            signatures: NumericCounts::default(),
+            fields: NumericCounts::default(),
+        }
+    }
+
+    fn check_sig(&mut self, vis: &ast::Visibility, sig: &ast::FnSig) {
+        if let ast::VisibilityKind::Public = vis.kind {
+            visit::walk_fn_decl(&mut self.signatures, &sig.decl);
+        }
+    }
+}
+
+impl Metric for IntTypesMetric {
+    fn id(&self) -> &'static str {
+        "int-types"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        match i.kind {
+            ast::ItemKind::Fn(box FnKind(_, ref sig, ..)) => self.check_sig(&i.vis, sig),
+            ast::ItemKind::Struct(ref data, _) if matches!(i.vis.kind, ast::VisibilityKind::Public) => {
+                for field in data.fields() {
+                    if let ast::VisibilityKind::Public = field.vis.kind {
+                        visit::Visitor::visit_ty(&mut self.fields, &field.ty);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_assoc_item(&mut self, i: &ast::AssocItem, _: visit::AssocCtxt) {
+        if let ast::AssocItemKind::Fn(box FnKind(_, ref sig, ..)) = i.kind {
+            self.check_sig(&i.vis, sig);
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        self.signatures.report("public function signatures", out)?;
+        self.fields.report("public struct fields", out)
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(IntTypesMetric::new())
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The crate's interior mutability profile: how often it names `Cell`,
+// `RefCell`, the once/lazy cells, atomics, and `UnsafeCell` in types and calls
+// their constructors. `UnsafeCell` is the one to look at closely, so we list
+// where it is used, except inside the crate's own versions of the well-known
+// wrappers (e.g., a `struct OnceCell`), where it is expected.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_span::Span;
+
+use counter::Counter;
+use metrics::{Metric, ReportContext};
+
+const CELL_TYPES: &[&str] = &[
+    "Cell",
+    "RefCell",
+    "OnceCell",
+    "OnceLock",
+    "Lazy",
+    "LazyCell",
+    "LazyLock",
+    "UnsafeCell",
+];
+
+// The category a type name belongs to, if it is one we count.
+fn category(name: &str) -> Option<&'static str> {
+    if name.starts_with("Atomic") && name.len() > "Atomic".len() {
+        Some("atomics")
+    } else {
+        CELL_TYPES.iter().find(|t| **t == name).cloned()
+    }
+}
+
+#[derive(Default)]
+struct Uses {
+    types: Counter,
+    constructors: Counter,
+}
+
+pub struct InteriorMutabilityMetric {
+    uses: BTreeMap<&'static str, Uses>,
+    unsafe_cells: Vec<Span>,
+    // How many of the crate's own wrapper types we are inside.
+    in_wrapper: usize,
+}
+
+impl InteriorMutabilityMetric {
+    pub fn new() -> InteriorMutabilityMetric {
+        InteriorMutabilityMetric {
+            uses: BTreeMap::new(),
+            unsafe_cells: vec![],
+            in_wrapper: 0,
+        }
+    }
+}
+
+// The name of the type an item defines or implements, if it has one.
+fn item_type_name(i: &ast::Item) -> Option<String> {
+    match i.kind {
+        ast::ItemKind::Struct(..) | ast::ItemKind::Enum(..) | ast::ItemKind::Union(..) => {
+            Some(i.ident.to_string())
+        }
+        ast::ItemKind::Impl(box ast::ImplKind { ref self_ty, .. }) => match self_ty.kind {
+            ast::TyKind::Path(None, ref path) => path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn is_wrapper(i: &ast::Item) -> bool {
+    item_type_name(i).map_or(false, |name| category(&name).is_some())
+}
+
+impl Metric for InteriorMutabilityMetric {
+    fn id(&self) -> &'static str {
+        "interior-mutability"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        if is_wrapper(i) {
+            self.in_wrapper += 1;
+        }
+    }
+
+    fn exit_item(&mut self, i: &ast::Item) {
+        if is_wrapper(i) {
+            self.in_wrapper -= 1;
+        }
+    }
+
+    fn visit_ty(&mut self, ty: &ast::Ty) {
+        if let ast::TyKind::Path(None, ref path) = ty.kind {
+            if let Some(segment) = path.segments.last() {
+                let name = segment.ident.as_str();
+                if let Some(category) = category(&name) {
+                    self.uses.entry(category).or_default().types.increment();
+                    if category == "UnsafeCell" && self.in_wrapper == 0 {
+                        self.unsafe_cells.push(ty.span);
+                    }
+                }
+            }
+        }
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        if let ast::ExprKind::Call(ref f, _) = e.kind {
+            if let ast::ExprKind::Path(None, ref path) = f.kind {
+                let n = path.segments.len();
+                if n >= 2 && path.segments[n - 1].ident.as_str().starts_with("new") {
+                    if let Some(category) = category(&path.segments[n - 2].ident.as_str()) {
+                        self.uses.entry(category).or_default().constructors.increment();
+                    }
+                }
+            }
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(out, "Interior mutability (types, constructor calls):")?;
+        for (name, uses) in &self.uses {
+            writeln!(out, "    `{}`: {}, {}", name, uses.types, uses.constructors)?;
+        }
+        writeln!(
+            out,
+            "{} uses of `UnsafeCell` outside well-known wrappers;",
+            self.unsafe_cells.len()
+        )?;
+        for &span in &self.unsafe_cells {
+            writeln!(out, "    {}", cx.source_map.span_to_string(span))?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(InteriorMutabilityMetric::new())
+    }
+}
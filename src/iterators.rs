@@ -0,0 +1,194 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How a crate hands out iterators: by writing its own iterator types (`impl
+// Iterator for X`), or by returning `impl Iterator` (or a boxed `dyn
+// Iterator`) from functions. Functions which return one of the crate's own
+// iterator types are counted too, which is the other half of the custom style.
+// We go by names, so an iterator type in an alias or another crate counts as
+// something else.
+
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit, FnKind};
+use rustc_span::symbol::Symbol;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+const ITERATOR_TRAITS: &[&str] = &["Iterator", "DoubleEndedIterator", "ExactSizeIterator"];
+
+fn is_iterator_path(path: &ast::Path) -> bool {
+    path.segments
+        .last()
+        .map_or(false, |s| ITERATOR_TRAITS.contains(&&*s.ident.as_str()))
+}
+
+fn has_iterator_bound(bounds: &ast::GenericBounds) -> bool {
+    bounds.iter().any(|b| match *b {
+        ast::GenericBound::Trait(ref t, _) => is_iterator_path(&t.trait_ref.path),
+        ast::GenericBound::Outlives(_) => false,
+    })
+}
+
+// The name of a type written as a plain path, e.g., `Iter` for `Iter<'a, T>`.
+fn type_name(ty: &ast::Ty) -> Option<Symbol> {
+    match ty.kind {
+        ast::TyKind::Path(None, ref path) => path.segments.last().map(|s| s.ident.name),
+        _ => None,
+    }
+}
+
+enum Returns {
+    ImplIterator,
+    BoxDynIterator,
+    // A named type, which is a custom iterator if the crate implements
+    // `Iterator` for it.
+    Named(Symbol),
+}
+
+fn classify_return(ty: &ast::Ty) -> Option<Returns> {
+    match ty.kind {
+        ast::TyKind::ImplTrait(_, ref bounds) if has_iterator_bound(bounds) => {
+            Some(Returns::ImplIterator)
+        }
+        ast::TyKind::Path(None, ref path) => {
+            let last = path.segments.last()?;
+            if &*last.ident.as_str() == "Box" {
+                if let Some(ast::GenericArgs::AngleBracketed(ref args)) = last.args {
+                    let boxes_iterator = args.args.iter().any(|a| match *a {
+                        ast::AngleBracketedArg::Arg(ast::GenericArg::Type(ref t)) => match t.kind {
+                            ast::TyKind::TraitObject(ref bounds, _) => has_iterator_bound(bounds),
+                            _ => false,
+                        },
+                        _ => false,
+                    });
+                    if boxes_iterator {
+                        return Some(Returns::BoxDynIterator);
+                    }
+                }
+            }
+            Some(Returns::Named(last.ident.name))
+        }
+        _ => None,
+    }
+}
+
+pub struct IteratorsMetric {
+    // The types the crate implements `Iterator` for, and how many of those
+    // are defined by the crate.
+    iterator_types: HashSet<Symbol>,
+    local_types: HashSet<Symbol>,
+    iterator_impls: Counter,
+    returns: Vec<Returns>,
+}
+
+impl IteratorsMetric {
+    pub fn new() -> IteratorsMetric {
+        IteratorsMetric {
+            iterator_types: HashSet::new(),
+            local_types: HashSet::new(),
+            iterator_impls: Counter::new(),
+            returns: vec![],
+        }
+    }
+
+    fn check_sig(&mut self, sig: &ast::FnSig) {
+        if let ast::FnRetTy::Ty(ref ty) = sig.decl.output {
+            if let Some(returns) = classify_return(ty) {
+                self.returns.push(returns);
+            }
+        }
+    }
+}
+
+impl Metric for IteratorsMetric {
+    fn id(&self) -> &'static str {
+        "iterators"
+    }
+
+    // Whether a function returns a custom iterator depends on the impls in
+    // the whole crate.
+    fn per_module(&self) -> bool {
+        false
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        match i.kind {
+            ast::ItemKind::Struct(..) | ast::ItemKind::Enum(..) | ast::ItemKind::Union(..) => {
+                self.local_types.insert(i.ident.name);
+            }
+            ast::ItemKind::Impl(box ast::ImplKind {
+                of_trait: Some(ref trait_ref),
+                ref self_ty,
+                ..
+            }) if is_iterator_path(&trait_ref.path) => {
+                // Only `Iterator` itself, the others are extras on an iterator
+                // which is already counted.
+                let is_iterator = trait_ref
+                    .path
+                    .segments
+                    .last()
+                    .map_or(false, |s| &*s.ident.as_str() == "Iterator");
+                if is_iterator {
+                    self.iterator_impls.increment();
+                    if let Some(name) = type_name(self_ty) {
+                        self.iterator_types.insert(name);
+                    }
+                }
+            }
+            ast::ItemKind::Fn(box FnKind(_, ref sig, _, _)) => self.check_sig(sig),
+            _ => {}
+        }
+    }
+
+    fn visit_assoc_item(&mut self, i: &ast::AssocItem, _: visit::AssocCtxt) {
+        if let ast::AssocItemKind::Fn(box FnKind(_, ref sig, _, _)) = i.kind {
+            self.check_sig(sig);
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let mut impl_iterator = Counter::new();
+        let mut box_dyn = Counter::new();
+        let mut custom = Counter::new();
+        for returns in &self.returns {
+            match *returns {
+                Returns::ImplIterator => impl_iterator.increment(),
+                Returns::BoxDynIterator => box_dyn.increment(),
+                Returns::Named(name) if self.iterator_types.contains(&name) => custom.increment(),
+                Returns::Named(_) => {}
+            }
+        }
+        let local_iterators =
+            self.iterator_types.iter().filter(|t| self.local_types.contains(t)).count();
+
+        writeln!(
+            out,
+            "Found {} `impl Iterator for` blocks, for {} iterator types defined in the crate;",
+            self.iterator_impls, local_iterators
+        )?;
+        writeln!(
+            out,
+            "{} functions return `impl Iterator`, {} return `Box<dyn Iterator>`, and {} return \
+             one of the crate's iterator types;",
+            impl_iterator, box_dyn, custom
+        )
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(IteratorsMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("iterators.impls", self.iterator_impls);
+        stats.set_count("iterators.iterator_types", self.iterator_types.len());
+    }
+}
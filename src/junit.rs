@@ -0,0 +1,76 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Findings as a JUnit XML test suite, since most CI systems can show those.
+// Each rule is a test case for the crate. It fails if the rule has any
+// warnings; its notes go in the case's output.
+
+use std::fmt::{self, Write};
+
+use rustc_span::source_map::SourceMap;
+
+use findings::{Findings, Severity, RULES};
+
+pub fn write_junit(
+    findings: &Findings,
+    crate_name: &str,
+    source_map: &SourceMap,
+    out: &mut String,
+) -> fmt::Result {
+    let failures = RULES
+        .iter()
+        .filter(|r| findings.iter().any(|f| f.id == **r && f.severity == Severity::Warning))
+        .count();
+
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<testsuite name="stupid-stats" tests="{}" failures="{}">"#,
+        RULES.len(),
+        failures
+    )?;
+    for rule in RULES {
+        writeln!(
+            out,
+            r#"  <testcase classname="{}" name="{}">"#,
+            escape(crate_name),
+            rule
+        )?;
+
+        let lines = |severity| {
+            findings
+                .iter()
+                .filter(|f| f.id == *rule && f.severity == severity)
+                .map(|f| format!("{}: {}", source_map.span_to_string(f.span), f.message))
+                .collect::<Vec<_>>()
+        };
+        let warnings = lines(Severity::Warning);
+        if !warnings.is_empty() {
+            writeln!(
+                out,
+                r#"    <failure message="{} findings">{}</failure>"#,
+                warnings.len(),
+                escape(&warnings.join("\n"))
+            )?;
+        }
+        let notes = lines(Severity::Note);
+        if !notes.is_empty() {
+            writeln!(out, "    <system-out>{}</system-out>", escape(&notes.join("\n")))?;
+        }
+
+        writeln!(out, "  </testcase>")?;
+    }
+    writeln!(out, "</testsuite>")
+}
+
+pub fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
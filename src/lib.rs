@@ -0,0 +1,1124 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The analysis behind the `stupid` driver, as a library so that other tools
+// can embed it: either run the compiler with `StupidCalls` as its callbacks,
+// or call `analyze` to get a crate's summary stats back as data.
+
+#![feature(rustc_private)]
+#![feature(box_patterns)]
+
+extern crate rustc_driver;
+extern crate rustc_hir;
+extern crate rustc_interface;
+extern crate rustc_middle;
+extern crate rustc_span;
+extern crate rustc_ast;
+extern crate rustc_ast_pretty;
+extern crate rustc_data_structures;
+extern crate rustc_attr;
+extern crate rustc_session;
+extern crate rustc_parse;
+extern crate rustc_errors;
+extern crate rustc_serialize;
+
+mod actions;
+pub mod api;
+mod args;
+mod assertions;
+mod async_code;
+mod attributes;
+mod baseline;
+mod bool_params;
+mod casts;
+mod cfgs;
+mod closures;
+mod cognitive;
+mod collect_targets;
+mod comments;
+pub mod completions;
+mod config;
+pub mod counter;
+mod crate_attrs;
+pub mod crate_stats;
+mod cyclomatic;
+mod delegation;
+mod diagnostics;
+pub mod doctor;
+mod double_indirection;
+mod drops;
+mod error_conversions;
+mod error_types;
+mod expansion_depth;
+mod expansion_growth;
+mod fallback;
+mod fan_out;
+mod features;
+mod findings;
+mod fn_length;
+mod format_strings;
+mod function_csv;
+mod gates;
+mod generated;
+mod generic_bloat;
+mod global_state;
+mod god_modules;
+mod html;
+mod impl_trait;
+mod int_types;
+mod interior_mutability;
+mod iterators;
+mod junit;
+mod lifetimes;
+mod line_endings;
+mod loop_allocations;
+mod macro_positions;
+mod macro_sizes;
+mod match_arms;
+mod match_style;
+pub mod merge;
+pub mod messages;
+pub mod metrics;
+mod modules;
+mod nested_types;
+mod non_exhaustive;
+pub mod options;
+mod overexposed;
+mod owners;
+mod panics;
+mod pipeline;
+mod polymorphism;
+mod println;
+pub mod profiles;
+mod provenance;
+mod receivers;
+mod returns;
+pub mod sample;
+mod spans;
+mod spawns;
+mod teams;
+mod timing;
+mod tracked;
+mod trait_bounds;
+mod type_stats;
+mod unsafe_code;
+
+use rustc_driver::{Compilation, Callbacks, RunCompiler};
+use rustc_interface::{Config, Queries, interface::Compiler};
+use rustc_ast::{ast, visit};
+use rustc_session::DiagnosticOutput;
+use rustc_session::config::Input;
+use rustc_span::Span;
+use rustc_span::source_map::SourceMap;
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::panic::{self, AssertUnwindSafe};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use actions::run_actions;
+use api::ApiVisitor;
+use args::set_arg_stats;
+use crate_stats::CrateStats;
+use diagnostics::{ask_for_json, CountingWriter, LintCounts};
+use fallback::analyze_files;
+use findings::Findings;
+use generated::{generated_lines, without_generated};
+use html::write_html;
+use junit::write_junit;
+use messages::Message;
+use metrics::{builtin_metrics, AnalysisContext, Metric, ReportContext, Stage};
+use modules::{load_modules, module_path, module_subtree};
+use options::{Format, Options};
+use provenance::Provenance;
+use sample::Sample;
+
+// This is the highest level controller of compiler execution. We often want
+// some context to remember facts about compilation (e.g., the input file or
+// some processed flags). We need our options, somewhere to count the warnings
+// rustc emits while it compiles, and to know whether we should keep quiet.
+// We need to delegate to RustcDefaultCalls when we want to do what the rust
+// compiler would do in certain circumstances. We do this so that we can emit
+// some of the same info to Cargo.
+pub struct StupidCalls {
+    lint_counts: Arc<Mutex<LintCounts>>,
+    // When we are wrapping rustc for somebody else's crate (e.g., a dependency
+    // in a Cargo build), we must not affect the build in any way: nothing goes
+    // to stdout and problems collecting stats are ignored.
+    quiet: bool,
+    // Whether to write the report (to stdout, or a file in quiet mode).
+    emit: bool,
+    options: Options,
+    // The metrics to collect as we walk the AST; `StupidVisitor` takes them
+    // when it's time.
+    metrics: Vec<Box<dyn Metric>>,
+    // The summary stats, once we have them.
+    stats: Option<CrateStats>,
+    // Whether rustc parsed the crate, in which case a failed build is a type
+    // or borrow error, say, and not one for `fallback`.
+    parsed: bool,
+    // How the report is made, for the manifest next to a saved report.
+    provenance: Option<Provenance>,
+    // What got worse since the baseline, if we compared with one.
+    regressions: Vec<String>,
+}
+
+impl StupidCalls {
+    pub fn new(quiet: bool, mut options: Options) -> StupidCalls {
+        // The HTML page, the API inventory, and the per-function rows are all
+        // names and places, so there's nothing to write when anonymizing.
+        if options.anonymize {
+            options.stats_output = None;
+            options.api_inventory = None;
+            options.per_function_csv = None;
+            options.sink = None;
+        }
+        let lint_counts = Arc::new(Mutex::new(LintCounts::default()));
+        StupidCalls {
+            lint_counts: lint_counts.clone(),
+            quiet,
+            emit: true,
+            metrics: builtin_metrics(&options, lint_counts),
+            options,
+            stats: None,
+            parsed: false,
+            provenance: None,
+            regressions: vec![],
+        }
+    }
+
+    // Adds a metric to the report, after the built-in ones.
+    pub fn register_metric(&mut self, metric: Box<dyn Metric>) {
+        self.metrics.push(metric);
+    }
+
+    // The summary stats for the crate, once analysis has finished.
+    pub fn crate_stats(&self) -> Option<&CrateStats> {
+        self.stats.as_ref()
+    }
+
+    // If rustc couldn't get as far as giving us the crate, collects what stats
+    // we can by parsing the crate's files one by one (see `fallback`), and
+    // reports them like the normal stats. `args` are rustc's arguments, for
+    // finding the crate. Returns whether there was anything to do.
+    //
+    // The files are analysed on their own, without their module paths and
+    // without findings, so there is nothing to do for `--module-filter` or the
+    // JUnit format, and we say so rather than report something else.
+    pub fn fallback(&mut self, args: &[String]) -> bool {
+        if self.stats.is_some() || self.parsed {
+            return false;
+        }
+        let unsupported = match self.options.format {
+            _ if self.options.module_filter.is_some() => Some("`--module-filter`"),
+            Format::Junit => Some("the JUnit format"),
+            _ => None,
+        };
+        if let Some(unsupported) = unsupported {
+            if !self.quiet {
+                eprintln!(
+                    "stupid-stats: rustc couldn't parse the crate, and there are no stats for {} \
+                     from parsing its files one at a time",
+                    unsupported
+                );
+            }
+            return false;
+        }
+        let root = args.iter().skip(1).find(|a| a.ends_with(".rs") && Path::new(a).is_file());
+        let root = match root {
+            Some(root) => Path::new(root),
+            None => return false,
+        };
+        let crate_name = args
+            .iter()
+            .position(|a| a == "--crate-name")
+            .and_then(|i| args.get(i + 1).cloned())
+            .or_else(|| root.file_stem().map(|s| s.to_string_lossy().replace('-', "_")))
+            .unwrap_or_else(|| String::from("unknown_crate"));
+
+        let options = &self.options;
+        let metrics = mem::replace(&mut self.metrics, vec![])
+            .into_iter()
+            .filter(|m| options.metric_enabled(m.id()))
+            .collect();
+        let (mut stats, mut report) =
+            analyze_files(root, &crate_name, metrics, self.options.lang);
+        if self.options.anonymize {
+            stats.anonymize();
+        }
+        // As in the normal report, JSON is just the summary.
+        if let Format::Json = self.options.format {
+            report = String::new();
+            stats.write_json(&mut report).unwrap();
+        }
+        if self.emit && !self.quiet {
+            print!("{}", report);
+        }
+        self.stats = Some(stats);
+        true
+    }
+
+    // The `--fail-if` conditions which hold for the crate, once analysis has
+    // finished, and the regressions since the baseline if they count.
+    pub fn failed_gates(&self) -> Vec<String> {
+        let mut failed = match self.stats {
+            Some(ref stats) => gates::check(&self.options.gates(), stats),
+            None => vec![],
+        };
+        if self.options.fail_on_regression {
+            failed.extend(self.regressions.iter().cloned());
+        }
+        failed
+    }
+
+    // Saves the stats as a baseline and compares them with the old one, as
+    // asked. The comparison is printed after the report.
+    fn baseline(&mut self, stats: &CrateStats) {
+        if let Some(ref path) = self.options.compare_baseline {
+            let path = path.replace("{crate}", &stats.crate_name);
+            match fs::read_to_string(&path).ok().and_then(|json| CrateStats::read_json(&json)) {
+                Some(mut old) => {
+                    set_arg_stats(&mut old);
+                    let mut out = String::new();
+                    self.regressions = baseline::compare(&old, stats, &mut out).unwrap();
+                    if !self.quiet {
+                        print!("{}", out);
+                    }
+                }
+                None => {
+                    if !self.quiet {
+                        eprintln!("stupid-stats: couldn't read the baseline `{}`", path);
+                    }
+                }
+            }
+        }
+        if let Some(ref path) = self.options.save_baseline {
+            let path = path.replace("{crate}", &stats.crate_name);
+            let mut json = String::new();
+            stats.write_json(&mut json).unwrap();
+            if let Err(e) = fs::write(&path, json) {
+                if !self.quiet {
+                    eprintln!("stupid-stats: couldn't write `{}`: {}", path, e);
+                }
+            }
+        }
+    }
+}
+
+/// Compiles a crate with rustc's command line arguments `args` (including the
+/// program name) and returns its summary stats; nothing is printed. `metrics`
+/// are collected after the built-in ones (as with
+/// `StupidCalls::register_metric`), and their numbers are in the stats'
+/// `counts`. Returns `None` if the crate didn't compile as far as analysis, or
+/// the stats couldn't be collected.
+pub fn analyze(args: &[String], metrics: Vec<Box<dyn Metric>>) -> Option<CrateStats> {
+    let args: Vec<_> = args.iter().cloned().chain(sys_root(args)).collect();
+    let mut options = Options::from_env();
+    // We only want the stats.
+    options.no_codegen = options.no_codegen.or(Some(true));
+    let mut calls = StupidCalls::new(true, options);
+    calls.emit = false;
+    for metric in metrics {
+        calls.register_metric(metric);
+    }
+    rustc_driver::catch_fatal_errors(|| RunCompiler::new(&args, &mut calls).run()).ok()?.ok()?;
+    calls.stats
+}
+
+// Callbacks is a trait for running code during compilation at the driver level. It
+// is basically a set of callbacks to call at various stages of compilation to
+// execute custom actions or influence compilation. We are mostly just going to
+// do nothing and let compilation continue.
+impl Callbacks for StupidCalls {
+    // first callback the compiler driver calls
+    fn config(&mut self, config: &mut Config) {
+        // this prevents the compiler from dropping the expanded AST
+        // although it still works without it?
+        config.opts.debugging_opts.save_analysis = true;
+        // Diagnostics go through our writer on their way to stderr, so we can
+        // count warnings, if we're going to report them.
+        // (`lints` is left out for a single module.)
+        if self.options.metric_enabled("lints") && self.options.module_filter.is_none() {
+            let render = ask_for_json(&mut config.opts.error_format);
+            let writer = CountingWriter::new(self.lint_counts.clone(), render);
+            config.diagnostic_output = DiagnosticOutput::Raw(Box::new(writer));
+        }
+        self.provenance = Some(Provenance::record(&self.options));
+        // Cargo checks a package's library and binaries again as unit tests
+        // for `--all-targets`; `cargo stupid-stats` only wants them once.
+        let unit_tests = config.opts.test && target_kind(&config.input).is_empty();
+        if self.options.report_dir.is_some() && unit_tests {
+            self.emit = false;
+        }
+    }
+
+    // next step once config has been read and all input parsed
+    fn after_parsing<'tcx>(
+        &mut self,
+        _compiler: &Compiler,
+        _queries: &'tcx Queries<'tcx>
+    ) -> Compilation {
+        self.parsed = true;
+        Compilation::Continue
+    }
+
+    // after macro expansion
+    fn after_expansion<'tcx>(
+        &mut self,
+        _compiler: &Compiler,
+        _queries: &'tcx Queries<'tcx>
+    ) -> Compilation {
+        Compilation::Continue
+    }
+
+    // This is a hook to allow us to supply a callback called after analysis.
+    // We are given access to the compiler and the various queries run by the compiler
+    // as `Compiler` and `Queries` respectively. The `after_analysis` stage of the
+    // compiler gives us access to a fully compiled crate with all meta data.
+    fn after_analysis<'tcx>(
+        &mut self,
+        compiler: &Compiler,
+        queries: &'tcx Queries<'tcx>,
+    ) -> Compilation {
+        // Collecting stats must never break a build. In quiet mode, if something
+        // goes wrong we give up on the stats and let compilation carry on.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.stats_report(compiler, queries)));
+        match result {
+            Ok((stats, report)) => {
+                if self.emit {
+                    self.emit_report(compiler, &stats, &report);
+                    self.save_json(compiler, &stats);
+                    self.baseline(&stats);
+                    let stem = file_stem(compiler, &stats.crate_name);
+                    for error in run_actions(&self.options, &stats, &stem) {
+                        if !self.quiet {
+                            eprintln!("stupid-stats: {}", error);
+                        }
+                    }
+                }
+                self.stats = Some(stats);
+            }
+            Err(e) => {
+                if !self.quiet {
+                    panic::resume_unwind(e);
+                }
+            }
+        }
+
+        if self.options.no_codegen == Some(true) {
+            Compilation::Stop
+        } else {
+            Compilation::Continue
+        }
+    }
+}
+
+impl StupidCalls {
+    // Collects the stats for the crate and renders them in our format. Returns
+    // the summary stats and the report.
+    fn stats_report<'tcx>(
+        &mut self,
+        compiler: &Compiler,
+        queries: &'tcx Queries<'tcx>,
+    ) -> (CrateStats, String) {
+        // `Queries::parse` gives us access to a `Result<Query<Crate>>` which is exactly what
+        // our ast `Visitor` needs.
+        let mut krate = queries.parse().expect("no Result<Query<Crate>> found").take();
+        // The out-of-line modules aren't loaded until expansion, so we load
+        // them ourselves.
+        if let Input::File(ref root) = *compiler.input() {
+            load_modules(&mut krate, root, compiler.session().parse_sess.clone_source_map());
+        }
+        let crate_name = match rustc_attr::find_crate_name(compiler.session(), &krate.attrs) {
+            Some(name) => name.to_string(),
+            None => String::from("unknown_crate"),
+        };
+        // If we only want one module, we pretend it's the whole crate. A module
+        // which doesn't exist has nothing in it. Likewise, we pretend generated
+        // code isn't there.
+        let filter = self.options.module_filter.clone();
+        let include_generated = self.options.include_generated;
+        let source_map = compiler.session().source_map();
+        let subtree = |krate: &ast::Crate| {
+            let subtree = filter.as_ref().map(|filter| {
+                module_subtree(krate, filter, &crate_name)
+                    .unwrap_or_else(|| ast::Crate { items: vec![], ..krate.clone() })
+            });
+            if include_generated {
+                return subtree;
+            }
+            without_generated(subtree.as_ref().unwrap_or(krate), source_map).or(subtree)
+        };
+        let krate = subtree(&krate).unwrap_or(krate);
+
+        // Walks the AST, collecting the summary stats.
+        let options = &self.options;
+        let quiet = self.quiet;
+        let metrics = mem::replace(&mut self.metrics, vec![])
+            .into_iter()
+            .filter(|m| options.metric_enabled(m.id()))
+            .filter(|m| {
+                if filter.is_none() || !m.whole_crate() {
+                    return true;
+                }
+                // Only complain if it was asked for by name.
+                if options.metrics.is_some() && !quiet {
+                    eprintln!(
+                        "stupid-stats: `{}` is for the whole crate, so it's left out with \
+                         `--module-filter`",
+                        m.id()
+                    );
+                }
+                false
+            })
+            .collect();
+        let mut visitor = StupidVisitor::new(metrics).sampling(self.options.sample);
+        if self.options.per_module || self.options.stats_output.is_some() {
+            visitor = visitor.per_module();
+        }
+        visitor.walk(&krate, Stage::Parse);
+
+        // Some metrics need the crate after macro expansion. It is still around
+        // because we set `save_analysis`.
+        if visitor.needs(Stage::Expansion) {
+            let expansion = queries.expansion().expect("no expanded crate found").peek();
+            let expanded_subtree = subtree(&*expansion.0);
+            visitor.walk(expanded_subtree.as_ref().unwrap_or(&*expansion.0), Stage::Expansion);
+        }
+        if visitor.needs(Stage::Analysis) {
+            let sample = self.options.sample;
+            let module = filter.as_ref().map(|filter| {
+                module_path(filter, &crate_name).into_iter().map(String::from).collect()
+            });
+            queries
+                .global_ctxt()
+                .expect("no type context found")
+                .peek_mut()
+                .enter(|tcx| {
+                    visitor.check_analysis(&AnalysisContext {
+                        tcx,
+                        sample,
+                        module,
+                        submodules: true,
+                    })
+                });
+        }
+        visitor.check_source(source_map);
+
+        let mut stats = visitor.crate_stats(&crate_name);
+        stats.target = target_kind(compiler.input()).to_owned();
+        if self.options.anonymize {
+            stats.anonymize();
+        }
+        if !include_generated {
+            stats.generated_lines_excluded = generated_lines(source_map);
+        }
+
+        if let Some(ref path) = self.options.stats_output {
+            let mut sections = String::new();
+            visitor.write_html(&mut sections).unwrap();
+            let mut html = String::new();
+            write_html(&stats, &visitor.module_stats(&crate_name), &sections, &mut html).unwrap();
+            let path = path.replace("{crate}", &crate_name);
+            if let Err(e) = fs::write(&path, html) {
+                if !self.quiet {
+                    eprintln!("stupid-stats: couldn't write `{}`: {}", path, e);
+                }
+            }
+        }
+
+        if let Some(ref path) = self.options.api_inventory {
+            let mut api_visitor = ApiVisitor::new(&crate_name);
+            visit::walk_crate(&mut api_visitor, &krate);
+            let mut inventory = String::new();
+            api_visitor.write_inventory(&mut inventory).unwrap();
+            let path = path.replace("{crate}", &crate_name);
+            if let Err(e) = fs::write(&path, inventory) {
+                if !self.quiet {
+                    eprintln!("stupid-stats: couldn't write `{}`: {}", path, e);
+                }
+            }
+        }
+
+        if let Some(ref path) = self.options.per_function_csv {
+            let path = path.replace("{crate}", &crate_name);
+            let ndjson = path.ends_with(".ndjson") || path.ends_with(".jsonl");
+            let result = if self.options.low_memory {
+                File::create(&path).and_then(|file| {
+                    let out = BufWriter::new(file);
+                    function_csv::write_rows(source_map, &crate_name, &krate, ndjson, out)
+                        .map(|_| ())
+                })
+            } else {
+                function_csv::write_rows(source_map, &crate_name, &krate, ndjson, Vec::<u8>::new())
+                    .and_then(|rows| fs::write(&path, rows))
+            };
+            if let Err(e) = result {
+                if !self.quiet {
+                    eprintln!("stupid-stats: couldn't write `{}`: {}", path, e);
+                }
+            }
+        }
+
+        // The same rows can be streamed to a sink, as they're written.
+        if let Some(ref address) = self.options.sink {
+            let result = function_csv::connect(address).and_then(|sink| {
+                let out = BufWriter::new(sink);
+                function_csv::write_rows(source_map, &crate_name, &krate, true, out).map(|_| ())
+            });
+            if let Err(e) = result {
+                if !self.quiet {
+                    eprintln!("stupid-stats: couldn't send the rows to `{}`: {}", address, e);
+                }
+            }
+        }
+
+        // JSON only has the summary, so we don't need to do any more than that.
+        if let Format::Json = self.options.format {
+            let mut report = String::new();
+            stats.write_json(&mut report).unwrap();
+            return (stats, report);
+        }
+
+        // Writing to a `String` can't fail.
+        let mut report = String::new();
+        let mut findings = Findings::new();
+        self.write_stats(source_map, &visitor, &crate_name, &mut findings, &mut report).unwrap();
+
+        // Findings can be reported in other formats, in which case we only
+        // report those.
+        match self.options.format {
+            Format::Text => findings.report(source_map, &mut report).unwrap(),
+            Format::Junit => {
+                report = String::new();
+                write_junit(&findings, &crate_name, source_map, &mut report).unwrap();
+            }
+            Format::Json => unreachable!(),
+        }
+        (stats, report)
+    }
+
+    fn write_stats(
+        &self,
+        source_map: &SourceMap,
+        visitor: &StupidVisitor,
+        crate_name: &str,
+        // Rules add their findings here as we go.
+        findings: &mut Findings,
+        out: &mut String,
+    ) -> fmt::Result {
+        // Writes out the stupid stats that we collected.
+        let lang = self.options.lang;
+        writeln!(out, "{}", lang.render(Message::InCrate(crate_name)))?;
+        if let Some(ref filter) = self.options.module_filter {
+            writeln!(out, "{}", lang.render(Message::OnlyModule(filter)))?;
+        }
+        if !self.options.include_generated {
+            let lines = generated_lines(source_map);
+            if lines > 0 {
+                writeln!(out, "{}", lang.render(Message::GeneratedExcluded(lines)))?;
+            }
+        }
+        let (sampled, population) = visitor.sampled_functions();
+        if visitor.is_sampled() {
+            let percent = self.options.sample.percent();
+            writeln!(out, "{}", lang.render(Message::Sampled(percent, sampled, population)))?;
+        }
+        let cx = ReportContext {
+            lang,
+            source_map,
+            sampled,
+            population,
+        };
+        visitor.report(&cx, out)?;
+        visitor.findings(findings);
+        if self.options.per_module {
+            visitor.report_modules(&cx, out)?;
+        }
+
+        Ok(())
+    }
+
+    // Saves the summary stats for `stupid merge`, if we've been asked to, named
+    // like the report so that crates with the same name (e.g., a package's
+    // library and binary, or two versions of a dependency) don't overwrite
+    // each other.
+    fn save_json(&self, compiler: &Compiler, stats: &CrateStats) {
+        let dir = match self.options.json_dir {
+            Some(ref dir) => dir,
+            None => return,
+        };
+        let stem = file_stem(compiler, &stats.crate_name);
+        let (path, contents) = if self.options.binary_stats {
+            let mut bytes = vec![];
+            stats.write_binary(&mut bytes);
+            (dir.join(format!("{}.stats", stem)), bytes)
+        } else {
+            let mut json = String::new();
+            stats.write_json(&mut json).unwrap();
+            (dir.join(format!("{}.json", stem)), json.into_bytes())
+        };
+        // As with the report, this isn't worth failing the build over.
+        let _ = fs::create_dir_all(dir).and_then(|_| fs::write(path, contents));
+    }
+
+    // Normally the report goes to stdout. In quiet mode we must not print
+    // anything, so it goes in a file next to the build artifacts instead, e.g.,
+    // `target/debug/stupid-stats/foo-0123456789abcdef.txt`, with a manifest of
+    // how it was made in `foo-0123456789abcdef.manifest.json`.
+    fn emit_report(&self, compiler: &Compiler, stats: &CrateStats, report: &str) {
+        if !self.quiet {
+            print!("{}", report);
+            return;
+        }
+
+        // `cargo stupid-stats` collects the reports in its own directory, one
+        // subdirectory for each package, with the examples, tests, and
+        // benchmarks in subdirectories of that.
+        let dir = match (&self.options.report_dir, compiler.output_dir()) {
+            (&Some(ref report_dir), _) => {
+                let dir = match std::env::var("CARGO_PKG_NAME") {
+                    Ok(package) => report_dir.join(package),
+                    Err(_) => report_dir.clone(),
+                };
+                match &*stats.target {
+                    "" => dir,
+                    "bench" => dir.join("benches"),
+                    target => dir.join(format!("{}s", target)),
+                }
+            }
+            (&None, &Some(ref out_dir)) => out_dir.parent().unwrap_or(out_dir).join("stupid-stats"),
+            (&None, &None) => return,
+        };
+        let stem = file_stem(compiler, &stats.crate_name);
+        let file_name = format!("{}.{}", stem, self.options.format.extension());
+        // Not being able to save the stats is not worth failing the build over.
+        let _ = fs::create_dir_all(&dir).and_then(|_| fs::write(dir.join(file_name), report));
+        if let Some(ref provenance) = self.provenance {
+            let mut manifest = String::new();
+            provenance.write_json(&mut manifest).unwrap();
+            let _ = fs::write(dir.join(format!("{}.manifest.json", stem)), manifest);
+        }
+    }
+}
+
+// What kind of Cargo target the crate with root `input` is, from where it is in
+// its package: `example`, `test`, or `bench` for a root in `examples`, `tests`,
+// or `benches`, and empty for anything else (the library and binaries, or a
+// crate which isn't built by Cargo).
+fn target_kind(input: &Input) -> &'static str {
+    let (root, package) = match (input, std::env::var_os("CARGO_MANIFEST_DIR")) {
+        (&Input::File(ref root), Some(package)) => (root, PathBuf::from(package)),
+        _ => return "",
+    };
+    // Cargo gives us the root relative to the workspace, where it runs us.
+    let root = std::env::current_dir().map(|dir| dir.join(root)).unwrap_or_else(|_| root.clone());
+    let dir = root.strip_prefix(&package).ok().and_then(|r| r.components().next());
+    match dir.and_then(|d| d.as_os_str().to_str()) {
+        Some("examples") => "example",
+        Some("tests") => "test",
+        Some("benches") => "bench",
+        _ => "",
+    }
+}
+
+// The name for the files we save for a crate, without the extension, e.g.,
+// `foo-0123456789abcdef`. Cargo gives each crate it builds a different
+// `-C extra-filename`.
+fn file_stem(compiler: &Compiler, crate_name: &str) -> String {
+    format!("{}{}", crate_name, compiler.session().opts.cg.extra_filename)
+}
+
+// We'll collect our stats by walking the AST. To do that we need a visitor object.
+// It drives the metrics, calling each one's hooks as it goes.
+pub struct StupidVisitor {
+    metrics: Vec<Box<dyn Metric>>,
+    // For a breakdown by module, a fresh set of metrics for each module (by
+    // path) which just sees that module's code, not its submodules'. Only the
+    // metrics which can be broken down are included.
+    modules: Option<BTreeMap<Vec<String>, Vec<Box<dyn Metric>>>>,
+    // The path of the module we're in.
+    path: Vec<String>,
+    // Which walk we're doing; only the metrics which need it see it.
+    stage: Stage,
+    // Which functions the metrics which sample look at, how many functions
+    // we're inside which aren't in the sample, and how many functions were and
+    // weren't in it.
+    sample: Sample,
+    unsampled: usize,
+    sampled_functions: usize,
+    functions: usize,
+}
+
+impl StupidVisitor {
+    pub fn new(metrics: Vec<Box<dyn Metric>>) -> StupidVisitor {
+        StupidVisitor {
+            metrics,
+            modules: None,
+            path: vec![],
+            stage: Stage::Parse,
+            sample: Sample::all(),
+            unsampled: 0,
+            sampled_functions: 0,
+            functions: 0,
+        }
+    }
+
+    // Also collects the metrics for each module.
+    pub fn per_module(mut self) -> StupidVisitor {
+        self.modules = Some(BTreeMap::new());
+        self
+    }
+
+    // Only the functions in `sample` are shown to the metrics which sample.
+    pub fn sampling(mut self, sample: Sample) -> StupidVisitor {
+        self.sample = sample;
+        self
+    }
+
+    // How many functions the metrics which sample saw, and how many there
+    // are.
+    pub fn sampled_functions(&self) -> (usize, usize) {
+        (self.sampled_functions, self.functions)
+    }
+
+    // Whether any of the metrics sample, and we're sampling.
+    pub fn is_sampled(&self) -> bool {
+        !self.sample.is_all() && self.metrics.iter().any(|m| m.sampled())
+    }
+
+    // Whether the function `fk` is in the sample, counting it if this is the
+    // first walk. Closures belong to the function they're in.
+    fn in_sample(&mut self, fk: visit::FnKind) -> bool {
+        let ident = match fk {
+            visit::FnKind::Fn(_, ident, _, _, Some(_)) => ident,
+            _ => return true,
+        };
+        let path: Vec<_> = self.path.iter().cloned().chain(Some(ident.to_string())).collect();
+        let sampled = self.sample.includes(&path.join("::"));
+        if self.stage == Stage::Parse {
+            self.functions += 1;
+            if sampled && self.unsampled == 0 {
+                self.sampled_functions += 1;
+            }
+        }
+        sampled
+    }
+
+    // Whether any of the metrics need `stage`.
+    pub fn needs(&self, stage: Stage) -> bool {
+        self.metrics.iter().any(|m| m.stages().contains(&stage))
+    }
+
+    // Walks `krate` for the metrics which need `stage`, `Parse` or
+    // `Expansion`.
+    pub fn walk(&mut self, krate: &ast::Crate, stage: Stage) {
+        self.stage = stage;
+        for metric in self.metrics.iter_mut().filter(|m| m.stages().contains(&stage)) {
+            metric.check_crate(krate, stage);
+        }
+        visit::walk_crate(self, krate);
+    }
+
+    // `cx` is for the whole crate, or the module we were asked for. Each
+    // module's metrics get to see just that module.
+    pub fn check_analysis<'tcx>(&mut self, cx: &AnalysisContext<'tcx>) {
+        for metric in self.metrics.iter_mut().filter(|m| m.stages().contains(&Stage::Analysis)) {
+            metric.check_analysis(cx);
+        }
+        let modules = match self.modules {
+            Some(ref mut modules) => modules,
+            None => return,
+        };
+        for (path, metrics) in modules.iter_mut() {
+            // The paths are from the root of what we walked.
+            let root = cx.module.iter().flatten();
+            let module_cx = AnalysisContext {
+                tcx: cx.tcx,
+                sample: cx.sample,
+                module: Some(root.chain(path).cloned().collect()),
+                submodules: false,
+            };
+            for metric in metrics.iter_mut().filter(|m| m.stages().contains(&Stage::Analysis)) {
+                metric.check_analysis(&module_cx);
+            }
+        }
+    }
+
+    // After the walks, so metrics can look up the source of what they saw.
+    pub fn check_source(&mut self, source_map: &SourceMap) {
+        let modules = self.modules.iter_mut().flat_map(|modules| modules.values_mut().flatten());
+        for metric in self.metrics.iter_mut().chain(modules) {
+            if metric.stages().contains(&Stage::Source) {
+                metric.check_source(source_map);
+            }
+        }
+    }
+
+    // Calls `f` on the crate's metrics which need the walk we're doing and,
+    // if we have them, those of the module we're in.
+    fn each<F: FnMut(&mut dyn Metric)>(&mut self, mut f: F) {
+        let stage = self.stage;
+        let unsampled = self.unsampled > 0;
+        let sees = |m: &&mut Box<dyn Metric>| {
+            m.stages().contains(&stage) && !(unsampled && m.sampled())
+        };
+        for metric in self.metrics.iter_mut().filter(&sees) {
+            f(&mut **metric);
+        }
+        if let Some(ref mut modules) = self.modules {
+            if !modules.contains_key(&self.path) {
+                let fresh = self
+                    .metrics
+                    .iter()
+                    .filter(|m| m.per_module())
+                    .map(|m| m.fresh())
+                    .collect();
+                modules.insert(self.path.clone(), fresh);
+            }
+            let metrics = modules.get_mut(&self.path).unwrap();
+            for metric in metrics.iter_mut().filter(&sees) {
+                f(&mut **metric);
+            }
+        }
+    }
+
+    pub fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        for metric in &self.metrics {
+            metric.report(cx, out)?;
+        }
+        Ok(())
+    }
+
+    // The tree of modules, each with its metrics' reports. Does nothing unless
+    // we collected them (see `per_module`).
+    pub fn report_modules(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let modules = match self.modules {
+            Some(ref modules) => modules,
+            None => return Ok(()),
+        };
+        writeln!(out, "By module:")?;
+        // Paths sort with each module before its submodules.
+        for (path, metrics) in modules {
+            let indent = "    ".repeat(path.len() + 1);
+            writeln!(out, "{}{}:", indent, path.last().map_or("crate", |s| &**s))?;
+            let mut section = String::new();
+            for metric in metrics {
+                metric.report(cx, &mut section)?;
+            }
+            for line in section.lines() {
+                writeln!(out, "{}    {}", indent, line)?;
+            }
+        }
+        let whole_crate: Vec<_> =
+            self.metrics.iter().filter(|m| !m.per_module()).map(|m| m.id()).collect();
+        if !whole_crate.is_empty() {
+            writeln!(out, "    (Only for the whole crate: {}.)", whole_crate.join(", "))?;
+        }
+        Ok(())
+    }
+
+    pub fn findings(&self, findings: &mut Findings) {
+        for metric in &self.metrics {
+            metric.findings(findings);
+        }
+    }
+
+    // The metrics' sections of the HTML page.
+    pub fn write_html(&self, out: &mut String) -> fmt::Result {
+        for metric in &self.metrics {
+            metric.write_html(out)?;
+        }
+        Ok(())
+    }
+
+    pub fn crate_stats(&self, crate_name: &str) -> CrateStats {
+        StupidVisitor::stats_for(&self.metrics, crate_name)
+    }
+
+    // The summary stats for each module, by path (e.g., `a::b`), if we
+    // collected them.
+    pub fn module_stats(&self, crate_name: &str) -> Vec<(String, CrateStats)> {
+        let modules = match self.modules {
+            Some(ref modules) => modules,
+            None => return vec![],
+        };
+        modules
+            .iter()
+            .map(|(path, metrics)| {
+                let path = if path.is_empty() { crate_name.to_owned() } else { path.join("::") };
+                (path, StupidVisitor::stats_for(metrics, crate_name))
+            })
+            .collect()
+    }
+
+    fn stats_for(metrics: &[Box<dyn Metric>], crate_name: &str) -> CrateStats {
+        let mut stats = CrateStats {
+            crate_name: crate_name.to_owned(),
+            package: std::env::var("CARGO_PKG_NAME").unwrap_or_default(),
+            ..CrateStats::default()
+        };
+        for metric in metrics {
+            metric.crate_stats(&mut stats);
+        }
+        stats
+    }
+}
+
+// visit::Visitor is the generic trait for walking an AST. For each node, the
+// metrics are told about it, then we walk inside it, then they're told we've
+// finished with it.
+impl<'a> visit::Visitor<'a> for StupidVisitor {
+    // We found an item, could be a function.
+    fn visit_item(&mut self, i: &'a ast::Item) {
+        self.each(|m| m.visit_item(i));
+        // Keep walking, inside the module if it is one.
+        if let ast::ItemKind::Mod(..) = i.kind {
+            self.path.push(i.ident.to_string());
+            visit::walk_item(self, i);
+            self.path.pop();
+        } else {
+            visit::walk_item(self, i)
+        }
+        self.each(|m| m.exit_item(i));
+    }
+
+    fn visit_assoc_item(&mut self, i: &'a ast::AssocItem, ctxt: visit::AssocCtxt) {
+        self.each(|m| m.visit_assoc_item(i, ctxt));
+        visit::walk_assoc_item(self, i, ctxt);
+        self.each(|m| m.exit_assoc_item(i, ctxt));
+    }
+
+    fn visit_foreign_item(&mut self, i: &'a ast::ForeignItem) {
+        self.each(|m| m.visit_foreign_item(i));
+        visit::walk_foreign_item(self, i)
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind<'a>, s: Span, _: ast::NodeId) {
+        // The metrics which sample don't see functions outside the sample.
+        let unsampled = !self.in_sample(fk);
+        if unsampled {
+            self.unsampled += 1;
+        }
+        self.each(|m| m.visit_fn(fk, s));
+        visit::walk_fn(self, fk, s);
+        self.each(|m| m.exit_fn(fk, s));
+        if unsampled {
+            self.unsampled -= 1;
+        }
+    }
+
+    fn visit_block(&mut self, b: &'a ast::Block) {
+        self.each(|m| m.visit_block(b));
+        visit::walk_block(self, b);
+        self.each(|m| m.exit_block(b));
+    }
+
+    fn visit_stmt(&mut self, s: &'a ast::Stmt) {
+        self.each(|m| m.visit_stmt(s));
+        visit::walk_stmt(self, s);
+        self.each(|m| m.exit_stmt(s));
+    }
+
+    fn visit_expr(&mut self, e: &'a ast::Expr) {
+        self.each(|m| m.visit_expr(e));
+        visit::walk_expr(self, e);
+        self.each(|m| m.exit_expr(e));
+    }
+
+    fn visit_pat(&mut self, p: &'a ast::Pat) {
+        self.each(|m| m.visit_pat(p));
+        visit::walk_pat(self, p)
+    }
+
+    fn visit_ty(&mut self, t: &'a ast::Ty) {
+        self.each(|m| m.visit_ty(t));
+        visit::walk_ty(self, t);
+        self.each(|m| m.exit_ty(t));
+    }
+
+    fn visit_param_bound(&mut self, bound: &'a ast::GenericBound) {
+        self.each(|m| m.visit_param_bound(bound));
+        visit::walk_param_bound(self, bound)
+    }
+
+    // We found a macro.
+    fn visit_mac_call(&mut self, mac: &'a ast::MacCall) {
+        self.each(|m| m.visit_mac_call(mac));
+        // Keep walking.
+        visit::walk_mac(self, mac)
+    }
+
+    fn visit_attribute(&mut self, attr: &'a ast::Attribute) {
+        self.each(|m| m.visit_attribute(attr));
+        visit::walk_attribute(self, attr)
+    }
+
+    fn visit_lifetime(&mut self, lifetime: &'a ast::Lifetime) {
+        self.each(|m| m.visit_lifetime(lifetime));
+        visit::walk_lifetime(self, lifetime)
+    }
+}
+
+/// Adds the correct --sysroot option, unless `args` (rustc's arguments) already
+/// has one.
+pub fn sys_root(args: &[String]) -> Vec<String> {
+    if args.iter().any(|a| a == "--sysroot" || a.starts_with("--sysroot=")) {
+        return vec![];
+    }
+    // If we can't find one, rustc looks next to its own libraries, which is
+    // right if we were installed into the toolchain.
+    match sysroot_path() {
+        Some(sysroot) => vec!["--sysroot".into(), sysroot],
+        None => vec![],
+    }
+}
+
+// The sysroot to compile with: `SYSROOT` if it's set, what rustc says if we
+// can run it, or else the toolchain we were built with, if we were built by
+// rustup.
+pub fn sysroot_path() -> Option<String> {
+    if let Some(sysroot) = std::env::var("SYSROOT").ok().filter(|s| !s.is_empty()) {
+        return Some(sysroot);
+    }
+    if let Some(sysroot) = rustc_sysroot() {
+        return Some(sysroot);
+    }
+    let home = option_env!("RUSTUP_HOME")?;
+    let toolchain = option_env!("RUSTUP_TOOLCHAIN")?;
+    Some(format!("{}/toolchains/{}", home, toolchain))
+}
+
+// Asks the `rustc` on the `PATH` for its sysroot. (Not `RUSTC`, which might be
+// us.)
+fn rustc_sysroot() -> Option<String> {
+    let output = Command::new("rustc").args(&["--print", "sysroot"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let sysroot = String::from_utf8(output.stdout).ok()?;
+    Some(sysroot.trim().to_owned()).filter(|s| !s.is_empty())
+}
+
+// Parses `source` as a crate and passes it to `f`, for the metrics' tests.
+#[cfg(test)]
+pub fn with_test_crate<T, F: FnOnce(&ast::Crate) -> T>(source: &str, f: F) -> T {
+    rustc_span::with_default_session_globals(|| {
+        let sess = rustc_session::parse::ParseSess::with_silent_emitter(None);
+        let name = rustc_span::FileName::Custom("test.rs".to_owned());
+        let krate = rustc_parse::parse_crate_from_source_str(name, source.to_owned(), &sess)
+            .map_err(|mut e| e.cancel())
+            .expect("the test crate doesn't parse");
+        f(&krate)
+    })
+}
@@ -0,0 +1,203 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How are lifetimes written? We count uses of named lifetimes, `'_`, and
+// `'static` in types and bounds (but not where they're declared, e.g., `<'a>`),
+// and references with no lifetime written at all. We also classify function
+// signatures by whether their lifetimes are fully elided, use `'_`, or are
+// named. This is useful for tracking migration to newer elision styles.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_span::Span;
+use rustc_span::symbol::kw;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+#[derive(Default)]
+struct LifetimeCounts {
+    named: Counter,
+    anonymous: Counter,
+    statics: Counter,
+    // References with no lifetime written at all, e.g., `&T`.
+    elided: Counter,
+}
+
+impl LifetimeCounts {
+    fn record(&mut self, lifetime: &ast::Lifetime) {
+        match lifetime.ident.name {
+            kw::UnderscoreLifetime => self.anonymous.increment(),
+            kw::StaticLifetime => self.statics.increment(),
+            _ => self.named.increment(),
+        }
+    }
+
+    // The lifetimes written in the type itself, e.g., `&'a T` or `Foo<'a>`. The
+    // types inside it are visited separately.
+    fn record_ty(&mut self, ty: &ast::Ty) {
+        match ty.kind {
+            ast::TyKind::Rptr(Some(ref lifetime), _) => self.record(lifetime),
+            ast::TyKind::Rptr(None, _) => self.elided.increment(),
+            ast::TyKind::Path(_, ref path) => self.record_path(path),
+            _ => {}
+        }
+    }
+
+    // E.g., `T: 'a` or `T: Foo<'a>`.
+    fn record_bound(&mut self, bound: &ast::GenericBound) {
+        match *bound {
+            ast::GenericBound::Outlives(ref lifetime) => self.record(lifetime),
+            ast::GenericBound::Trait(ref poly, _) => self.record_path(&poly.trait_ref.path),
+        }
+    }
+
+    fn record_path(&mut self, path: &ast::Path) {
+        for segment in &path.segments {
+            if let Some(box ast::GenericArgs::AngleBracketed(ref args)) = segment.args {
+                for arg in &args.args {
+                    if let ast::AngleBracketedArg::Arg(ast::GenericArg::Lifetime(ref lifetime)) =
+                        *arg
+                    {
+                        self.record(lifetime);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Counts the lifetimes in a single signature.
+struct SigVisitor {
+    counts: LifetimeCounts,
+}
+
+impl<'a> visit::Visitor<'a> for SigVisitor {
+    fn visit_ty(&mut self, ty: &'a ast::Ty) {
+        self.counts.record_ty(ty);
+        visit::walk_ty(self, ty)
+    }
+
+    fn visit_param_bound(&mut self, bound: &'a ast::GenericBound) {
+        self.counts.record_bound(bound);
+        visit::walk_param_bound(self, bound)
+    }
+}
+
+pub struct LifetimesMetric {
+    counts: LifetimeCounts,
+    // Signatures with references, but where no lifetime is written.
+    fully_elided_sigs: Counter,
+    // Signatures using `'_` (and maybe named lifetimes too).
+    anonymous_sigs: Counter,
+    // Signatures using only named lifetimes.
+    named_sigs: Counter,
+}
+
+impl LifetimesMetric {
+    pub fn new() -> LifetimesMetric {
+        LifetimesMetric {
+            counts: LifetimeCounts::default(),
+            fully_elided_sigs: Counter::new(),
+            anonymous_sigs: Counter::new(),
+            named_sigs: Counter::new(),
+        }
+    }
+}
+
+impl Metric for LifetimesMetric {
+    fn id(&self) -> &'static str {
+        "lifetimes"
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(_, _, sig, ..) = fk {
+            let mut sig_visitor = SigVisitor {
+                counts: LifetimeCounts::default(),
+            };
+            visit::walk_fn_decl(&mut sig_visitor, &sig.decl);
+
+            let sig = sig_visitor.counts;
+            if sig.anonymous.get() > 0 {
+                self.anonymous_sigs.increment();
+            } else if sig.named.get() > 0 || sig.statics.get() > 0 {
+                self.named_sigs.increment();
+            } else if sig.elided.get() > 0 {
+                self.fully_elided_sigs.increment();
+            }
+        }
+    }
+
+    fn visit_ty(&mut self, ty: &ast::Ty) {
+        self.counts.record_ty(ty);
+    }
+
+    fn visit_param_bound(&mut self, bound: &ast::GenericBound) {
+        self.counts.record_bound(bound);
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "Found {} uses of named lifetimes, {} of `'_`, and {} of `'static`;",
+            self.counts.named, self.counts.anonymous, self.counts.statics
+        )?;
+        writeln!(out, "{} references have no lifetime written;", self.counts.elided)?;
+        writeln!(
+            out,
+            "Of the function signatures with lifetimes, {} are fully elided, {} use `'_`, and {} use named lifetimes;",
+            self.fully_elided_sigs, self.anonymous_sigs, self.named_sigs
+        )
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(LifetimesMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("lifetimes.named", self.counts.named);
+        stats.set_count("lifetimes.anonymous", self.counts.anonymous);
+        stats.set_count("lifetimes.static", self.counts.statics);
+        stats.set_count("lifetimes.elided", self.counts.elided);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metrics::Stage;
+    use {with_test_crate, StupidVisitor};
+
+    fn counts(source: &str) -> Vec<usize> {
+        let stats = with_test_crate(source, |krate| {
+            let mut visitor = StupidVisitor::new(vec![Box::new(LifetimesMetric::new())]);
+            visitor.walk(krate, Stage::Parse);
+            visitor.crate_stats("foo")
+        });
+        ["named", "anonymous", "static", "elided"]
+            .iter()
+            .map(|kind| stats.counts[&format!("lifetimes.{}", kind)].get())
+            .collect()
+    }
+
+    #[test]
+    fn declarations_arent_uses() {
+        assert_eq!(counts("fn f<'a>(x: u32) {}"), vec![0, 0, 0, 0]);
+        assert_eq!(counts("struct S<'a, 'b: 'a>(&'a u32, &'b u32);"), vec![3, 0, 0, 0]);
+        assert_eq!(counts("fn f(g: &dyn for<'a> Fn(&'a u32)) {}"), vec![1, 0, 0, 1]);
+    }
+
+    #[test]
+    fn uses() {
+        assert_eq!(counts("fn f<'a>(x: &'a str, y: &str) -> Cow<'a, str> { x }"), vec![2, 0, 0, 1]);
+        assert_eq!(counts("impl Foo<'_> { fn f(&self) -> &'static str {} }"), vec![0, 1, 1, 1]);
+        assert_eq!(counts("fn f<T: 'static + Foo<'_>>() {}"), vec![0, 1, 1, 0]);
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Formatting hygiene of the crate's source files: line endings, byte order
+// marks, tabs vs spaces for indentation, and long lines. This is mostly
+// interesting for code which isn't formatted with rustfmt.
+//
+// The source map's copy of each file has had its line endings normalised and
+// its byte order mark removed, so we read the files again from disk.
+
+use std::fmt::{self, Write};
+use std::fs;
+use std::path::PathBuf;
+
+use rustc_span::FileName;
+use rustc_span::source_map::SourceMap;
+
+use counter::Counter;
+use metrics::{Metric, ReportContext, Stage};
+
+// rustfmt's default maximum width.
+const MAX_WIDTH: usize = 100;
+
+#[derive(Default)]
+struct FileStats {
+    crlf: Counter,
+    lf: Counter,
+    bom: bool,
+    tab_indented: Counter,
+    space_indented: Counter,
+    long_lines: Counter,
+    // The longest line's length (in chars) and number.
+    longest: (usize, usize),
+}
+
+impl FileStats {
+    fn scan(src: &str) -> FileStats {
+        let mut stats = FileStats::default();
+        stats.bom = src.starts_with('\u{feff}');
+        let crlf = src.matches("\r\n").count();
+        stats.crlf = Counter::from(crlf);
+        stats.lf = Counter::from(src.matches('\n').count() - crlf);
+        // `lines` strips both kinds of line ending.
+        for (i, line) in src.lines().enumerate() {
+            match line.chars().next() {
+                Some('\t') => stats.tab_indented.increment(),
+                Some(' ') => stats.space_indented.increment(),
+                _ => {}
+            }
+            let len = line.chars().count();
+            if len > MAX_WIDTH {
+                stats.long_lines.increment();
+            }
+            if len > stats.longest.0 {
+                stats.longest = (len, i + 1);
+            }
+        }
+        stats
+    }
+}
+
+pub struct LineEndingsMetric {
+    files: Vec<(PathBuf, FileStats)>,
+}
+
+impl LineEndingsMetric {
+    pub fn new() -> LineEndingsMetric {
+        LineEndingsMetric {
+            files: vec![],
+        }
+    }
+}
+
+impl Metric for LineEndingsMetric {
+    fn id(&self) -> &'static str {
+        "line-endings"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Source]
+    }
+
+    fn per_module(&self) -> bool {
+        false
+    }
+
+    // It reads every source file.
+    fn whole_crate(&self) -> bool {
+        true
+    }
+
+    fn check_source(&mut self, source_map: &SourceMap) {
+        for file in source_map.files().iter() {
+            // Skip files from other crates and ones which aren't really files.
+            if file.is_imported() {
+                continue;
+            }
+            let path = match file.name {
+                FileName::Real(ref name) => name.local_path().to_owned(),
+                _ => continue,
+            };
+            if let Ok(src) = fs::read_to_string(&path) {
+                self.files.push((path, FileStats::scan(&src)));
+            }
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let crlf = self.files.iter().filter(|f| f.1.crlf.get() > 0 && f.1.lf.get() == 0).count();
+        let mixed = self.files.iter().filter(|f| f.1.crlf.get() > 0 && f.1.lf.get() > 0).count();
+        let bom = self.files.iter().filter(|f| f.1.bom).count();
+        writeln!(
+            out,
+            "Of {} source files, {} use CRLF line endings, {} mix CRLF and LF, and {} start with a byte order mark;",
+            self.files.len(),
+            crlf,
+            mixed,
+            bom
+        )?;
+
+        let tabs = Counter::sum(self.files.iter().map(|f| &f.1.tab_indented));
+        let spaces = Counter::sum(self.files.iter().map(|f| &f.1.space_indented));
+        writeln!(out, "{} lines are indented with tabs and {} with spaces;", tabs, spaces)?;
+
+        let long = Counter::sum(self.files.iter().map(|f| &f.1.long_lines));
+        writeln!(out, "{} lines are longer than {} characters;", long, MAX_WIDTH)?;
+        if let Some(&(ref path, ref stats)) = self.files.iter().max_by_key(|f| f.1.longest.0) {
+            writeln!(
+                out,
+                "    the longest is {} characters: {}:{}",
+                stats.longest.0,
+                path.display(),
+                stats.longest.1
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(LineEndingsMetric::new())
+    }
+}
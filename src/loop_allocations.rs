@@ -0,0 +1,189 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Heap allocations inside loops, which are worth a look when something is
+// slow: allocating constructors (e.g., `Box::new`, `Vec::with_capacity`),
+// methods which usually allocate (e.g., `to_string`, `collect`), and the
+// `vec!` and `format!` macros, anywhere in a loop's body (or a `while` loop's
+// condition, which also runs every time round). We go by names, so this is a
+// heuristic: `Vec::new` doesn't allocate until it grows, and a `collect` might
+// not make a collection.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+use rustc_span::Span;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+// How many of the places to list.
+const TOP_PLACES: usize = 10;
+
+// `Type::function`s which allocate.
+const CONSTRUCTORS: &[(&str, &str)] = &[
+    ("Box", "new"),
+    ("Rc", "new"),
+    ("Arc", "new"),
+    ("Vec", "with_capacity"),
+    ("Vec", "from"),
+    ("String", "with_capacity"),
+    ("String", "from"),
+    ("HashMap", "with_capacity"),
+    ("HashSet", "with_capacity"),
+];
+const METHODS: &[&str] = &["to_string", "to_owned", "to_vec", "collect"];
+const MACROS: &[&str] = &["vec", "format"];
+
+pub struct LoopAllocationsMetric {
+    // How many of each allocation, e.g., `Box::new` or `.collect()`.
+    allocations: BTreeMap<String, Counter>,
+    places: Vec<(String, Span)>,
+    // How many loops we're in, in the function we're in.
+    loop_depth: usize,
+    // The bodies (and `while` conditions) of the loops we've seen but not got
+    // inside yet, and those we're inside, innermost last.
+    pending: HashSet<Span>,
+    entered: Vec<Span>,
+    // The loop depth outside each item we're in.
+    item_depths: Vec<usize>,
+}
+
+impl LoopAllocationsMetric {
+    pub fn new() -> LoopAllocationsMetric {
+        LoopAllocationsMetric {
+            allocations: BTreeMap::new(),
+            places: vec![],
+            loop_depth: 0,
+            pending: HashSet::new(),
+            entered: vec![],
+            item_depths: vec![],
+        }
+    }
+
+    // Called for each block and expression, in case it's what a loop runs.
+    fn enter(&mut self, span: Span) {
+        if self.pending.remove(&span) {
+            self.loop_depth += 1;
+            self.entered.push(span);
+        }
+    }
+
+    fn exit(&mut self, span: Span) {
+        if self.entered.last() == Some(&span) {
+            self.entered.pop();
+            self.loop_depth -= 1;
+        }
+    }
+
+    fn record(&mut self, name: String, span: Span) {
+        if self.loop_depth == 0 {
+            return;
+        }
+        self.allocations.entry(name.clone()).or_insert_with(Counter::new).increment();
+        self.places.push((name, span));
+    }
+}
+
+impl Metric for LoopAllocationsMetric {
+    fn id(&self) -> &'static str {
+        "loop-allocations"
+    }
+
+    // Items in a loop's body aren't run by the loop.
+    fn visit_item(&mut self, _: &ast::Item) {
+        self.item_depths.push(self.loop_depth);
+        self.loop_depth = 0;
+    }
+
+    fn exit_item(&mut self, _: &ast::Item) {
+        self.loop_depth = self.item_depths.pop().unwrap();
+    }
+
+    fn visit_block(&mut self, b: &ast::Block) {
+        self.enter(b.span);
+    }
+
+    fn exit_block(&mut self, b: &ast::Block) {
+        self.exit(b.span);
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        self.enter(e.span);
+        match e.kind {
+            ast::ExprKind::While(ref cond, ref body, _) => {
+                self.pending.insert(cond.span);
+                self.pending.insert(body.span);
+            }
+            // The iterator is only made once.
+            ast::ExprKind::ForLoop(_, _, ref body, _) | ast::ExprKind::Loop(ref body, _) => {
+                self.pending.insert(body.span);
+            }
+            ast::ExprKind::Call(ref f, _) => {
+                if let ast::ExprKind::Path(None, ref path) = f.kind {
+                    let n = path.segments.len();
+                    if n >= 2 {
+                        let ty = path.segments[n - 2].ident.as_str();
+                        let function = path.segments[n - 1].ident.as_str();
+                        if CONSTRUCTORS.iter().any(|&(t, f)| t == &*ty && f == &*function) {
+                            self.record(format!("{}::{}", ty, function), e.span);
+                        }
+                    }
+                }
+            }
+            ast::ExprKind::MethodCall(ref segment, ..) => {
+                let name = segment.ident.as_str();
+                if METHODS.contains(&&*name) {
+                    self.record(format!(".{}()", name), e.span);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn exit_expr(&mut self, e: &ast::Expr) {
+        self.exit(e.span);
+    }
+
+    fn visit_mac_call(&mut self, mac: &ast::MacCall) {
+        if let Some(name) = mac.path.segments.last().map(|s| s.ident.to_string()) {
+            // An empty `vec![]` doesn't allocate.
+            let empty = match *mac.args {
+                ast::MacArgs::Delimited(_, _, ref tts) => tts.is_empty(),
+                _ => false,
+            };
+            if MACROS.contains(&&*name) && !(name == "vec" && empty) {
+                self.record(format!("{}!", name), mac.span());
+            }
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(out, "Found {} allocations inside loops;", self.places.len())?;
+        for (name, count) in &self.allocations {
+            writeln!(out, "    `{}`: {}", name, count)?;
+        }
+        if !self.places.is_empty() {
+            writeln!(out, "Allocations in loops at:")?;
+            for &(ref name, span) in self.places.iter().take(TOP_PLACES) {
+                writeln!(out, "    `{}` at {}", name, cx.source_map.span_to_string(span))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(LoopAllocationsMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("loop-allocations.allocations", self.places.len());
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Where macros are used. A macro in an expression or a statement only affects
+// that code, but one in a type (especially in a function's signature) or one
+// which generates items shapes the crate's API, and an attribute macro (e.g.,
+// `#[async_trait]` or `#[pin_project]`) rewrites the whole item it's on, so
+// they're worth knowing about separately. We look before expansion, so
+// attribute macros are attributes on items which aren't built in or for a
+// tool; without name resolution we can't tell them from a derive's helper
+// attributes (e.g., `#[serde(..)]`), so those count too.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+// How many of the most used type and attribute macros to list.
+const TOP_MACROS: usize = 10;
+
+const TOOLS: &[&str] = &["rustfmt", "clippy", "rustdoc"];
+
+// E.g., `#[rustfmt::skip]`.
+fn is_tool_attr(path: &ast::Path) -> bool {
+    path.segments.len() > 1 && TOOLS.contains(&&*path.segments[0].ident.as_str())
+}
+
+fn record(counts: &mut HashMap<String, Counter>, path: &ast::Path) {
+    counts.entry(pprust::path_to_string(path)).or_default().increment();
+}
+
+fn write_top(counts: &HashMap<String, Counter>, suffix: &str, out: &mut String) -> fmt::Result {
+    let mut counts: Vec<_> = counts.iter().collect();
+    counts.sort_by(|a, b| b.1.get().cmp(&a.1.get()).then(a.0.cmp(b.0)));
+    for (path, count) in counts.into_iter().take(TOP_MACROS) {
+        writeln!(out, "    `{}{}`: {}", path, suffix, count)?;
+    }
+    Ok(())
+}
+
+pub struct MacroPositionsMetric {
+    expressions: Counter,
+    items: Counter,
+    patterns: Counter,
+    types: HashMap<String, Counter>,
+    // How many of the macros in types are in function signatures.
+    in_signatures: Counter,
+    attributes: HashMap<String, Counter>,
+    // The signatures of the functions we're in, innermost last.
+    signatures: Vec<Span>,
+}
+
+impl MacroPositionsMetric {
+    pub fn new() -> MacroPositionsMetric {
+        MacroPositionsMetric {
+            expressions: Counter::new(),
+            items: Counter::new(),
+            patterns: Counter::new(),
+            types: HashMap::new(),
+            in_signatures: Counter::new(),
+            attributes: HashMap::new(),
+            signatures: vec![],
+        }
+    }
+
+    fn check_attrs(&mut self, attrs: &[ast::Attribute]) {
+        for attr in attrs {
+            if rustc_attr::is_builtin_attr(attr) {
+                continue;
+            }
+            if let ast::AttrKind::Normal(ref item, ..) = attr.kind {
+                if !is_tool_attr(&item.path) {
+                    record(&mut self.attributes, &item.path);
+                }
+            }
+        }
+    }
+}
+
+impl Metric for MacroPositionsMetric {
+    fn id(&self) -> &'static str {
+        "macro-positions"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        self.check_attrs(&i.attrs);
+        if let ast::ItemKind::MacCall(..) = i.kind {
+            self.items.increment();
+        }
+    }
+
+    fn visit_assoc_item(&mut self, i: &ast::AssocItem, _: visit::AssocCtxt) {
+        self.check_attrs(&i.attrs);
+        if let ast::AssocItemKind::MacCall(..) = i.kind {
+            self.items.increment();
+        }
+    }
+
+    fn visit_foreign_item(&mut self, i: &ast::ForeignItem) {
+        self.check_attrs(&i.attrs);
+        if let ast::ForeignItemKind::MacCall(..) = i.kind {
+            self.items.increment();
+        }
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(_, _, sig, ..) = fk {
+            self.signatures.push(sig.span);
+        }
+    }
+
+    fn exit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(..) = fk {
+            self.signatures.pop();
+        }
+    }
+
+    fn visit_stmt(&mut self, s: &ast::Stmt) {
+        if let ast::StmtKind::MacCall(..) = s.kind {
+            self.expressions.increment();
+        }
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        if let ast::ExprKind::MacCall(..) = e.kind {
+            self.expressions.increment();
+        }
+    }
+
+    fn visit_pat(&mut self, p: &ast::Pat) {
+        if let ast::PatKind::MacCall(..) = p.kind {
+            self.patterns.increment();
+        }
+    }
+
+    fn visit_ty(&mut self, t: &ast::Ty) {
+        if let ast::TyKind::MacCall(ref mac) = t.kind {
+            record(&mut self.types, &mac.path);
+            if self.signatures.last().map_or(false, |sig| sig.contains(t.span)) {
+                self.in_signatures.increment();
+            }
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let types = Counter::sum(self.types.values());
+        writeln!(
+            out,
+            "Found {} macro calls in expressions and statements, {} as items, {} in patterns, \
+             and {} in types ({} in function signatures);",
+            self.expressions,
+            self.items,
+            self.patterns,
+            types,
+            self.in_signatures
+        )?;
+        if types.get() > 0 {
+            writeln!(out, "The most used macros in types are:")?;
+            write_top(&self.types, "!", out)?;
+        }
+
+        let attributes = Counter::sum(self.attributes.values());
+        writeln!(out, "Found {} attribute macros (or derive helpers) on items;", attributes)?;
+        if attributes.get() > 0 {
+            writeln!(out, "The most used are:")?;
+            write_top(&self.attributes, "", out)?;
+        }
+        Ok(())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("macro-positions.types", Counter::sum(self.types.values()));
+        stats.set_count("macro-positions.in_signatures", self.in_signatures);
+        stats.set_count("macro-positions.attributes", Counter::sum(self.attributes.values()));
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(MacroPositionsMetric::new())
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How much code each macro call expands to. We walk the crate after expansion
+// and count the AST nodes (items, statements, expressions, patterns, and types)
+// which came from each macro call in the user's code. Calls which expand to a
+// lot of code are good targets for speeding up a build.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+use rustc_span::Span;
+
+use counter::Counter;
+use expansion_depth::expansion_chain;
+use metrics::{Metric, ReportContext, Stage};
+
+// How many of the biggest expansions to list.
+const TOP_CALLS: usize = 10;
+
+pub struct MacroSizesMetric {
+    // For each macro call in the user's code, the macro and how many nodes it
+    // expanded to.
+    calls: HashMap<Span, (String, Counter)>,
+}
+
+impl MacroSizesMetric {
+    pub fn new() -> MacroSizesMetric {
+        MacroSizesMetric {
+            calls: HashMap::new(),
+        }
+    }
+
+    fn record(&mut self, span: Span) {
+        if let Some((call_site, mut chain)) = expansion_chain(span) {
+            let call = self
+                .calls
+                .entry(call_site)
+                .or_insert_with(|| (chain.swap_remove(0), Counter::new()));
+            call.1.increment();
+        }
+    }
+}
+
+impl Metric for MacroSizesMetric {
+    fn id(&self) -> &'static str {
+        "macro-sizes"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Expansion]
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        self.record(i.span);
+    }
+
+    fn visit_stmt(&mut self, s: &ast::Stmt) {
+        self.record(s.span);
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        self.record(e.span);
+    }
+
+    fn visit_pat(&mut self, p: &ast::Pat) {
+        self.record(p.span);
+    }
+
+    fn visit_ty(&mut self, t: &ast::Ty) {
+        self.record(t.span);
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let total = Counter::sum(self.calls.values().map(|c| &c.1));
+        let average = if self.calls.is_empty() {
+            0.0
+        } else {
+            total.get() as f64 / self.calls.len() as f64
+        };
+        writeln!(
+            out,
+            "{} macro calls expand to {} AST nodes, {:.1} on average;",
+            self.calls.len(),
+            total,
+            average
+        )?;
+
+        let mut calls: Vec<_> = self.calls.iter().collect();
+        calls.sort_by(|a, b| {
+            (b.1).1.get().cmp(&(a.1).1.get()).then(a.0.lo().cmp(&b.0.lo()))
+        });
+        if calls.is_empty() {
+            return Ok(());
+        }
+        writeln!(out, "The biggest expansions are:")?;
+        for (&span, &(ref name, nodes)) in calls.into_iter().take(TOP_CALLS) {
+            writeln!(out, "    {} nodes from {} at {}", nodes, name, cx.source_map.span_to_string(span))?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(MacroSizesMetric::new())
+    }
+}
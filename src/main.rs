@@ -0,0 +1,150 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The `stupid` driver: rustc, plus our stats. See `lib.rs` for the analysis.
+
+#![feature(rustc_private)]
+
+extern crate rustc_driver;
+extern crate stupid_stats;
+
+use rustc_driver::RunCompiler;
+
+use std::fs;
+use std::path::Path;
+
+use stupid_stats::{api, completions, doctor, merge, sys_root, sysroot_path, StupidCalls};
+use stupid_stats::metrics::describe_metrics;
+use stupid_stats::options::Options;
+
+// When Cargo runs us as `RUSTC_WRAPPER`, our first argument is the path to
+// rustc. We don't need it, since we are rustc.
+fn is_wrapper(args: &[String]) -> bool {
+    args.get(1).map_or(false, |arg| Path::new(arg).file_stem().map_or(false, |s| s == "rustc"))
+}
+
+fn main() {
+    if std::env::args().nth(1).map_or(false, |arg| arg == "doctor") {
+        let ok = doctor::run(sysroot_path().as_ref().map(|s| &**s));
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    // `stupid api-diff <old> <new>` compares two inventories saved with
+    // `STUPID_STATS_API_INVENTORY`, and fails if anything was removed or changed.
+    if std::env::args().nth(1).map_or(false, |arg| arg == "api-diff") {
+        let paths: Vec<_> = std::env::args().skip(2).collect();
+        if paths.len() != 2 {
+            eprintln!("usage: stupid api-diff <old inventory> <new inventory>");
+            std::process::exit(2);
+        }
+        let read = |path: &str| {
+            fs::read_to_string(path).unwrap_or_else(|e| {
+                eprintln!("error: can't read `{}`: {}", path, e);
+                std::process::exit(2);
+            })
+        };
+        let mut out = String::new();
+        let breaking = api::diff(&read(&paths[0]), &read(&paths[1]), &mut out).unwrap();
+        print!("{}", out);
+        std::process::exit(if breaking { 1 } else { 0 });
+    }
+
+    // `stupid merge <dir>` combines the stats saved with `STUPID_STATS_JSON_DIR`.
+    if std::env::args().nth(1).map_or(false, |arg| arg == "merge") {
+        let dir = match std::env::args().nth(2) {
+            Some(dir) => dir,
+            None => {
+                eprintln!("usage: stupid merge <dir>");
+                std::process::exit(2);
+            }
+        };
+        let crates = merge::read_dir(Path::new(&dir)).unwrap_or_else(|e| {
+            eprintln!("error: can't read `{}`: {}", dir, e);
+            std::process::exit(2);
+        });
+        let mut out = String::new();
+        merge::write_table(&crates, &mut out).unwrap();
+        print!("{}", out);
+        return;
+    }
+
+    // `stupid completions <shell>` prints a completion script for the shell.
+    if std::env::args().nth(1).map_or(false, |arg| arg == "completions") {
+        let shell = match std::env::args().nth(2) {
+            Some(shell) => shell,
+            None => {
+                eprintln!("usage: stupid completions <bash|zsh|fish>");
+                std::process::exit(2);
+            }
+        };
+        let mut out = String::new();
+        if !completions::write_script(&shell, &mut out).unwrap() {
+            eprintln!("error: unknown shell `{}`, expected bash, zsh, or fish", shell);
+            std::process::exit(2);
+        }
+        print!("{}", out);
+        return;
+    }
+
+    if std::env::args().any(|arg| arg == "--describe-metrics") {
+        let mut out = String::new();
+        describe_metrics(&mut out).unwrap();
+        print!("{}", out);
+        return;
+    }
+
+    let mut gates_failed = false;
+    let exit_code = rustc_driver::catch_with_exit_code(|| {
+        // Grab the command line arguments.
+        let mut args: Vec<_> = std::env::args_os().flat_map(|s| s.into_string()).collect();
+        let mut options = Options::from_env();
+        if let Err(e) = options.take_flags(&mut args) {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+        let wrapper = is_wrapper(&args);
+        if wrapper {
+            args.remove(1);
+        }
+        // Run by hand, we're only wanted for the stats, so there's no point
+        // generating code.
+        options.no_codegen = options.no_codegen.or(Some(!wrapper));
+        // Cargo sets `CARGO_PRIMARY_PACKAGE` for the crates the user asked to
+        // build, we keep quiet for all the others. If we are saving all the
+        // reports, we keep quiet for everything.
+        let quiet = wrapper &&
+            (std::env::var_os("CARGO_PRIMARY_PACKAGE").is_none() || options.report_dir.is_some());
+
+        let args2 = args.iter()
+            .map(|s| (*s).to_string())
+            .chain(sys_root(&args).into_iter())
+            .collect::<Vec<_>>();
+
+        // Only the crates we report on are checked against `--fail-if`.
+        let gated = !wrapper || std::env::var_os("CARGO_PRIMARY_PACKAGE").is_some();
+        let mut calls = StupidCalls::new(quiet, options);
+        let result = RunCompiler::new(&args2, &mut calls).run();
+        // If rustc couldn't parse the crate, the build has failed either way,
+        // but we can still give somebody running us by hand some stats. (Any
+        // later error is the crate's, and the fallback would only hide it.)
+        if result.is_err() && !wrapper {
+            calls.fallback(&args);
+        }
+        result?;
+        if gated {
+            for failure in calls.failed_gates() {
+                eprintln!("stupid-stats: failed: {}", failure);
+                gates_failed = true;
+            }
+        }
+        Ok(())
+    });
+    // Exit with whatever code rustc would have, so that we don't change the
+    // result of a build, unless a `--fail-if` condition held.
+    std::process::exit(if exit_code == 0 && gates_failed { 1 } else { exit_code });
+}
@@ -0,0 +1,100 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Copy-pasted match arms. We hash the pretty-printed body of every match arm
+// and report bodies which occur more than once, either within one match (where
+// the patterns could be combined with `|`) or across matches (where a helper
+// function might be better).
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::{self, Write};
+use std::hash::{Hash, Hasher};
+
+use rustc_ast::ast;
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+// Bodies shorter than this (when pretty printed) are too trivial to count as
+// duplicates, e.g., `None` or `return`.
+const MIN_BODY_LEN: usize = 20;
+// How many groups of duplicates to list.
+const TOP_GROUPS: usize = 10;
+
+pub struct MatchArmsMetric {
+    // Each `match` gets a number, so we can tell if duplicates are in the same
+    // match.
+    match_count: usize,
+    // Arms, grouped by the hash of their body, with the match they are in.
+    arms: HashMap<u64, Vec<(usize, Span)>>,
+}
+
+impl MatchArmsMetric {
+    pub fn new() -> MatchArmsMetric {
+        MatchArmsMetric {
+            match_count: 0,
+            arms: HashMap::new(),
+        }
+    }
+}
+
+impl Metric for MatchArmsMetric {
+    fn id(&self) -> &'static str {
+        "match-arms"
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        if let ast::ExprKind::Match(_, ref arms) = e.kind {
+            self.match_count += 1;
+            for arm in arms {
+                let body = pprust::expr_to_string(&arm.body);
+                if body.len() < MIN_BODY_LEN {
+                    continue;
+                }
+                let mut hasher = DefaultHasher::new();
+                body.hash(&mut hasher);
+                self.arms.entry(hasher.finish()).or_default().push((self.match_count, arm.span));
+            }
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let mut groups: Vec<_> = self.arms.values().filter(|arms| arms.len() > 1).collect();
+        let within = groups
+            .iter()
+            .filter(|arms| arms.iter().all(|&(m, _)| m == arms[0].0))
+            .count();
+        writeln!(
+            out,
+            "Found {} sets of match arms with identical bodies ({} within a single match, {} across \
+             matches);",
+            groups.len(),
+            within,
+            groups.len() - within
+        )?;
+
+        groups.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].1.lo().cmp(&b[0].1.lo())));
+        for arms in groups.into_iter().take(TOP_GROUPS) {
+            let first = cx.source_map.span_to_string(arms[0].1);
+            writeln!(out, "    {} copies, first at {}", arms.len(), first)?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(MatchArmsMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        let sets = self.arms.values().filter(|arms| arms.len() > 1).count();
+        stats.set_count("match-arms.duplicate_sets", sets);
+    }
+}
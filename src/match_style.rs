@@ -0,0 +1,193 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// `match` where `if` would do, and the other way round. A `match` with two
+// arms on `true` and `false` could be an `if`, and one with a `Some(..)` arm
+// and a `None` (or `_`) arm could often be an `if let`. Going the other way,
+// an `if`-`else if` chain which compares the same expression with `==` at
+// every step could be a `match`. We go by the shape of the patterns and
+// conditions, since we don't have types before expansion.
+
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+// How long an `if` chain must be (in comparisons) to be worth a `match`.
+const MIN_CHAIN: usize = 3;
+// How many of the places to list.
+const TOP_PLACES: usize = 10;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ArmShape {
+    True,
+    False,
+    Some,
+    None,
+    Wild,
+    Other,
+}
+
+fn arm_shape(pat: &ast::Pat) -> ArmShape {
+    let last_is = |path: &ast::Path, name: &str| {
+        path.segments.last().map_or(false, |s| &*s.ident.as_str() == name)
+    };
+    match pat.kind {
+        ast::PatKind::Wild => ArmShape::Wild,
+        ast::PatKind::Lit(ref e) => match e.kind {
+            ast::ExprKind::Lit(ast::Lit { kind: ast::LitKind::Bool(true), .. }) => ArmShape::True,
+            ast::ExprKind::Lit(ast::Lit { kind: ast::LitKind::Bool(false), .. }) => ArmShape::False,
+            _ => ArmShape::Other,
+        },
+        ast::PatKind::TupleStruct(ref path, _) if last_is(path, "Some") => ArmShape::Some,
+        // Before name resolution, `None` is just a name.
+        ast::PatKind::Ident(_, ident, None) if &*ident.as_str() == "None" => ArmShape::None,
+        ast::PatKind::Path(None, ref path) if last_is(path, "None") => ArmShape::None,
+        _ => ArmShape::Other,
+    }
+}
+
+// If `cond` is `a == b`, `a` pretty printed.
+fn compared(cond: &ast::Expr) -> Option<String> {
+    match cond.kind {
+        ast::ExprKind::Binary(op, ref lhs, _) if op.node == ast::BinOpKind::Eq => {
+            Some(pprust::expr_to_string(lhs))
+        }
+        _ => None,
+    }
+}
+
+pub struct MatchStyleMetric {
+    matches: Counter,
+    bool_matches: Vec<Span>,
+    option_matches: Vec<Span>,
+    if_chains: Counter,
+    // `if` chains which could be matches, with the expression they compare.
+    matchable_chains: Vec<(String, Span)>,
+    // The `else if`s of chains we've already seen.
+    links: HashSet<Span>,
+}
+
+impl MatchStyleMetric {
+    pub fn new() -> MatchStyleMetric {
+        MatchStyleMetric {
+            matches: Counter::new(),
+            bool_matches: vec![],
+            option_matches: vec![],
+            if_chains: Counter::new(),
+            matchable_chains: vec![],
+            links: HashSet::new(),
+        }
+    }
+
+    fn check_match(&mut self, e: &ast::Expr, arms: &[ast::Arm]) {
+        self.matches.increment();
+        if arms.len() != 2 || arms.iter().any(|a| a.guard.is_some()) {
+            return;
+        }
+        let mut shapes = [arm_shape(&arms[0].pat), arm_shape(&arms[1].pat)];
+        shapes.sort_by_key(|&s| s as u8);
+        match shapes {
+            [ArmShape::True, ArmShape::False] |
+            [ArmShape::True, ArmShape::Wild] |
+            [ArmShape::False, ArmShape::Wild] => self.bool_matches.push(e.span),
+            [ArmShape::Some, ArmShape::None] | [ArmShape::Some, ArmShape::Wild] => {
+                self.option_matches.push(e.span)
+            }
+            _ => {}
+        }
+    }
+
+    // Follows an `if`-`else if` chain from its first `if`, so we see each
+    // chain once: the `else if`s in it are noted in `links` and skipped when
+    // the walk gets to them.
+    fn check_if_chain(&mut self, e: &ast::Expr) {
+        if self.links.remove(&e.span) {
+            return;
+        }
+        let mut compared_exprs = vec![];
+        let mut next = Some(e);
+        while let Some(link) = next {
+            next = None;
+            if let ast::ExprKind::If(ref cond, _, ref els) = link.kind {
+                if !compared_exprs.is_empty() {
+                    self.links.insert(link.span);
+                }
+                compared_exprs.push(compared(cond));
+                next = els.as_ref().map(|e| &**e);
+            }
+        }
+
+        if compared_exprs.len() < 2 {
+            return;
+        }
+        self.if_chains.increment();
+        let first = match compared_exprs[0] {
+            Some(ref first) => first.clone(),
+            None => return,
+        };
+        if compared_exprs.len() >= MIN_CHAIN &&
+            compared_exprs.iter().all(|c| c.as_ref() == Some(&first))
+        {
+            self.matchable_chains.push((first, e.span));
+        }
+    }
+}
+
+impl Metric for MatchStyleMetric {
+    fn id(&self) -> &'static str {
+        "match-style"
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        match e.kind {
+            ast::ExprKind::Match(_, ref arms) => self.check_match(e, arms),
+            ast::ExprKind::If(..) => self.check_if_chain(e),
+            _ => {}
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "Of {} matches, {} on booleans could be `if` and {} on options could be `if let`;",
+            self.matches,
+            self.bool_matches.len(),
+            self.option_matches.len()
+        )?;
+        for &span in self.bool_matches.iter().chain(&self.option_matches).take(TOP_PLACES) {
+            writeln!(out, "    {}", cx.source_map.span_to_string(span))?;
+        }
+        writeln!(
+            out,
+            "Of {} `else if` chains, {} compare the same expression every time and could be `match`;",
+            self.if_chains,
+            self.matchable_chains.len()
+        )?;
+        for &(ref expr, span) in self.matchable_chains.iter().take(TOP_PLACES) {
+            writeln!(out, "    on `{}` at {}", expr, cx.source_map.span_to_string(span))?;
+        }
+        Ok(())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("match-style.bool_matches", self.bool_matches.len());
+        stats.set_count("match-style.option_matches", self.option_matches.len());
+        stats.set_count("match-style.matchable_chains", self.matchable_chains.len());
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(MatchStyleMetric::new())
+    }
+}
@@ -0,0 +1,263 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// `stupid merge <dir>`: combines the summary stats saved for each crate (see
+// `STUPID_STATS_JSON_DIR`) into one report for the workspace, with a row for
+// each crate, grouped by Cargo package, and one for the total. `cargo
+// stupid-stats` ends with the same table, checked against the `--fail-if`
+// conditions.
+
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use args::set_arg_stats;
+use crate_stats::CrateStats;
+use gates;
+
+// Reads every crate's stats in `dir`, as JSON (`.json`) or in the binary form
+// (`.stats`), in order of package, then the package's own crates before its
+// examples, tests, and benchmarks, then crate name. Files we can't make sense
+// of are skipped, and so are build scripts, which aren't part of what a
+// package builds.
+pub fn read_dir(dir: &Path) -> io::Result<Vec<CrateStats>> {
+    let mut crates = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let stats = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => CrateStats::read_json(&fs::read_to_string(&path)?),
+            Some("stats") => CrateStats::read_binary(&fs::read(&path)?),
+            _ => continue,
+        };
+        if let Some(mut stats) = stats {
+            if is_build_script(&stats) {
+                continue;
+            }
+            set_arg_stats(&mut stats);
+            crates.push(stats);
+        }
+    }
+    crates.sort_by_key(|c| (c.package.clone(), target_order(&c.target), c.crate_name.clone()));
+    Ok(crates)
+}
+
+// Cargo builds a package's `build.rs` as a crate called `build_script_build`
+// (or after the `build` key in the manifest, still starting `build_script_`).
+fn is_build_script(stats: &CrateStats) -> bool {
+    !stats.package.is_empty() && stats.crate_name.starts_with("build_script_")
+}
+
+fn target_order(target: &str) -> usize {
+    ["", "example", "test", "bench"].iter().position(|&t| t == target).unwrap_or(4)
+}
+
+// The crate column: the crate's name, and what it is if it's not one of the
+// package's own crates, e.g., `demo (example)`.
+fn crate_column(stats: &CrateStats) -> String {
+    if stats.target.is_empty() {
+        stats.crate_name.clone()
+    } else {
+        format!("{} ({})", stats.crate_name, stats.target)
+    }
+}
+
+// All the crates' stats added together.
+pub fn total(crates: &[CrateStats]) -> CrateStats {
+    let mut total = CrateStats {
+        crate_name: "total".to_owned(),
+        ..CrateStats::default()
+    };
+    for stats in crates {
+        total.println_count.add(stats.println_count.get());
+        for (name, count) in &stats.format_macros {
+            total.format_macros.entry(name.clone()).or_default().add(count.get());
+        }
+        total.arg_counts.merge(&stats.arg_counts);
+        total.generated_lines_excluded += stats.generated_lines_excluded;
+        for (name, count) in &stats.counts {
+            total.counts.entry(name.clone()).or_default().add(count.get());
+        }
+    }
+    set_arg_stats(&mut total);
+    total
+}
+
+pub fn write_table(crates: &[CrateStats], out: &mut String) -> fmt::Result {
+    let widths = widths(crates);
+    write_header(widths, out)?;
+    writeln!(out)?;
+    for stats in crates.iter().chain(Some(&total(crates))) {
+        write_row(stats, widths, out)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+// The table, with whether each crate passes the `--fail-if` conditions and a
+// line of totals, like `cargo deny` does for its checks. The reasons for
+// failures are listed after the table. Returns how many crates failed.
+pub fn write_summary(
+    crates: &[CrateStats],
+    conditions: &[String],
+    out: &mut String,
+) -> Result<usize, fmt::Error> {
+    let widths = widths(crates);
+    write_header(widths, out)?;
+    writeln!(out, "  status")?;
+    let mut failed = 0;
+    let mut failures = vec![];
+    for stats in crates {
+        let crate_failures = gates::check(conditions, stats);
+        write_row(stats, widths, out)?;
+        if crate_failures.is_empty() {
+            writeln!(out, "  ok")?;
+        } else {
+            writeln!(out, "  FAILED")?;
+            failed += 1;
+        }
+        failures.extend(crate_failures);
+    }
+    write_row(&total(crates), widths, out)?;
+    writeln!(out)?;
+
+    writeln!(
+        out,
+        "\n{} crates checked: {} ok, {} failed",
+        crates.len(),
+        crates.len() - failed,
+        failed
+    )?;
+    for failure in &failures {
+        writeln!(out, "    {}", failure)?;
+    }
+    Ok(failed)
+}
+
+// The widths of the package and crate columns. There's only a package column
+// if some of the crates were built by Cargo.
+fn widths(crates: &[CrateStats]) -> (usize, usize) {
+    let package = match crates.iter().map(|c| c.package.len()).max().unwrap_or(0) {
+        0 => 0,
+        width => width.max(7),
+    };
+    let name = crates.iter().map(|c| crate_column(c).len()).max().unwrap_or(0).max(5);
+    (package, name)
+}
+
+fn write_header((package, width): (usize, usize), out: &mut String) -> fmt::Result {
+    if package > 0 {
+        write!(out, "{:width$}  ", "package", width = package)?;
+    }
+    write!(
+        out,
+        "{:width$}  {:>8}  {:>9}  {:>11}  {:>9}  {:>9}",
+        "crate",
+        "println!",
+        "functions",
+        "common args",
+        "mean args",
+        "4+ args",
+        width = width
+    )
+}
+
+fn write_row(
+    stats: &CrateStats,
+    (package, width): (usize, usize),
+    out: &mut String,
+) -> fmt::Result {
+    if package > 0 {
+        write!(out, "{:width$}  ", stats.package, width = package)?;
+    }
+    write!(
+        out,
+        "{:width$}  {:>8}  {:>9}  {:>11}  {:>9.1}  {:>8.0}%",
+        crate_column(stats),
+        stats.println_count,
+        stats.arg_counts.total(),
+        stats.common_args,
+        stats.mean_args,
+        stats.four_or_more_args_percent,
+        width = width
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(name: &str, printlns: usize, args: &[usize]) -> CrateStats {
+        let mut stats = CrateStats {
+            crate_name: name.to_owned(),
+            ..CrateStats::default()
+        };
+        stats.println_count.add(printlns);
+        for &n in args {
+            stats.arg_counts.record(n);
+        }
+        stats.set_count("unsafe.blocks", printlns * 2);
+        set_arg_stats(&mut stats);
+        stats
+    }
+
+    #[test]
+    fn totals() {
+        let total = total(&[stats("a", 1, &[0, 1]), stats("b", 2, &[1, 5])]);
+        assert_eq!(total.crate_name, "total");
+        assert_eq!(total.println_count.get(), 3);
+        assert_eq!(total.arg_counts.total().get(), 4);
+        assert_eq!(total.common_args, 1);
+        assert_eq!(total.max_args, 5);
+        assert_eq!(total.counts["unsafe.blocks"].get(), 6);
+    }
+
+    #[test]
+    fn summary_counts_failures() {
+        let crates = [stats("a", 0, &[0]), stats("b", 2, &[1])];
+        let mut out = String::new();
+        let failed = write_summary(&crates, &["println_count>0".to_owned()], &mut out).unwrap();
+        assert_eq!(failed, 1);
+        let lines: Vec<_> = out.lines().collect();
+        assert!(lines[1].starts_with("a ") && lines[1].ends_with("  ok"));
+        assert!(lines[2].starts_with("b ") && lines[2].ends_with("  FAILED"));
+        assert!(lines[3].starts_with("total "));
+        assert!(out.contains("2 crates checked: 1 ok, 1 failed\n"));
+        assert!(out.contains("    `println_count>0` in crate `b`: println_count is 2\n"));
+    }
+
+    #[test]
+    fn examples_and_tests_are_marked() {
+        let mut example = stats("demo", 1, &[0]);
+        example.target = "example".to_owned();
+        let mut out = String::new();
+        write_table(&[stats("foo", 0, &[1]), example], &mut out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert!(lines[1].starts_with("foo  "));
+        assert!(lines[2].starts_with("demo (example)  "));
+        assert!(lines[3].starts_with("total  "));
+    }
+
+    #[test]
+    fn reads_json_and_binary() {
+        let dir = std::env::temp_dir().join(format!("stupid-stats-merge-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut json = String::new();
+        stats("b", 1, &[2]).write_json(&mut json).unwrap();
+        fs::write(dir.join("b-1.json"), json).unwrap();
+        let mut bytes = vec![];
+        stats("a", 2, &[3]).write_binary(&mut bytes);
+        fs::write(dir.join("a-2.stats"), bytes).unwrap();
+        fs::write(dir.join("a-2.txt"), "In crate: a,\n").unwrap();
+
+        let crates = read_dir(&dir).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        let names: Vec<_> = crates.iter().map(|c| (&*c.crate_name, c.max_args)).collect();
+        assert_eq!(names, vec![("a", 3), ("b", 2)]);
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The message catalog for the report, so it can be written in languages other
+// than English. Each message is a variant of `Message` carrying the values it
+// needs, and each language renders every message.
+//
+// So far the catalog covers the summary at the top of the report (and the
+// println, args and polymorphism stats in it); every other metric's report is
+// still only in English. Because of that, a language is only used when it is
+// asked for, with `--lang` or `STUPID_STATS_LANG`, never picked up from `LANG`.
+
+use counter::Counter;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    De,
+}
+
+impl Lang {
+    // Looks up a language by its code, e.g., `de` or `de-AT`.
+    pub fn from_code(code: &str) -> Option<Lang> {
+        let code = code.split(|c| c == '-' || c == '_').next().unwrap_or("");
+        match &*code.to_lowercase() {
+            "en" => Some(Lang::En),
+            "de" => Some(Lang::De),
+            _ => None,
+        }
+    }
+}
+
+pub enum Message<'a> {
+    InCrate(&'a str),
+    // We're only analysing this module.
+    OnlyModule(&'a str),
+    // We left out this many lines of generated code.
+    GeneratedExcluded(usize),
+    // The percentage we sample, and how many of how many functions that was.
+    Sampled(f64, usize, usize),
+    PrintlnCount(Counter),
+    // Introduces the counts of each formatting macro.
+    FormatMacros,
+    // Introduce the most used macros from the standard library, and the rest.
+    TopStdMacros,
+    TopOtherMacros,
+    // The most common number of arguments and the percentage of functions with
+    // that many.
+    CommonArgs(usize, f64),
+    // The percentage of functions with four or more arguments.
+    FourOrMoreArgs(f64),
+    // Some argument counts were clamped at this many.
+    ApproximateArgs(usize),
+    // The most common number of arguments, the percentage with that many, and
+    // the percentage with four or more, for free functions and for methods.
+    FreeFnArgs(usize, f64, f64),
+    MethodArgs(usize, f64, f64),
+    // The mean, median, 90th percentile, and maximum number of arguments.
+    ArgSpread(f64, usize, usize, usize),
+    // Fields holding trait objects and type parameters on data types.
+    Polymorphism(Counter, Counter),
+    // Data types with only trait object fields, only type parameters, or both.
+    PolymorphicTypes(Counter, Counter, Counter),
+}
+
+impl Lang {
+    pub fn render(self, message: Message) -> String {
+        match (self, message) {
+            (Lang::En, Message::InCrate(name)) => format!("In crate: {},\n", name),
+            (Lang::De, Message::InCrate(name)) => format!("Im Crate: {},\n", name),
+
+            (Lang::En, Message::OnlyModule(path)) => format!("(Only module `{}`.)", path),
+            (Lang::De, Message::OnlyModule(path)) => format!("(Nur Modul `{}`.)", path),
+
+            (Lang::En, Message::GeneratedExcluded(lines)) => {
+                format!("({} lines of generated code excluded.)", lines)
+            }
+            (Lang::De, Message::GeneratedExcluded(lines)) => {
+                format!("({} Zeilen generierter Code ausgenommen.)", lines)
+            }
+
+            (Lang::En, Message::Sampled(percent, sampled, population)) => format!(
+                "(Sampled {} of {} functions ({:.0}%) for the expensive metrics; their counts \
+                 are estimates.)",
+                sampled, population, percent
+            ),
+            (Lang::De, Message::Sampled(percent, sampled, population)) => format!(
+                "(Stichprobe von {} aus {} Funktionen ({:.0} %) für die aufwendigen Metriken; \
+                 deren Zahlen sind Schätzungen.)",
+                sampled, population, percent
+            ),
+
+            (Lang::En, Message::PrintlnCount(count)) => {
+                format!("Found {} uses of `println!`;", count)
+            }
+            (Lang::De, Message::PrintlnCount(count)) => {
+                format!("{} Verwendungen von `println!` gefunden;", count)
+            }
+
+            (Lang::En, Message::FormatMacros) => "Uses of the formatting macros:".to_owned(),
+            (Lang::De, Message::FormatMacros) => "Verwendungen der Formatierungsmakros:".to_owned(),
+
+            (Lang::En, Message::TopStdMacros) => {
+                "The most used built-in and standard library macros:".to_owned()
+            }
+            (Lang::De, Message::TopStdMacros) => {
+                "Die meistverwendeten eingebauten Makros und Makros der Standardbibliothek:".to_owned()
+            }
+
+            (Lang::En, Message::TopOtherMacros) => {
+                "The most used macros from this and other crates:".to_owned()
+            }
+            (Lang::De, Message::TopOtherMacros) => {
+                "Die meistverwendeten Makros aus diesem und anderen Crates:".to_owned()
+            }
+
+            (Lang::En, Message::CommonArgs(common, percent)) => format!(
+                "The most common number of arguments is {} ({:.0}% of all functions);",
+                common, percent
+            ),
+            (Lang::De, Message::CommonArgs(common, percent)) => format!(
+                "Die häufigste Anzahl von Argumenten ist {} ({:.0}% aller Funktionen);",
+                common, percent
+            ),
+
+            (Lang::En, Message::FourOrMoreArgs(percent)) => {
+                format!("{:.0}% of functions have four or more arguments.", percent)
+            }
+            (Lang::De, Message::FourOrMoreArgs(percent)) => {
+                format!("{:.0}% der Funktionen haben vier oder mehr Argumente.", percent)
+            }
+
+            (Lang::En, Message::ApproximateArgs(max)) => format!(
+                "(These are approximate: some functions have {} or more arguments, or a count overflowed.)",
+                max
+            ),
+            (Lang::De, Message::ApproximateArgs(max)) => format!(
+                "(Diese Werte sind ungefähr: manche Funktionen haben {} oder mehr Argumente, oder ein Zähler ist übergelaufen.)",
+                max
+            ),
+
+            (Lang::En, Message::FreeFnArgs(common, percent, four)) => format!(
+                "    free functions: most often {} arguments ({:.0}%), {:.0}% have four or more;",
+                common, percent, four
+            ),
+            (Lang::De, Message::FreeFnArgs(common, percent, four)) => format!(
+                "    freie Funktionen: meist {} Argumente ({:.0}%), {:.0}% haben vier oder mehr;",
+                common, percent, four
+            ),
+
+            (Lang::En, Message::MethodArgs(common, percent, four)) => format!(
+                "    methods: most often {} arguments ({:.0}%), {:.0}% have four or more.",
+                common, percent, four
+            ),
+            (Lang::De, Message::MethodArgs(common, percent, four)) => format!(
+                "    Methoden: meist {} Argumente ({:.0}%), {:.0}% haben vier oder mehr.",
+                common, percent, four
+            ),
+
+            (Lang::En, Message::ArgSpread(mean, median, p90, max)) => format!(
+                "Functions have {:.1} arguments on average (median {}, 90th percentile {}, at most {}):",
+                mean, median, p90, max
+            ),
+            (Lang::De, Message::ArgSpread(mean, median, p90, max)) => format!(
+                "Funktionen haben im Schnitt {:.1} Argumente (Median {}, 90. Perzentil {}, höchstens {}):",
+                mean, median, p90, max
+            ),
+
+            (Lang::En, Message::Polymorphism(dyn_fields, type_params)) => format!(
+                "Data types have {} fields holding trait objects and {} type parameters;",
+                dyn_fields, type_params
+            ),
+            (Lang::De, Message::Polymorphism(dyn_fields, type_params)) => format!(
+                "Datentypen haben {} Felder mit Trait-Objekten und {} Typparameter;",
+                dyn_fields, type_params
+            ),
+
+            (Lang::En, Message::PolymorphicTypes(dyn_only, generic_only, mixed)) => format!(
+                "{} data types use only trait objects, {} only type parameters, and {} both.",
+                dyn_only, generic_only, mixed
+            ),
+            (Lang::De, Message::PolymorphicTypes(dyn_only, generic_only, mixed)) => format!(
+                "{} Datentypen nutzen nur Trait-Objekte, {} nur Typparameter und {} beides.",
+                dyn_only, generic_only, mixed
+            ),
+        }
+    }
+}
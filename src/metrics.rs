@@ -0,0 +1,504 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Metrics, and a description of every metric we report.
+//
+// A `Metric` collects its numbers as `StupidVisitor` walks the crate's AST,
+// then reports them. Every stat in the report is a metric, built in ones and
+// those other tools add with `StupidCalls::register_metric` alike, so they're
+// all turned on and off with `--metrics`, broken down by module, and included
+// in the JSON summary the same way. Most metrics look at the crate as it was
+// parsed; the ones which need it after expansion, type information, or the
+// source files say so with `stages`.
+//
+// The descriptions are for `stupid --describe-metrics`, so that dashboards can
+// set themselves up for whichever metrics this version of stupid-stats
+// produces. Keep them in step with `builtin_metrics` and each metric's
+// `stages`.
+
+use std::fmt::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use rustc_ast::{ast, visit};
+use rustc_hir as hir;
+use rustc_hir::def_id::LocalDefId;
+use rustc_hir::intravisit::Visitor;
+use rustc_hir::itemlikevisit::ItemLikeVisitor;
+use rustc_hir::HirId;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::Span;
+use rustc_span::source_map::SourceMap;
+
+use args::ArgsMetric;
+use assertions::AssertionsMetric;
+use async_code::AsyncMetric;
+use attributes::AttributesMetric;
+use bool_params::BoolParamsMetric;
+use casts::CastsMetric;
+use cfgs::CfgsMetric;
+use closures::ClosuresMetric;
+use cognitive::CognitiveMetric;
+use collect_targets::CollectTargets;
+use comments::CommentsMetric;
+use crate_attrs::CrateAttrsMetric;
+use crate_stats::{escape, CrateStats};
+use cyclomatic::CyclomaticMetric;
+use delegation::DelegationMetric;
+use diagnostics::{LintCounts, LintsMetric};
+use double_indirection::DoubleIndirectionMetric;
+use drops::DropsMetric;
+use error_conversions::ErrorConversions;
+use error_types::ErrorTypesMetric;
+use expansion_depth::ExpansionDepthMetric;
+use expansion_growth::ExpansionGrowth;
+use fan_out::FanOutMetric;
+use features::FeaturesMetric;
+use findings::Findings;
+use fn_length::FnLengthMetric;
+use format_strings::FormatStringsMetric;
+use generic_bloat::GenericBloat;
+use global_state::GlobalStateMetric;
+use god_modules::GodModulesMetric;
+use impl_trait::ImplTraitMetric;
+use int_types::IntTypesMetric;
+use interior_mutability::InteriorMutabilityMetric;
+use iterators::IteratorsMetric;
+use lifetimes::LifetimesMetric;
+use line_endings::LineEndingsMetric;
+use loop_allocations::LoopAllocationsMetric;
+use macro_positions::MacroPositionsMetric;
+use macro_sizes::MacroSizesMetric;
+use match_arms::MatchArmsMetric;
+use match_style::MatchStyleMetric;
+use messages::Lang;
+use modules::module_of;
+use nested_types::NestedTypesMetric;
+use non_exhaustive::NonExhaustiveMetric;
+use options::Options;
+use overexposed::OverexposedItems;
+use panics::PanicsMetric;
+use pipeline::PipelineMetric;
+use polymorphism::PolymorphismMetric;
+use println::PrintlnMetric;
+use receivers::ReceiversMetric;
+use returns::ReturnsMetric;
+use sample::Sample;
+use spawns::SpawnsMetric;
+use teams::TeamsMetric;
+use timing::TimingMetric;
+use tracked::TrackedMetric;
+use trait_bounds::TraitBoundsMetric;
+use type_stats::TypeStats;
+use unsafe_code::UnsafeMetric;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    // The crate as it was parsed, before macro expansion.
+    Parse,
+    // The crate after macro expansion.
+    Expansion,
+    // Type checking has finished, see `Metric::check_analysis`.
+    Analysis,
+    // The source files, see `Metric::check_source`.
+    Source,
+}
+
+// What a metric gets to look at once type checking has finished.
+pub struct AnalysisContext<'tcx> {
+    pub tcx: TyCtxt<'tcx>,
+    // Which functions to look at, for metrics which are expensive enough to be
+    // worth sampling.
+    pub sample: Sample,
+    // The module to look at (by its path from the crate root) if not the whole
+    // crate, for `--module-filter` and the breakdown by module. Type
+    // information is for the whole crate, so metrics must check items are in
+    // it with `includes` (or use `visit_items`).
+    pub module: Option<Vec<String>>,
+    // Whether the module's submodules are included too. They are for
+    // `--module-filter`, but not in the breakdown, where they have their own
+    // metrics.
+    pub submodules: bool,
+}
+
+impl<'tcx> AnalysisContext<'tcx> {
+    // Whether `def_id` is in the module we're looking at.
+    pub fn includes(&self, def_id: LocalDefId) -> bool {
+        let module = match self.module {
+            Some(ref module) => module,
+            None => return true,
+        };
+        let path = module_of(self.tcx, def_id);
+        if self.submodules { path.starts_with(module) } else { path == *module }
+    }
+
+    // Walks the items (and their bodies) in the module we're looking at, like
+    // `visit_all_item_likes` does for the whole crate.
+    pub fn visit_items<V: Visitor<'tcx>>(&self, visitor: &mut V) {
+        let mut scoped = Scoped {
+            cx: self,
+            visitor: visitor.as_deep_visitor(),
+        };
+        self.tcx.hir().krate().visit_all_item_likes(&mut scoped);
+    }
+}
+
+// Passes on the items in the module `cx` is looking at.
+struct Scoped<'c, 'tcx, V> {
+    cx: &'c AnalysisContext<'tcx>,
+    visitor: V,
+}
+
+impl<'c, 'tcx, V> Scoped<'c, 'tcx, V> {
+    fn includes(&self, hir_id: HirId) -> bool {
+        self.cx.includes(self.cx.tcx.hir().local_def_id(hir_id))
+    }
+}
+
+impl<'c, 'tcx, V: ItemLikeVisitor<'tcx>> ItemLikeVisitor<'tcx> for Scoped<'c, 'tcx, V> {
+    fn visit_item(&mut self, item: &'tcx hir::Item<'tcx>) {
+        if self.includes(item.hir_id) {
+            self.visitor.visit_item(item);
+        }
+    }
+
+    fn visit_trait_item(&mut self, item: &'tcx hir::TraitItem<'tcx>) {
+        if self.includes(item.hir_id) {
+            self.visitor.visit_trait_item(item);
+        }
+    }
+
+    fn visit_impl_item(&mut self, item: &'tcx hir::ImplItem<'tcx>) {
+        if self.includes(item.hir_id) {
+            self.visitor.visit_impl_item(item);
+        }
+    }
+
+    fn visit_foreign_item(&mut self, item: &'tcx hir::ForeignItem<'tcx>) {
+        if self.includes(item.hir_id) {
+            self.visitor.visit_foreign_item(item);
+        }
+    }
+}
+
+pub struct ReportContext<'a> {
+    pub lang: Lang,
+    // For turning spans into file names and lines.
+    pub source_map: &'a SourceMap,
+    // How many functions the metrics which sample saw, and how many there
+    // are, for estimates (see `estimate_count`). The same when we don't
+    // sample.
+    pub sampled: usize,
+    pub population: usize,
+}
+
+pub trait Metric {
+    // Identifies the metric for `--metrics`.
+    fn id(&self) -> &'static str;
+
+    // The stages the metric needs. Its visit and exit methods are called when
+    // we walk the crate for `Parse` or `Expansion` (or both, with
+    // `check_crate` telling it which walk is starting).
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Parse]
+    }
+
+    // Whether the metric makes sense for one module on its own. Metrics which
+    // look at the whole crate at once (e.g., in `check_crate`) don't.
+    fn per_module(&self) -> bool {
+        true
+    }
+
+    // Whether the metric is expensive enough to sample (`--sample`). If it
+    // is, its hooks aren't called for functions which aren't in the sample,
+    // or anything inside them.
+    fn sampled(&self) -> bool {
+        false
+    }
+
+    // Whether the metric can only look at the whole crate, even when we're
+    // asked for one module (`--module-filter`), e.g., because it counts the
+    // crate's attributes or files. Such metrics are left out then.
+    fn whole_crate(&self) -> bool {
+        false
+    }
+
+    // Called before each walk of the crate.
+    fn check_crate(&mut self, _krate: &ast::Crate, _stage: Stage) {}
+
+    // Called for everything of each kind in the crate, outermost first. The
+    // `exit_` methods are called once everything inside has been visited.
+    fn visit_item(&mut self, _item: &ast::Item) {}
+    fn exit_item(&mut self, _item: &ast::Item) {}
+    fn visit_assoc_item(&mut self, _item: &ast::AssocItem, _ctxt: visit::AssocCtxt) {}
+    fn exit_assoc_item(&mut self, _item: &ast::AssocItem, _ctxt: visit::AssocCtxt) {}
+    fn visit_foreign_item(&mut self, _item: &ast::ForeignItem) {}
+    // Functions, methods, and closures, after the item they belong to.
+    fn visit_fn(&mut self, _fk: visit::FnKind, _span: Span) {}
+    fn exit_fn(&mut self, _fk: visit::FnKind, _span: Span) {}
+    fn visit_block(&mut self, _block: &ast::Block) {}
+    fn exit_block(&mut self, _block: &ast::Block) {}
+    fn visit_stmt(&mut self, _stmt: &ast::Stmt) {}
+    fn exit_stmt(&mut self, _stmt: &ast::Stmt) {}
+    fn visit_expr(&mut self, _expr: &ast::Expr) {}
+    fn exit_expr(&mut self, _expr: &ast::Expr) {}
+    fn visit_pat(&mut self, _pat: &ast::Pat) {}
+    fn visit_ty(&mut self, _ty: &ast::Ty) {}
+    fn exit_ty(&mut self, _ty: &ast::Ty) {}
+    fn visit_param_bound(&mut self, _bound: &ast::GenericBound) {}
+    fn visit_mac_call(&mut self, _mac: &ast::MacCall) {}
+    fn visit_attribute(&mut self, _attr: &ast::Attribute) {}
+    fn visit_lifetime(&mut self, _lifetime: &ast::Lifetime) {}
+
+    // Called once, after the walks, for metrics which need `Analysis`.
+    fn check_analysis<'tcx>(&mut self, _cx: &AnalysisContext<'tcx>) {}
+
+    // Called once, after the walks, for metrics which need `Source`.
+    fn check_source(&mut self, _source_map: &SourceMap) {}
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result;
+
+    // Adds the places where the crate breaks the metric's rules, if it has
+    // any (see `findings`).
+    fn findings(&self, _findings: &mut Findings) {}
+
+    // A new, empty metric of the same kind (with the same settings), for the
+    // breakdown by module.
+    fn fresh(&self) -> Box<dyn Metric>;
+
+    // Adds its numbers to the summary stats, if it has any there.
+    fn crate_stats(&self, _stats: &mut CrateStats) {}
+
+    // Adds a section to the HTML page (`--stats-output`), if it has one.
+    fn write_html(&self, _out: &mut String) -> fmt::Result {
+        Ok(())
+    }
+}
+
+// Every built-in metric, in the order they're reported. `lints` counts the
+// warnings which rustc writes to `lint_counts` (see `diagnostics`).
+pub fn builtin_metrics(
+    options: &Options,
+    lint_counts: Arc<Mutex<LintCounts>>,
+) -> Vec<Box<dyn Metric>> {
+    vec![
+        Box::new(PrintlnMetric::new(options.top_macros)),
+        Box::new(ArgsMetric::new(options.args_by_kind, options.max_args)),
+        Box::new(PolymorphismMetric::new()),
+        Box::new(CfgsMetric::new()),
+        Box::new(FeaturesMetric::new()),
+        Box::new(FanOutMetric::new()),
+        Box::new(ReturnsMetric::new()),
+        Box::new(FormatStringsMetric::new()),
+        Box::new(ErrorTypesMetric::new()),
+        Box::new(LifetimesMetric::new()),
+        Box::new(CognitiveMetric::new(options.cognitive_threshold)),
+        Box::new(TrackedMetric::new(&options.track)),
+        Box::new(ImplTraitMetric::new()),
+        Box::new(GodModulesMetric::new()),
+        Box::new(MatchArmsMetric::new()),
+        Box::new(DropsMetric::new()),
+        Box::new(ExpansionDepthMetric::new()),
+        Box::new(AssertionsMetric::new(options.assert_complexity, options.min_assertions)),
+        Box::new(ReceiversMetric::new()),
+        Box::new(TimingMetric::new(options.timing)),
+        Box::new(CastsMetric::new()),
+        Box::new(TeamsMetric::new()),
+        Box::new(InteriorMutabilityMetric::new()),
+        Box::new(DelegationMetric::new()),
+        Box::new(NonExhaustiveMetric::new()),
+        Box::new(CollectTargets::new()),
+        Box::new(CommentsMetric::new(options.cognitive_threshold)),
+        Box::new(SpawnsMetric::new()),
+        Box::new(IntTypesMetric::new()),
+        Box::new(NestedTypesMetric::new()),
+        Box::new(CrateAttrsMetric::new()),
+        Box::new(MacroSizesMetric::new()),
+        Box::new(TraitBoundsMetric::new()),
+        Box::new(GlobalStateMetric::new()),
+        Box::new(ErrorConversions::new()),
+        Box::new(OverexposedItems::new()),
+        Box::new(LineEndingsMetric::new()),
+        Box::new(DoubleIndirectionMetric::new()),
+        Box::new(GenericBloat::new()),
+        Box::new(IteratorsMetric::new()),
+        Box::new(UnsafeMetric::new(options.max_unsafe_blocks)),
+        Box::new(TypeStats::new()),
+        Box::new(LoopAllocationsMetric::new()),
+        Box::new(CyclomaticMetric::new(options.max_cyclomatic)),
+        Box::new(FnLengthMetric::new()),
+        Box::new(MatchStyleMetric::new()),
+        Box::new(ExpansionGrowth::new()),
+        Box::new(BoolParamsMetric::new()),
+        Box::new(PanicsMetric::new(options.max_todos)),
+        Box::new(PipelineMetric::new()),
+        Box::new(AttributesMetric::new()),
+        Box::new(ClosuresMetric::new()),
+        Box::new(MacroPositionsMetric::new()),
+        Box::new(AsyncMetric::new()),
+        Box::new(LintsMetric::new(lint_counts)),
+    ]
+}
+
+pub struct MetricInfo {
+    pub id: &'static str,
+    // What sort of numbers it reports: `count`, `histogram`, `distribution`
+    // (an average and the worst cases), or `list` (of places in the code).
+    pub kind: &'static str,
+    // What it counts.
+    pub unit: &'static str,
+    // The compiler stages it needs: `parse`, `expansion`, `analysis`, or
+    // `source` (the files on disk).
+    pub stages: &'static [&'static str],
+    // The settings which change its thresholds, i.e., what counts as a
+    // finding.
+    pub thresholds: &'static [&'static str],
+    // The ids of the findings it reports.
+    pub findings: &'static [&'static str],
+}
+
+const fn metric(
+    id: &'static str,
+    kind: &'static str,
+    unit: &'static str,
+    stages: &'static [&'static str],
+) -> MetricInfo {
+    MetricInfo {
+        id,
+        kind,
+        unit,
+        stages,
+        thresholds: &[],
+        findings: &[],
+    }
+}
+
+const PARSE: &[&str] = &["parse"];
+const EXPANSION: &[&str] = &["expansion"];
+const ANALYSIS: &[&str] = &["analysis"];
+
+pub const METRICS: &[MetricInfo] = &[
+    metric("println", "count", "macro calls", PARSE),
+    MetricInfo {
+        thresholds: &["STUPID_STATS_MAX_ARGS"],
+        findings: &["too-many-arguments"],
+        ..metric("args", "histogram", "arguments", PARSE)
+    },
+    metric("polymorphism", "count", "data types", PARSE),
+    metric("cfgs", "count", "items", PARSE),
+    metric("features", "count", "items", PARSE),
+    metric("fan-out", "distribution", "callees", &["parse", "analysis"]),
+    metric("returns", "count", "functions", PARSE),
+    metric("format-strings", "count", "format strings", PARSE),
+    metric("error-types", "count", "functions", PARSE),
+    metric("lifetimes", "count", "lifetimes", PARSE),
+    MetricInfo {
+        thresholds: &["STUPID_STATS_COGNITIVE_THRESHOLD"],
+        findings: &["cognitive-complexity"],
+        ..metric("cognitive-complexity", "distribution", "complexity", PARSE)
+    },
+    metric("tracked-calls", "list", "call sites", ANALYSIS),
+    metric("impl-trait", "count", "types", PARSE),
+    metric("god-modules", "list", "score", EXPANSION),
+    metric("match-arms", "list", "arms", PARSE),
+    metric("drops", "count", "calls", PARSE),
+    metric("expansion-depth", "distribution", "macro expansions", EXPANSION),
+    MetricInfo {
+        thresholds: &["STUPID_STATS_ASSERT_COMPLEXITY", "STUPID_STATS_MIN_ASSERTIONS"],
+        findings: &["assertion-density", "test-without-assertions"],
+        ..metric("assertions", "count", "assertions", PARSE)
+    },
+    metric("receivers", "count", "methods", PARSE),
+    metric("timing", "list", "milliseconds", PARSE),
+    MetricInfo {
+        findings: &["truncating-cast"],
+        ..metric("casts", "count", "casts", PARSE)
+    },
+    metric("teams", "count", "items", &["expansion", "source"]),
+    metric("interior-mutability", "count", "uses", PARSE),
+    metric("delegation", "count", "functions", PARSE),
+    metric("non-exhaustive", "count", "types", PARSE),
+    metric("collect-targets", "count", "calls", ANALYSIS),
+    MetricInfo {
+        thresholds: &["STUPID_STATS_COGNITIVE_THRESHOLD"],
+        findings: &["uncommented-complex-function"],
+        ..metric("comments", "count", "functions", &["parse", "source"])
+    },
+    MetricInfo {
+        findings: &["spawned-capture"],
+        ..metric("spawns", "count", "spawns", PARSE)
+    },
+    metric("int-types", "count", "types", PARSE),
+    MetricInfo {
+        findings: &["nested-signature-type"],
+        ..metric("nested-types", "distribution", "nesting depth", PARSE)
+    },
+    metric("crate-attrs", "list", "attributes", PARSE),
+    metric("macro-sizes", "distribution", "AST nodes", EXPANSION),
+    metric("trait-bounds", "count", "bounds", PARSE),
+    metric("global-state", "distribution", "globals", PARSE),
+    metric("error-conversions", "distribution", "error types", ANALYSIS),
+    MetricInfo {
+        findings: &["overexposed-visibility"],
+        ..metric("overexposed", "list", "items", ANALYSIS)
+    },
+    metric("line-endings", "count", "lines", &["source"]),
+    metric("double-indirection", "list", "types", PARSE),
+    metric("generic-bloat", "list", "lines", ANALYSIS),
+    metric("iterators", "count", "functions", PARSE),
+    MetricInfo {
+        thresholds: &["STUPID_STATS_MAX_UNSAFE_BLOCKS"],
+        findings: &["unsafe-block"],
+        ..metric("unsafe", "count", "functions", PARSE)
+    },
+    metric("types", "count", "functions", ANALYSIS),
+    metric("loop-allocations", "list", "allocations", PARSE),
+    MetricInfo {
+        thresholds: &["STUPID_STATS_MAX_CYCLOMATIC"],
+        findings: &["cyclomatic-complexity"],
+        ..metric("cyclomatic-complexity", "distribution", "complexity", PARSE)
+    },
+    metric("fn-length", "distribution", "lines", PARSE),
+    metric("match-style", "list", "expressions", PARSE),
+    metric("expansion-growth", "count", "AST nodes", &["parse", "expansion"]),
+    metric("bool-params", "list", "functions", PARSE),
+    MetricInfo {
+        thresholds: &["STUPID_STATS_MAX_TODOS"],
+        findings: &["todo"],
+        ..metric("panics", "list", "panicking calls", PARSE)
+    },
+    metric("pipeline", "list", "functions", PARSE),
+    metric("attributes", "count", "attributes", PARSE),
+    metric("closures", "distribution", "arguments", PARSE),
+    metric("macro-positions", "count", "macro calls", PARSE),
+    metric("async", "count", "functions", PARSE),
+    metric("lints", "count", "warnings", ANALYSIS),
+];
+
+pub fn describe_metrics(out: &mut String) -> fmt::Result {
+    let list = |items: &[&str]| {
+        let items: Vec<_> = items.iter().map(|i| format!("\"{}\"", escape(i))).collect();
+        format!("[{}]", items.join(", "))
+    };
+
+    writeln!(out, "[")?;
+    for (i, m) in METRICS.iter().enumerate() {
+        writeln!(
+            out,
+            "  {{\"id\": \"{}\", \"kind\": \"{}\", \"unit\": \"{}\", \"stages\": {}, \
+             \"thresholds\": {}, \"findings\": {}}}{}",
+            m.id,
+            m.kind,
+            m.unit,
+            list(m.stages),
+            list(m.thresholds),
+            list(m.findings),
+            if i + 1 < METRICS.len() { "," } else { "" }
+        )?;
+    }
+    writeln!(out, "]")
+}
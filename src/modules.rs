@@ -0,0 +1,244 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Restricting analysis to one module of a crate (`--module-filter`). We make a
+// copy of the crate whose items are just the module's, so every stat which
+// walks the AST only sees that module without knowing anything about it.
+// Stats which use type information look at the whole crate's, so they check
+// which module each item is in (see `AnalysisContext::includes`).
+//
+// Before expansion, a `mod foo;` hasn't been loaded from `foo.rs` yet, so
+// `load_modules` parses those files itself; otherwise the stats which walk the
+// parsed crate would only see the root file and its inline modules.
+
+use std::path::{Path, PathBuf};
+
+use rustc_ast::ast;
+use rustc_ast::ptr::P;
+use rustc_data_structures::sync::Lrc;
+use rustc_errors::emitter::Emitter;
+use rustc_errors::{Diagnostic, Handler};
+use rustc_hir::def_id::LocalDefId;
+use rustc_hir::definitions::DefPathData;
+use rustc_middle::ty::TyCtxt;
+use rustc_session::parse::ParseSess;
+use rustc_span::source_map::SourceMap;
+use rustc_span::symbol::sym;
+
+// Splits a module path like `my_crate::parser` or `crate::parser` into the
+// names of the modules below the crate root.
+pub fn module_path<'a>(filter: &'a str, crate_name: &str) -> Vec<&'a str> {
+    let mut path: Vec<_> = filter.split("::").filter(|s| !s.is_empty()).collect();
+    if path.first().map_or(false, |s| *s == "crate" || *s == crate_name) {
+        path.remove(0);
+    }
+    path
+}
+
+// A copy of `krate` with only the items of the module at `filter`, or `None`
+// if there is no such module.
+pub fn module_subtree(krate: &ast::Crate, filter: &str, crate_name: &str) -> Option<ast::Crate> {
+    let mut items = &krate.items;
+    for name in module_path(filter, crate_name) {
+        items = items.iter().find_map(|i| match i.kind {
+            ast::ItemKind::Mod(_, ast::ModKind::Loaded(ref items, ..)) if &*i.ident.as_str() == name => {
+                Some(items)
+            }
+            _ => None,
+        })?;
+    }
+    Some(ast::Crate {
+        items: items.clone(),
+        ..krate.clone()
+    })
+}
+
+// Diagnostics from parsing the modules' files are thrown away: rustc has
+// already reported any problems with the files it loaded, and the others are
+// behind a `#[cfg]` which is off.
+struct Silent;
+
+impl Emitter for Silent {
+    fn emit_diagnostic(&mut self, _: &Diagnostic) {}
+
+    fn source_map(&self) -> Option<&Lrc<SourceMap>> {
+        None
+    }
+}
+
+// Where the files of a module's out-of-line submodules are.
+struct Dirs {
+    // The directory of the file the module is in, which `#[path]`s outside
+    // inline modules are relative to.
+    file: PathBuf,
+    // The directory of the module's submodules' files.
+    module: PathBuf,
+    // Whether we're in an inline module (`mod foo { .. }`).
+    inline: bool,
+}
+
+impl Dirs {
+    // For the items of `file`. A `mod.rs`, a crate root, or a file given by a
+    // `#[path]` has its submodules next to it; `foo.rs` has them in `foo/`.
+    fn for_file(file: &Path, next_to_it: bool) -> Dirs {
+        let dir = file.parent().unwrap_or_else(|| Path::new("")).to_owned();
+        let module = match file.file_stem() {
+            Some(stem) if !next_to_it && file.file_name().map_or(true, |n| n != "mod.rs") => {
+                dir.join(stem)
+            }
+            _ => dir.clone(),
+        };
+        Dirs {
+            file: dir,
+            module,
+            inline: false,
+        }
+    }
+}
+
+// Loads the out-of-line modules of `krate`, whose root is the file `root`,
+// into it, as expansion would. The files are added to `source_map`, so spans
+// in them are the same as rustc's. A module whose file is missing or doesn't
+// parse is left unloaded.
+pub fn load_modules(krate: &mut ast::Crate, root: &Path, source_map: Lrc<SourceMap>) {
+    let handler = Handler::with_emitter(false, None, Box::new(Silent));
+    let sess = ParseSess::with_span_handler(handler, source_map);
+    let mut files = vec![root.to_owned()];
+    load(&mut krate.items, &Dirs::for_file(root, true), &sess, &mut files);
+}
+
+// `files` are the files we're loading, so a `#[path]` which goes round in a
+// circle doesn't send us round with it.
+fn load(items: &mut [P<ast::Item>], dirs: &Dirs, sess: &ParseSess, files: &mut Vec<PathBuf>) {
+    for item in items {
+        let name = item.ident.to_string();
+        let path_attr = item
+            .attrs
+            .iter()
+            .find(|a| a.has_name(sym::path))
+            .and_then(|a| a.value_str())
+            .map(|p| p.to_string());
+        let kind = match item.kind {
+            ast::ItemKind::Mod(_, ref mut kind) => kind,
+            _ => continue,
+        };
+        match *kind {
+            ast::ModKind::Loaded(ref mut items, ast::Inline::Yes, _) => {
+                let module = match path_attr {
+                    Some(ref path) => dirs.module.join(path),
+                    None => dirs.module.join(&name),
+                };
+                let inner = Dirs {
+                    file: dirs.file.clone(),
+                    module,
+                    inline: true,
+                };
+                load(items, &inner, sess, files);
+            }
+            ast::ModKind::Loaded(..) => {}
+            ast::ModKind::Unloaded => {
+                let file = match path_attr {
+                    Some(ref path) if dirs.inline => dirs.module.join(path),
+                    Some(ref path) => dirs.file.join(path),
+                    None => {
+                        let file = dirs.module.join(format!("{}.rs", name));
+                        if file.is_file() { file } else { dirs.module.join(&name).join("mod.rs") }
+                    }
+                };
+                if files.contains(&file) || !file.is_file() {
+                    continue;
+                }
+                let errors = sess.span_diagnostic.err_count();
+                let mut loaded = match rustc_parse::parse_crate_from_file(&file, sess) {
+                    Ok(loaded) if sess.span_diagnostic.err_count() == errors => loaded,
+                    Ok(_) => continue,
+                    Err(mut e) => {
+                        e.cancel();
+                        continue;
+                    }
+                };
+                files.push(file.clone());
+                load(&mut loaded.items, &Dirs::for_file(&file, path_attr.is_some()), sess, files);
+                files.pop();
+                *kind = ast::ModKind::Loaded(loaded.items, ast::Inline::No, loaded.span);
+                // The file's inner attributes are the module's.
+                item.attrs.extend(loaded.attrs);
+            }
+        }
+    }
+}
+
+// The path of the module `def_id` is in, from the crate root, in the same form
+// as `module_path`'s. Modules inside functions are named as if they weren't,
+// as they are when we walk the AST.
+pub fn module_of(tcx: TyCtxt, def_id: LocalDefId) -> Vec<String> {
+    let module = tcx.parent_module_from_def_id(def_id);
+    tcx.def_path(module.to_def_id())
+        .data
+        .iter()
+        .filter_map(|d| match d.data {
+            DefPathData::TypeNs(name) => Some(name.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process;
+
+    use rustc_span::source_map::FilePathMapping;
+
+    fn names(krate: &ast::Crate) -> Vec<String> {
+        krate.items.iter().map(|i| i.ident.to_string()).collect()
+    }
+
+    #[test]
+    fn file_modules() {
+        let dir = std::env::temp_dir().join(format!("stupid-stats-modules-{}", process::id()));
+        let files = [
+            (
+                "lib.rs",
+                "mod parser;\nmod inline { mod nested; }\n#[path = \"other.rs\"] mod renamed;\n",
+            ),
+            ("parser.rs", "#![allow(dead_code)]\nfn parse() {}\nmod lexer;\n"),
+            ("parser/lexer.rs", "fn lex() {}\n"),
+            ("inline/nested.rs", "fn nested() {}\n"),
+            ("other.rs", "fn other() {}\n"),
+        ];
+        for &(path, text) in &files {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+
+        rustc_span::with_default_session_globals(|| {
+            let source_map = Lrc::new(SourceMap::new(FilePathMapping::empty()));
+            let handler = Handler::with_emitter(false, None, Box::new(Silent));
+            let sess = ParseSess::with_span_handler(handler, source_map.clone());
+            let root = dir.join("lib.rs");
+            let mut krate = rustc_parse::parse_crate_from_file(&root, &sess).unwrap();
+            assert!(module_subtree(&krate, "my_crate::parser", "my_crate").is_none());
+
+            load_modules(&mut krate, &root, source_map);
+            let parser = module_subtree(&krate, "my_crate::parser", "my_crate").unwrap();
+            assert_eq!(names(&parser), vec!["parse", "lexer"]);
+            let lexer = module_subtree(&krate, "crate::parser::lexer", "my_crate").unwrap();
+            assert_eq!(names(&lexer), vec!["lex"]);
+            let nested = module_subtree(&krate, "inline::nested", "my_crate").unwrap();
+            assert_eq!(names(&nested), vec!["nested"]);
+            let renamed = module_subtree(&krate, "renamed", "my_crate").unwrap();
+            assert_eq!(names(&renamed), vec!["other"]);
+            // The file's inner attributes are on the module.
+            assert!(krate.items[0].attrs.iter().any(|a| a.has_name(sym::allow)));
+        });
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
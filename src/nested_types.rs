@@ -0,0 +1,155 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Complicated types in public signatures: `Result`s and `Option`s directly
+// inside each other (e.g., `Result<Option<T>, E>`), and generic types nested
+// more than two deep (e.g., `Vec<HashMap<K, Vec<V>>>`). Both make an API
+// harder to use.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit, FnKind};
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+
+use counter::Counter;
+use findings::{Finding, Findings, Severity};
+use metrics::{Metric, ReportContext};
+
+// Generic types nested deeper than this are flagged.
+const MAX_GENERIC_DEPTH: usize = 2;
+
+// The last segment of a path type and its type arguments.
+pub fn generic_parts(ty: &ast::Ty) -> Option<(String, Vec<&ast::Ty>)> {
+    let path = match ty.kind {
+        ast::TyKind::Path(None, ref path) => path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    let args = match segment.args.as_ref().map(|a| &**a) {
+        Some(&ast::GenericArgs::AngleBracketed(ref args)) => args
+            .args
+            .iter()
+            .filter_map(|a| match *a {
+                ast::AngleBracketedArg::Arg(ast::GenericArg::Type(ref ty)) => Some(&**ty),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    };
+    Some((segment.ident.to_string(), args))
+}
+
+fn is_wrapper(name: &str) -> bool {
+    name == "Result" || name == "Option"
+}
+
+// Whether a `Result` or `Option` directly contains another one, anywhere in
+// `ty`.
+fn has_nested_wrapper(ty: &ast::Ty) -> bool {
+    match generic_parts(ty) {
+        Some((name, args)) => {
+            let direct = is_wrapper(&name) &&
+                args.iter().any(|a| generic_parts(a).map_or(false, |(n, _)| is_wrapper(&n)));
+            direct || args.iter().any(|a| has_nested_wrapper(a))
+        }
+        None => false,
+    }
+}
+
+// How deeply generic types are nested in `ty`, e.g., one for `Vec<u8>`.
+fn generic_depth(ty: &ast::Ty) -> usize {
+    match generic_parts(ty) {
+        Some((_, ref args)) if !args.is_empty() => {
+            1 + args.iter().map(|a| generic_depth(a)).max().unwrap_or(0)
+        }
+        _ => match ty.kind {
+            ast::TyKind::Rptr(_, ref mt) | ast::TyKind::Ptr(ref mt) => generic_depth(&mt.ty),
+            ast::TyKind::Slice(ref ty) | ast::TyKind::Array(ref ty, _) => generic_depth(ty),
+            ast::TyKind::Tup(ref tys) => tys.iter().map(|t| generic_depth(t)).max().unwrap_or(0),
+            _ => 0,
+        },
+    }
+}
+
+pub struct NestedTypesMetric {
+    signatures: Counter,
+    // Complicated types, where they are, and why they were flagged.
+    flagged: Vec<(Span, String, &'static str)>,
+}
+
+impl NestedTypesMetric {
+    pub fn new() -> NestedTypesMetric {
+        NestedTypesMetric {
+            signatures: Counter::new(),
+            flagged: vec![],
+        }
+    }
+
+    fn check_sig(&mut self, vis: &ast::Visibility, sig: &ast::FnSig) {
+        if !matches!(vis.kind, ast::VisibilityKind::Public) {
+            return;
+        }
+        self.signatures.increment();
+
+        let output = match sig.decl.output {
+            ast::FnRetTy::Ty(ref ty) => Some(&**ty),
+            ast::FnRetTy::Default(_) => None,
+        };
+        for ty in sig.decl.inputs.iter().map(|p| &*p.ty).chain(output) {
+            if has_nested_wrapper(ty) {
+                self.flagged.push((ty.span, pprust::ty_to_string(ty), "nests `Result`s and `Option`s"));
+            } else if generic_depth(ty) > MAX_GENERIC_DEPTH {
+                self.flagged.push((ty.span, pprust::ty_to_string(ty), "has deeply nested generics"));
+            }
+        }
+    }
+}
+
+impl Metric for NestedTypesMetric {
+    fn id(&self) -> &'static str {
+        "nested-types"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        if let ast::ItemKind::Fn(box FnKind(_, ref sig, ..)) = i.kind {
+            self.check_sig(&i.vis, sig);
+        }
+    }
+
+    fn visit_assoc_item(&mut self, i: &ast::AssocItem, _: visit::AssocCtxt) {
+        if let ast::AssocItemKind::Fn(box FnKind(_, ref sig, ..)) = i.kind {
+            self.check_sig(&i.vis, sig);
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "{} types in {} public signatures are deeply nested;",
+            self.flagged.len(),
+            self.signatures
+        )
+    }
+
+    fn findings(&self, findings: &mut Findings) {
+        for &(span, ref ty, why) in &self.flagged {
+            findings.push(Finding {
+                id: "nested-signature-type",
+                severity: Severity::Note,
+                span,
+                message: format!("`{}` {}", ty, why),
+                value: None,
+            });
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(NestedTypesMetric::new())
+    }
+}
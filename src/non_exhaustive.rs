@@ -0,0 +1,84 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Public enums and structs with and without `#[non_exhaustive]`. Adding a
+// variant or field to an exhaustive public type breaks users who match on it or
+// construct it, so this is a semver risk for libraries. A struct with a private
+// field can't be constructed or exhaustively matched outside the crate anyway,
+// so only structs whose fields are all public count as exhaustive.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+use rustc_span::Span;
+use rustc_span::symbol::sym;
+
+use counter::Counter;
+use metrics::{Metric, ReportContext};
+
+pub struct NonExhaustiveMetric {
+    non_exhaustive: Counter,
+    // Public types which could be matched or constructed exhaustively.
+    exhaustive: Vec<(String, Span)>,
+}
+
+impl NonExhaustiveMetric {
+    pub fn new() -> NonExhaustiveMetric {
+        NonExhaustiveMetric {
+            non_exhaustive: Counter::new(),
+            exhaustive: vec![],
+        }
+    }
+}
+
+fn is_public(vis: &ast::Visibility) -> bool {
+    matches!(vis.kind, ast::VisibilityKind::Public)
+}
+
+impl Metric for NonExhaustiveMetric {
+    fn id(&self) -> &'static str {
+        "non-exhaustive"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        let exhaustive = match i.kind {
+            ast::ItemKind::Enum(..) => Some(true),
+            ast::ItemKind::Struct(ref data, _) => {
+                Some(data.fields().iter().all(|f| is_public(&f.vis)))
+            }
+            _ => None,
+        };
+
+        if let Some(exhaustive) = exhaustive {
+            if is_public(&i.vis) {
+                if i.attrs.iter().any(|a| a.has_name(sym::non_exhaustive)) {
+                    self.non_exhaustive.increment();
+                } else if exhaustive {
+                    self.exhaustive.push((i.ident.to_string(), i.span));
+                }
+            }
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "{} public enums and structs are `#[non_exhaustive]` and {} are exhaustive;",
+            self.non_exhaustive,
+            self.exhaustive.len()
+        )?;
+        for &(ref name, span) in &self.exhaustive {
+            writeln!(out, "    `{}`: {}", name, cx.source_map.span_to_string(span))?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(NonExhaustiveMetric::new())
+    }
+}
@@ -0,0 +1,611 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Options for stupid-stats itself. They come from environment variables, or
+// else from `stupid-stats.toml` (see `config`), since when we are run by Cargo
+// we don't control the command line. Some can also be given as flags, which
+// `take_flags` takes out before the rest of the command line goes to rustc.
+
+use std::path::{Path, PathBuf};
+
+use config::Config;
+use messages::Lang;
+use profiles::{Profile, Thresholds};
+use sample::Sample;
+
+pub enum Format {
+    Text,
+    Junit,
+    Json,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "text" => Some(Format::Text),
+            "junit" => Some(Format::Junit),
+            "json" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    // The extension for report files in this format.
+    pub fn extension(&self) -> &'static str {
+        match *self {
+            Format::Text => "txt",
+            Format::Junit => "xml",
+            Format::Json => "json",
+        }
+    }
+}
+
+pub struct Options {
+    // Sets the defaults for the thresholds below, from `--profile` or
+    // `STUPID_STATS_PROFILE`: `strict`, `default` (the default), or `lenient`.
+    pub profile: Profile,
+    // Functions with a higher cognitive complexity than this are findings,
+    // from `STUPID_STATS_COGNITIVE_THRESHOLD`.
+    pub cognitive_threshold: usize,
+    // Fully qualified paths of functions whose call sites we should report,
+    // from `STUPID_STATS_TRACK` (comma separated).
+    pub track: Vec<String>,
+    // Public functions more complex than this must have enough assertions or
+    // tests, from `STUPID_STATS_ASSERT_COMPLEXITY`. No rule if it is not set.
+    pub assert_complexity: Option<usize>,
+    // How many assertions or tests such functions need, from
+    // `STUPID_STATS_MIN_ASSERTIONS` (default 1).
+    pub min_assertions: usize,
+    // Functions with a higher cyclomatic complexity than this are findings,
+    // from `STUPID_STATS_MAX_CYCLOMATIC`. No rule if it is not set.
+    pub max_cyclomatic: Option<usize>,
+    // Functions with more arguments than this are findings, from
+    // `STUPID_STATS_MAX_ARGS`, and so are all the unsafe blocks or `todo!`s if
+    // there are more than `STUPID_STATS_MAX_UNSAFE_BLOCKS` or
+    // `STUPID_STATS_MAX_TODOS`. Each is also a `--fail-if` condition (see
+    // `gates`). No rule if it is not set (or is `none`, to turn off the
+    // profile's).
+    pub max_args: Option<usize>,
+    pub max_unsafe_blocks: Option<usize>,
+    pub max_todos: Option<usize>,
+    // Conditions on the summary stats which make us exit with an error, e.g.,
+    // `max_args>7`, from `--fail-if` (which can be given more than once) or
+    // `STUPID_STATS_FAIL_IF` (comma separated). See `gates`.
+    pub fail_if: Vec<String>,
+    // Whether the thresholds which are also conditions (`max_args` and so on,
+    // above) count as `--fail-if` conditions, from
+    // `STUPID_STATS_THRESHOLD_GATES` (`0` if not). They're still findings
+    // either way; `cargo stupid-stats` turns this off for the crates it builds,
+    // and checks the conditions itself.
+    pub threshold_gates: bool,
+    // Where to save the summary stats as a baseline, and the baseline to
+    // compare them with, from `--save-baseline` and `--compare-baseline` or
+    // `STUPID_STATS_SAVE_BASELINE` and `STUPID_STATS_COMPARE_BASELINE`.
+    // `{crate}` is replaced with the crate's name. See `baseline`.
+    pub save_baseline: Option<String>,
+    pub compare_baseline: Option<String>,
+    // Whether a regression since the baseline fails like a `--fail-if`
+    // condition, from `--fail-on-regression` or
+    // `STUPID_STATS_FAIL_ON_REGRESSION`.
+    pub fail_on_regression: bool,
+    // Whether to report how long analysing each module took, from
+    // `STUPID_STATS_TIMING`.
+    pub timing: bool,
+    // How many of the most used macros of each sort to list, from
+    // `STUPID_STATS_TOP_MACROS` (default 10).
+    pub top_macros: usize,
+    // Whether to report argument counts for free functions and methods
+    // separately, from `STUPID_STATS_ARGS_BY_KIND`.
+    pub args_by_kind: bool,
+    // The language to write the report in, from `--lang` or `STUPID_STATS_LANG`
+    // (default English). Only asked for explicitly, not taken from `LANG`,
+    // because only some of the report is translated.
+    pub lang: Lang,
+    // How to write the report, from `--stats-format` or `STUPID_STATS_FORMAT`:
+    // `text` (the default), `junit`, or `json`.
+    pub format: Format,
+    // The percentage of functions to analyse for expensive per-function
+    // metrics, from `--sample` or `STUPID_STATS_SAMPLE` (default all).
+    pub sample: Sample,
+    // Where to save reports, from `STUPID_STATS_REPORT_DIR`. If this is set,
+    // reports are always saved rather than printed; `cargo stupid-stats` sets
+    // it so that it can collect them.
+    pub report_dir: Option<PathBuf>,
+    // Where to save each crate's summary stats as JSON (whatever the format of
+    // the report), for `stupid merge`, from `STUPID_STATS_JSON_DIR`.
+    pub json_dir: Option<PathBuf>,
+    // Whether to save them in the binary form instead (see `crate_stats`),
+    // from `STUPID_STATS_BINARY_STATS`.
+    pub binary_stats: bool,
+    // The ids of the metrics to report, from `--metrics` or
+    // `STUPID_STATS_METRICS` (comma separated, default all).
+    pub metrics: Option<Vec<String>>,
+    // Whether to break the summary stats down by module, from
+    // `STUPID_STATS_PER_MODULE`.
+    pub per_module: bool,
+    // The path of the only module to analyse (e.g., `my_crate::parser`), from
+    // `--module-filter` or `STUPID_STATS_MODULE_FILTER`.
+    pub module_filter: Option<String>,
+    // Where to write the summary as an HTML page, from `--stats-output` or
+    // `STUPID_STATS_OUTPUT`. `{crate}` is replaced with the crate's name.
+    pub stats_output: Option<String>,
+    // Whether to analyse generated code too, from `--include-generated` or
+    // `STUPID_STATS_INCLUDE_GENERATED`. See `generated`.
+    pub include_generated: bool,
+    // Where to save the inventory of the crate's `pub` functions for
+    // `stupid api-diff`, from `STUPID_STATS_API_INVENTORY`. `{crate}` is
+    // replaced with the crate's name.
+    pub api_inventory: Option<String>,
+    // Where to write a CSV file with a row for each function, from
+    // `--per-function-csv` or `STUPID_STATS_PER_FUNCTION_CSV`. `{crate}` is
+    // replaced with the crate's name.
+    pub per_function_csv: Option<String>,
+    // Whether to write the per-function rows straight to the file rather than
+    // buffering them, from `--low-memory` or `STUPID_STATS_LOW_MEMORY`.
+    pub low_memory: bool,
+    // Where to stream a JSON line for each function, from `--sink` or
+    // `STUPID_STATS_SINK`, e.g., `tcp://localhost:9000` (see `function_csv`).
+    pub sink: Option<String>,
+    // Whether to leave anything which could identify the code out of the stats
+    // we save or send, from `--anonymize` or `STUPID_STATS_ANONYMIZE`.
+    pub anonymize: bool,
+    // Whether to stop once we have the stats, skipping codegen and linking,
+    // from `--no-codegen` or `STUPID_STATS_NO_CODEGEN` (`0` for the opposite;
+    // there's no flag for that, since `--codegen` is rustc's `-C`). If it's not set, the
+    // driver decides: Cargo needs the object code, anyone else probably
+    // doesn't.
+    pub no_codegen: Option<bool>,
+    // What to do with the summary stats (as JSON) after the report is done:
+    // POST them to the URL `STUPID_STATS_WEBHOOK`, upload them under the
+    // `s3://` URL `STUPID_STATS_UPLOAD` (to the S3-compatible service at
+    // `STUPID_STATS_UPLOAD_ENDPOINT`, if set), or run the shell command
+    // `STUPID_STATS_POST_REPORT` with the path of the file.
+    pub webhook: Option<String>,
+    pub upload: Option<String>,
+    pub upload_endpoint: Option<String>,
+    pub post_report: Option<String>,
+    // Where the options which aren't set in the environment come from, and
+    // where `set_profile` looks for overrides.
+    config: Config,
+}
+
+impl Options {
+    pub fn from_env() -> Options {
+        Options::from_config(Config::load())
+    }
+
+    fn from_config(config: Config) -> Options {
+        let profile = config
+            .var("STUPID_STATS_PROFILE")
+            .and_then(|name| Profile::from_name(&name))
+            .unwrap_or(Profile::Default);
+        let thresholds = thresholds(profile, &config);
+        Options {
+            profile,
+            cognitive_threshold: thresholds.cognitive,
+            track: list_var(&config, "STUPID_STATS_TRACK"),
+            assert_complexity: thresholds.assert_complexity,
+            min_assertions: thresholds.min_assertions,
+            max_cyclomatic: thresholds.max_cyclomatic,
+            max_args: thresholds.max_args,
+            max_unsafe_blocks: thresholds.max_unsafe_blocks,
+            max_todos: thresholds.max_todos,
+            fail_if: list_var(&config, "STUPID_STATS_FAIL_IF"),
+            threshold_gates: config.flag("STUPID_STATS_THRESHOLD_GATES").unwrap_or(true),
+            save_baseline: config.var("STUPID_STATS_SAVE_BASELINE").filter(|b| !b.is_empty()),
+            compare_baseline: config
+                .var("STUPID_STATS_COMPARE_BASELINE")
+                .filter(|b| !b.is_empty()),
+            fail_on_regression: config.is_set("STUPID_STATS_FAIL_ON_REGRESSION"),
+            timing: config.is_set("STUPID_STATS_TIMING"),
+            top_macros: usize_var(&config, "STUPID_STATS_TOP_MACROS").unwrap_or(10),
+            args_by_kind: config.is_set("STUPID_STATS_ARGS_BY_KIND"),
+            lang: config
+                .var("STUPID_STATS_LANG")
+                .and_then(|code| Lang::from_code(&code))
+                .unwrap_or(Lang::En),
+            format: config
+                .var("STUPID_STATS_FORMAT")
+                .and_then(|name| Format::from_name(&name))
+                .unwrap_or(Format::Text),
+            sample: config
+                .var("STUPID_STATS_SAMPLE")
+                .and_then(|s| Sample::parse(&s))
+                .unwrap_or(Sample::all()),
+            report_dir: config.var("STUPID_STATS_REPORT_DIR").map(PathBuf::from),
+            json_dir: config.var("STUPID_STATS_JSON_DIR").map(PathBuf::from),
+            binary_stats: config.is_set("STUPID_STATS_BINARY_STATS"),
+            metrics: Some(list_var(&config, "STUPID_STATS_METRICS")).filter(|m| !m.is_empty()),
+            per_module: config.is_set("STUPID_STATS_PER_MODULE"),
+            module_filter: config.var("STUPID_STATS_MODULE_FILTER").filter(|f| !f.is_empty()),
+            stats_output: config.var("STUPID_STATS_OUTPUT").filter(|o| !o.is_empty()),
+            include_generated: config.is_set("STUPID_STATS_INCLUDE_GENERATED"),
+            api_inventory: config.var("STUPID_STATS_API_INVENTORY").filter(|a| !a.is_empty()),
+            per_function_csv: config
+                .var("STUPID_STATS_PER_FUNCTION_CSV")
+                .filter(|c| !c.is_empty()),
+            low_memory: config.is_set("STUPID_STATS_LOW_MEMORY"),
+            sink: config.var("STUPID_STATS_SINK").filter(|s| !s.is_empty()),
+            anonymize: config.is_set("STUPID_STATS_ANONYMIZE"),
+            no_codegen: config.flag("STUPID_STATS_NO_CODEGEN"),
+            webhook: config.var("STUPID_STATS_WEBHOOK").filter(|w| !w.is_empty()),
+            upload: config.var("STUPID_STATS_UPLOAD").filter(|u| !u.is_empty()),
+            upload_endpoint: config.var("STUPID_STATS_UPLOAD_ENDPOINT").filter(|e| !e.is_empty()),
+            post_report: config.var("STUPID_STATS_POST_REPORT").filter(|c| !c.is_empty()),
+            config,
+        }
+    }
+
+    pub fn set_profile(&mut self, profile: Profile) {
+        let thresholds = thresholds(profile, &self.config);
+        self.profile = profile;
+        self.cognitive_threshold = thresholds.cognitive;
+        self.assert_complexity = thresholds.assert_complexity;
+        self.min_assertions = thresholds.min_assertions;
+        self.max_cyclomatic = thresholds.max_cyclomatic;
+        self.max_args = thresholds.max_args;
+        self.max_unsafe_blocks = thresholds.max_unsafe_blocks;
+        self.max_todos = thresholds.max_todos;
+    }
+
+    // The `--fail-if` conditions, with those for the thresholds which have
+    // them. A threshold for a metric we aren't running doesn't count, rather
+    // than failing as an unknown stat.
+    pub fn gates(&self) -> Vec<String> {
+        let mut gates = self.fail_if.clone();
+        if !self.threshold_gates {
+            return gates;
+        }
+        let limits = [
+            ("args", "max_args", self.max_args),
+            ("unsafe", "unsafe.blocks", self.max_unsafe_blocks),
+            ("panics", "panics.todo", self.max_todos),
+        ];
+        for &(metric, stat, limit) in &limits {
+            if let Some(limit) = limit.filter(|_| self.metric_enabled(metric)) {
+                gates.push(format!("{}>{}", stat, limit));
+            }
+        }
+        gates
+    }
+
+    // The config file the options were read from, if there was one.
+    pub fn config_file(&self) -> Option<&Path> {
+        self.config.path()
+    }
+
+    pub fn metric_enabled(&self, id: &str) -> bool {
+        self.metrics.as_ref().map_or(true, |m| m.iter().any(|m| m == id))
+    }
+
+    // Takes our own flags out of `args` (rustc wouldn't know what to do with
+    // them) and applies them, leaving the arguments for rustc. If there is a
+    // `--`, everything before it is ours and everything after it is rustc's;
+    // otherwise, our flags can be anywhere, and anything else is rustc's except
+    // for unknown `--stats-*` flags, which are surely typos of ours. Flags which
+    // take a value can have it after `=` or as the next argument. Returns an
+    // error message for an unknown flag, a missing value, or a value we can't
+    // use. The flags are in `FLAGS`.
+    pub fn take_flags(&mut self, args: &mut Vec<String>) -> Result<(), String> {
+        let separator = args.iter().position(|arg| arg == "--");
+        let (ours, theirs) = match separator {
+            Some(i) => (args[1..i].to_vec(), args[i + 1..].to_vec()),
+            None => (args[1..].to_vec(), vec![]),
+        };
+
+        let mut rustc_args = vec![args[0].clone()];
+        let mut ours = ours.into_iter();
+        while let Some(arg) = ours.next() {
+            if !arg.starts_with("--") {
+                if separator.is_some() {
+                    return Err(format!(
+                        "unexpected argument `{}`, rustc's arguments go after `--`",
+                        arg
+                    ));
+                }
+                rustc_args.push(arg);
+                continue;
+            }
+
+            let (name, value) = match arg.find('=') {
+                Some(i) => (&arg[2..i], Some(arg[i + 1..].to_owned())),
+                None => (&arg[2..], None),
+            };
+            let takes_value = match FLAGS.iter().find(|f| f.0 == name) {
+                Some(&(_, takes_value)) => takes_value,
+                None if separator.is_some() || name.starts_with("stats-") => {
+                    return Err(unknown_flag(name));
+                }
+                None => {
+                    rustc_args.push(arg.clone());
+                    continue;
+                }
+            };
+            let value = match (takes_value, value) {
+                (true, Some(value)) => Some(value),
+                (true, None) => match ours.next() {
+                    Some(value) => Some(value),
+                    None => return Err(format!("`--{}` needs a value", name)),
+                },
+                (false, Some(_)) => return Err(format!("`--{}` doesn't take a value", name)),
+                (false, None) => None,
+            };
+            self.apply_flag(name, value.unwrap_or_default())?;
+        }
+
+        rustc_args.extend(theirs);
+        *args = rustc_args;
+        Ok(())
+    }
+
+    fn apply_flag(&mut self, name: &str, value: String) -> Result<(), String> {
+        match name {
+            "stats-format" => {
+                self.format = Format::from_name(&value).ok_or_else(|| {
+                    format!("unknown format `{}`, expected text, junit, or json", value)
+                })?
+            }
+            "metrics" => {
+                let metrics = split_list(&value);
+                self.metrics = Some(metrics).filter(|m| !m.is_empty());
+            }
+            "module-filter" => self.module_filter = Some(value).filter(|f| !f.is_empty()),
+            "stats-output" => self.stats_output = Some(value).filter(|o| !o.is_empty()),
+            "per-function-csv" => self.per_function_csv = Some(value).filter(|p| !p.is_empty()),
+            "low-memory" => self.low_memory = true,
+            "sink" => {
+                if !value.starts_with("tcp://") && !value.starts_with("unix://") {
+                    return Err(format!(
+                        "`--sink` needs a `tcp://` or `unix://` address, not `{}`",
+                        value
+                    ));
+                }
+                self.sink = Some(value);
+            }
+            "include-generated" => self.include_generated = true,
+            "anonymize" => self.anonymize = true,
+            "no-codegen" => self.no_codegen = Some(true),
+            "profile" => {
+                let profile = Profile::from_name(&value).ok_or_else(|| {
+                    format!("unknown profile `{}`, expected strict, default, or lenient", value)
+                })?;
+                self.set_profile(profile);
+            }
+            "fail-if" => self.fail_if.push(value),
+            "save-baseline" => self.save_baseline = Some(value),
+            "compare-baseline" => self.compare_baseline = Some(value),
+            "fail-on-regression" => self.fail_on_regression = true,
+            "lang" => {
+                self.lang = Lang::from_code(&value)
+                    .ok_or_else(|| format!("unknown language `{}`, expected en or de", value))?
+            }
+            "sample" => {
+                self.sample = Sample::parse(&value).ok_or_else(|| {
+                    format!("`--sample` needs a percentage from 0 to 100, not `{}`", value)
+                })?
+            }
+            _ => unreachable!("`--{}` is in `FLAGS` but not handled", name),
+        }
+        Ok(())
+    }
+}
+
+// Our flags, and whether each takes a value.
+const FLAGS: &[(&str, bool)] = &[
+    ("stats-format", true),
+    ("metrics", true),
+    ("module-filter", true),
+    ("stats-output", true),
+    ("per-function-csv", true),
+    ("low-memory", false),
+    ("sink", true),
+    ("anonymize", false),
+    ("include-generated", false),
+    ("no-codegen", false),
+    ("profile", true),
+    ("fail-if", true),
+    ("save-baseline", true),
+    ("compare-baseline", true),
+    ("fail-on-regression", false),
+    ("sample", true),
+    ("lang", true),
+];
+
+// The error for an unknown flag, suggesting the one of ours which is closest,
+// if any is close.
+fn unknown_flag(name: &str) -> String {
+    let closest = FLAGS
+        .iter()
+        .map(|f| (edit_distance(name, f.0), f.0))
+        .filter(|&(distance, _)| distance <= 3)
+        .min();
+    match closest {
+        Some((_, flag)) => format!("unknown flag `--{}`, did you mean `--{}`?", name, flag),
+        None => {
+            let flags: Vec<_> = FLAGS.iter().map(|f| format!("`--{}`", f.0)).collect();
+            format!("unknown flag `--{}`, stupid-stats' flags are {}", name, flags.join(", "))
+        }
+    }
+}
+
+// The Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = previous[j] + if ca == cb { 0 } else { 1 };
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// The profile's thresholds, with any which are set in the environment or the
+// config file instead.
+fn thresholds(profile: Profile, config: &Config) -> Thresholds {
+    let defaults = profile.thresholds();
+    Thresholds {
+        cognitive: usize_var(config, "STUPID_STATS_COGNITIVE_THRESHOLD")
+            .unwrap_or(defaults.cognitive),
+        max_cyclomatic: limit_var(config, "STUPID_STATS_MAX_CYCLOMATIC", defaults.max_cyclomatic),
+        assert_complexity: limit_var(
+            config,
+            "STUPID_STATS_ASSERT_COMPLEXITY",
+            defaults.assert_complexity,
+        ),
+        min_assertions: usize_var(config, "STUPID_STATS_MIN_ASSERTIONS")
+            .unwrap_or(defaults.min_assertions),
+        max_args: limit_var(config, "STUPID_STATS_MAX_ARGS", defaults.max_args),
+        max_unsafe_blocks: limit_var(
+            config,
+            "STUPID_STATS_MAX_UNSAFE_BLOCKS",
+            defaults.max_unsafe_blocks,
+        ),
+        max_todos: limit_var(config, "STUPID_STATS_MAX_TODOS", defaults.max_todos),
+    }
+}
+
+// Reads a threshold which can be turned off: a number, or `none` for no rule
+// even if the profile has one. Otherwise it's the profile's.
+fn limit_var(config: &Config, name: &str, default: Option<usize>) -> Option<usize> {
+    match config.var(name) {
+        Some(ref value) if value.trim() == "none" => None,
+        Some(value) => value.trim().parse().ok().or(default),
+        None => default,
+    }
+}
+
+// Reads a comma-separated list from an environment variable (or the config
+// file).
+fn list_var(config: &Config, name: &str) -> Vec<String> {
+    match config.var(name) {
+        Some(value) => split_list(&value),
+        None => vec![],
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned())
+        .collect()
+}
+
+// Reads a number from an environment variable (or the config file). Values
+// which aren't numbers are ignored.
+fn usize_var(config: &Config, name: &str) -> Option<usize> {
+    config.var(name).and_then(|value| value.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn take(mut command_line: Vec<String>) -> (Options, Result<Vec<String>, String>) {
+        let mut options = Options::from_config(Config::empty());
+        let result = options.take_flags(&mut command_line).map(|_| command_line);
+        (options, result)
+    }
+
+    #[test]
+    fn codegen_is_rustcs() {
+        let command_line = args(&["stupid", "--codegen", "opt-level=3", "foo.rs"]);
+        let (options, result) = take(command_line.clone());
+        assert_eq!(result, Ok(command_line));
+        assert_eq!(options.no_codegen, None);
+    }
+
+    #[test]
+    fn flags_anywhere() {
+        let (options, result) = take(args(&[
+            "stupid",
+            "--metrics=println,args",
+            "foo.rs",
+            "--no-codegen",
+            "--edition=2018",
+            "--profile",
+            "strict",
+        ]));
+        assert_eq!(result, Ok(args(&["stupid", "foo.rs", "--edition=2018"])));
+        assert_eq!(options.metrics, Some(args(&["println", "args"])));
+        assert_eq!(options.no_codegen, Some(true));
+        assert_eq!(options.profile, Profile::Strict);
+        assert_eq!(options.lang, Lang::En);
+
+        let (options, result) = take(args(&["stupid", "--lang=de-AT", "foo.rs"]));
+        assert_eq!(result, Ok(args(&["stupid", "foo.rs"])));
+        assert_eq!(options.lang, Lang::De);
+    }
+
+    #[test]
+    fn separator() {
+        let (options, result) =
+            take(args(&["stupid", "--fail-if", "max_args>7", "--", "--metrics", "foo.rs"]));
+        assert_eq!(result, Ok(args(&["stupid", "--metrics", "foo.rs"])));
+        assert_eq!(options.fail_if, args(&["max_args>7"]));
+        assert_eq!(options.metrics, None);
+
+        let (_, result) = take(args(&["stupid", "foo.rs", "--", "-O"]));
+        assert_eq!(
+            result,
+            Err("unexpected argument `foo.rs`, rustc's arguments go after `--`".to_owned())
+        );
+    }
+
+    #[test]
+    fn unknown_flags() {
+        let (_, result) = take(args(&["stupid", "--stats-fromat=json", "foo.rs"]));
+        assert_eq!(
+            result,
+            Err("unknown flag `--stats-fromat`, did you mean `--stats-format`?".to_owned())
+        );
+
+        let (_, result) = take(args(&["stupid", "--codegen", "--", "foo.rs"]));
+        assert_eq!(
+            result,
+            Err("unknown flag `--codegen`, did you mean `--no-codegen`?".to_owned())
+        );
+
+        let (_, result) = take(args(&["stupid", "--verbose-stats", "--", "foo.rs"]));
+        let error = result.unwrap_err();
+        assert!(error.starts_with("unknown flag `--verbose-stats`, stupid-stats' flags are "));
+        assert!(error.contains("`--metrics`"));
+    }
+
+    #[test]
+    fn bad_values() {
+        let (_, result) = take(args(&["stupid", "foo.rs", "--metrics"]));
+        assert_eq!(result, Err("`--metrics` needs a value".to_owned()));
+
+        let (_, result) = take(args(&["stupid", "--low-memory=yes", "foo.rs"]));
+        assert_eq!(result, Err("`--low-memory` doesn't take a value".to_owned()));
+
+        let (_, result) = take(args(&["stupid", "--sample", "200%", "foo.rs"]));
+        assert_eq!(
+            result,
+            Err("`--sample` needs a percentage from 0 to 100, not `200%`".to_owned())
+        );
+
+        let (_, result) = take(args(&["stupid", "--sink=localhost:9000", "foo.rs"]));
+        assert_eq!(
+            result,
+            Err("`--sink` needs a `tcp://` or `unix://` address, not `localhost:9000`".to_owned())
+        );
+
+        let (_, result) = take(args(&["stupid", "--lang", "fr", "foo.rs"]));
+        assert_eq!(result, Err("unknown language `fr`, expected en or de".to_owned()));
+    }
+}
@@ -0,0 +1,234 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// `pub(crate)` items which don't need to be. After analysis every path is
+// resolved, so we record which module each use of an item is in, including
+// method calls and other paths which type checking resolves. If every use of a
+// `pub(crate)` item is inside some module below the crate root, the item could
+// be visible to just that module: private if it's the item's own module,
+// `pub(super)` if it's the parent, and so on.
+//
+// Uses of fields aren't tracked, so `pub(crate)` fields aren't checked.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+
+use rustc_hir as hir;
+use rustc_hir::def::Res;
+use rustc_hir::def_id::{DefId, LocalDefId, CRATE_DEF_INDEX};
+use rustc_hir::intravisit::{self, NestedVisitorMap, Visitor};
+use rustc_middle::hir::map::Map;
+use rustc_middle::ty::{self, TyCtxt, TypeckResults};
+use rustc_span::Span;
+
+use findings::{Finding, Findings, Severity};
+use metrics::{AnalysisContext, Metric, ReportContext, Stage};
+
+// How many of the over-exposed items to list.
+const TOP_ITEMS: usize = 10;
+
+struct Overexposed {
+    name: String,
+    span: Span,
+    // The visibility the item could have instead.
+    suggestion: String,
+}
+
+pub struct OverexposedItems {
+    pub_crate: usize,
+    overexposed: Vec<Overexposed>,
+}
+
+impl OverexposedItems {
+    pub fn new() -> OverexposedItems {
+        OverexposedItems {
+            pub_crate: 0,
+            overexposed: vec![],
+        }
+    }
+}
+
+impl Metric for OverexposedItems {
+    fn id(&self) -> &'static str {
+        "overexposed"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Analysis]
+    }
+
+    fn check_analysis<'tcx>(&mut self, cx: &AnalysisContext<'tcx>) {
+        let mut finder = Finder {
+            tcx: cx.tcx,
+            results: None,
+            candidates: vec![],
+            uses: HashMap::new(),
+        };
+        cx.tcx.hir().krate().visit_all_item_likes(&mut finder.as_deep_visitor());
+        // Uses anywhere in the crate count, but only the items in the module
+        // we're looking at are candidates.
+        finder.candidates.retain(|&def_id| cx.includes(def_id));
+        *self = finder.overexposed();
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "{} of {} `pub(crate)` items could be less visible;",
+            self.overexposed.len(),
+            self.pub_crate
+        )?;
+        for item in self.overexposed.iter().take(TOP_ITEMS) {
+            writeln!(
+                out,
+                "    `{}` could be {}: {}",
+                item.name,
+                item.suggestion,
+                cx.source_map.span_to_string(item.span)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn findings(&self, findings: &mut Findings) {
+        for item in &self.overexposed {
+            findings.push(Finding {
+                id: "overexposed-visibility",
+                severity: Severity::Note,
+                span: item.span,
+                message: format!("`{}` is `pub(crate)` but could be {}", item.name, item.suggestion),
+                value: None,
+            });
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(OverexposedItems::new())
+    }
+}
+
+struct Finder<'tcx> {
+    tcx: TyCtxt<'tcx>,
+    // The type checking results for the body we're in.
+    results: Option<&'tcx TypeckResults<'tcx>>,
+    // `pub(crate)` items which are not in the crate root (where `pub(crate)`
+    // is the same as private).
+    candidates: Vec<LocalDefId>,
+    // For each local item, the modules it's used in.
+    uses: HashMap<LocalDefId, HashSet<DefId>>,
+}
+
+impl<'tcx> Finder<'tcx> {
+    fn crate_root() -> DefId {
+        DefId::local(CRATE_DEF_INDEX)
+    }
+
+    fn check_visibility(&mut self, def_id: LocalDefId) {
+        let root = Finder::crate_root();
+        if self.tcx.visibility(def_id.to_def_id()) == ty::Visibility::Restricted(root) &&
+            self.tcx.parent_module_from_def_id(def_id).to_def_id() != root
+        {
+            self.candidates.push(def_id);
+        }
+    }
+
+    fn record_use(&mut self, def_id: DefId, hir_id: hir::HirId) {
+        if let Some(def_id) = def_id.as_local() {
+            let module = self.tcx.parent_module(hir_id).to_def_id();
+            self.uses.entry(def_id).or_insert_with(HashSet::new).insert(module);
+        }
+    }
+
+    // The innermost module containing `module` and all of `uses`.
+    fn common_ancestor(&self, module: DefId, uses: &HashSet<DefId>) -> DefId {
+        let mut ancestor = module;
+        while ancestor != Finder::crate_root() &&
+            !uses.iter().all(|&u| self.tcx.is_descendant_of(u, ancestor))
+        {
+            ancestor = self.tcx.parent(ancestor).unwrap_or(Finder::crate_root());
+        }
+        ancestor
+    }
+
+    fn overexposed(&self) -> OverexposedItems {
+        let empty = HashSet::new();
+        let mut overexposed = vec![];
+        for &def_id in &self.candidates {
+            let module = self.tcx.parent_module_from_def_id(def_id).to_def_id();
+            let uses = self.uses.get(&def_id).unwrap_or(&empty);
+            let ancestor = self.common_ancestor(module, uses);
+            let suggestion = if ancestor == Finder::crate_root() {
+                continue;
+            } else if ancestor == module {
+                "private".to_owned()
+            } else if Some(ancestor) == self.tcx.parent(module) {
+                "`pub(super)`".to_owned()
+            } else {
+                format!("`pub(in crate::{})`", self.tcx.def_path_str(ancestor))
+            };
+            overexposed.push(Overexposed {
+                name: self.tcx.def_path_str(def_id.to_def_id()),
+                span: self.tcx.def_span(def_id),
+                suggestion,
+            });
+        }
+        overexposed.sort_by(|a, b| a.name.cmp(&b.name));
+
+        OverexposedItems {
+            pub_crate: self.candidates.len(),
+            overexposed,
+        }
+    }
+}
+
+impl<'tcx> Visitor<'tcx> for Finder<'tcx> {
+    type Map = Map<'tcx>;
+
+    fn nested_visit_map(&mut self) -> NestedVisitorMap<Self::Map> {
+        NestedVisitorMap::OnlyBodies(self.tcx.hir())
+    }
+
+    fn visit_nested_body(&mut self, id: hir::BodyId) {
+        let old = self.results.replace(self.tcx.typeck_body(id));
+        let body = self.tcx.hir().body(id);
+        self.visit_body(body);
+        self.results = old;
+    }
+
+    fn visit_item(&mut self, i: &'tcx hir::Item<'tcx>) {
+        match i.kind {
+            // Visibility on these doesn't mean the same thing.
+            hir::ItemKind::Use(..) | hir::ItemKind::Impl(..) | hir::ItemKind::Mod(..) => {}
+            _ => self.check_visibility(self.tcx.hir().local_def_id(i.hir_id)),
+        }
+        intravisit::walk_item(self, i)
+    }
+
+    fn visit_impl_item(&mut self, i: &'tcx hir::ImplItem<'tcx>) {
+        self.check_visibility(self.tcx.hir().local_def_id(i.hir_id));
+        intravisit::walk_impl_item(self, i)
+    }
+
+    fn visit_path(&mut self, path: &'tcx hir::Path<'tcx>, hir_id: hir::HirId) {
+        if let Res::Def(_, def_id) = path.res {
+            self.record_use(def_id, hir_id);
+        }
+        intravisit::walk_path(self, path)
+    }
+
+    // Method calls and paths like `Type::function` are resolved by type
+    // checking, so `visit_path` doesn't see them.
+    fn visit_expr(&mut self, e: &'tcx hir::Expr<'tcx>) {
+        if let Some(results) = self.results {
+            if let Some(def_id) = results.type_dependent_def_id(e.hir_id) {
+                self.record_use(def_id, e.hir_id);
+            }
+        }
+        intravisit::walk_expr(self, e)
+    }
+}
@@ -0,0 +1,129 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Who owns which files, according to a CODEOWNERS file. We look for one in the
+// places GitHub does (the root, `.github/`, and `docs/`) in the crate's
+// directory and each of its parents, so a crate in a monorepo finds the file at
+// the top of the repo.
+//
+// Patterns are a subset of gitignore's: `*`, `**`, and `?` wildcards, leading
+// `/` to anchor a pattern at the root, and trailing `/` for directories. As in
+// CODEOWNERS, the last matching pattern wins.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub struct Owners {
+    // The directory the patterns are relative to.
+    root: PathBuf,
+    // Patterns and their owners, in the order they appear in the file.
+    rules: Vec<(String, Vec<String>)>,
+}
+
+impl Owners {
+    // Finds and reads the CODEOWNERS file for the crate being compiled, if
+    // there is one.
+    pub fn find() -> Option<Owners> {
+        let start = env::var_os("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .or_else(|| env::current_dir().ok())?;
+        for dir in start.ancestors() {
+            for location in &["CODEOWNERS", ".github/CODEOWNERS", "docs/CODEOWNERS"] {
+                if let Ok(text) = fs::read_to_string(dir.join(location)) {
+                    return Some(Owners::parse(dir, &text));
+                }
+            }
+        }
+        None
+    }
+
+    fn parse(root: &Path, text: &str) -> Owners {
+        let rules = text
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .filter_map(|l| {
+                let mut words = l.split_whitespace();
+                let pattern = words.next()?.to_owned();
+                Some((pattern, words.map(|w| w.to_owned()).collect()))
+            })
+            .collect();
+        Owners {
+            root: root.to_owned(),
+            rules,
+        }
+    }
+
+    // The owners of a file, or an empty slice if it has none. Relative paths
+    // are relative to the current directory, as they are for rustc.
+    pub fn owners_of(&self, path: &Path) -> &[String] {
+        let absolute = match env::current_dir() {
+            Ok(dir) => dir.join(path),
+            Err(_) => path.to_owned(),
+        };
+        let relative = match absolute.strip_prefix(&self.root) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => return &[],
+        };
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|&&(ref pattern, _)| pattern_matches(pattern, &relative))
+            .map_or(&[], |&(_, ref owners)| &owners[..])
+    }
+}
+
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    // A pattern for a directory matches everything in it.
+    let pattern = if pattern.ends_with('/') {
+        format!("{}**", pattern)
+    } else {
+        pattern.to_owned()
+    };
+
+    // A pattern with no slash (except at the end) matches at any depth;
+    // otherwise it is relative to the root.
+    let pattern = match pattern.trim_end_matches("/**").find('/') {
+        Some(0) => pattern[1..].to_owned(),
+        Some(_) => pattern,
+        None => format!("**/{}", pattern),
+    };
+
+    // A pattern which names a directory (without the trailing slash) also
+    // matches everything in it.
+    glob_matches(pattern.as_bytes(), path.as_bytes()) ||
+        glob_matches(format!("{}/**", pattern).as_bytes(), path.as_bytes())
+}
+
+fn glob_matches(pattern: &[u8], path: &[u8]) -> bool {
+    if pattern.starts_with(b"**/") {
+        // Zero or more whole directories.
+        let rest = &pattern[3..];
+        return glob_matches(rest, path) ||
+            path.iter()
+                .enumerate()
+                .any(|(i, &c)| c == b'/' && glob_matches(rest, &path[i + 1..]));
+    }
+    if pattern == b"**" {
+        return true;
+    }
+
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(&b'*'), _) => {
+            // Anything within a single path component.
+            glob_matches(&pattern[1..], path) ||
+                (!path.is_empty() && path[0] != b'/' && glob_matches(pattern, &path[1..]))
+        }
+        (Some(&b'?'), Some(&c)) => c != b'/' && glob_matches(&pattern[1..], &path[1..]),
+        (Some(&p), Some(&c)) => p == c && glob_matches(&pattern[1..], &path[1..]),
+        _ => false,
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Places which can panic: calls to `.unwrap()` and `.expect()`, and uses of
+// `panic!`, `todo!`, `unimplemented!`, and `unreachable!`. We count each sort,
+// and list the functions with the most, with where each one is. We look
+// before expansion and go by the names, so any method called `unwrap` counts.
+// Closures count towards the function they're in. With
+// `STUPID_STATS_MAX_TODOS`, if there are more `todo!`s than that, each is a
+// finding.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::mem;
+
+use rustc_ast::{ast, visit};
+use rustc_span::Span;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use findings::{Finding, Findings, Severity};
+use metrics::{Metric, ReportContext};
+
+// The methods and macros we count, in the order we report them.
+const PANICKY: &[&str] =
+    &[".unwrap()", ".expect()", "panic!", "todo!", "unimplemented!", "unreachable!"];
+
+// How many of the worst functions to list, and how many places in each.
+const TOP_FUNCTIONS: usize = 10;
+const TOP_PLACES: usize = 5;
+
+pub struct PanicsMetric {
+    totals: BTreeMap<&'static str, Counter>,
+    // The name and span of each function with any, and where they are.
+    functions: Vec<(String, Span, Vec<(&'static str, Span)>)>,
+    // The places in the function we're in (or outside any function).
+    current: Vec<(&'static str, Span)>,
+    // The places in each function we're inside, outermost first.
+    outer: Vec<Vec<(&'static str, Span)>>,
+    todos: Vec<Span>,
+    max_todos: Option<usize>,
+}
+
+impl PanicsMetric {
+    pub fn new(max_todos: Option<usize>) -> PanicsMetric {
+        PanicsMetric {
+            totals: BTreeMap::new(),
+            functions: vec![],
+            current: vec![],
+            outer: vec![],
+            todos: vec![],
+            max_todos,
+        }
+    }
+
+    fn record(&mut self, what: &'static str, span: Span) {
+        if what == "todo!" {
+            self.todos.push(span);
+        }
+        self.totals.entry(what).or_default().increment();
+        self.current.push((what, span));
+    }
+}
+
+impl Metric for PanicsMetric {
+    fn id(&self) -> &'static str {
+        "panics"
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, _s: Span) {
+        if let visit::FnKind::Fn(_, _, _, _, Some(_)) = fk {
+            let outer = mem::replace(&mut self.current, vec![]);
+            self.outer.push(outer);
+        }
+    }
+
+    fn exit_fn(&mut self, fk: visit::FnKind, s: Span) {
+        if let visit::FnKind::Fn(_, ident, _, _, Some(_)) = fk {
+            let outer = self.outer.pop().unwrap_or_default();
+            let places = mem::replace(&mut self.current, outer);
+            if !places.is_empty() {
+                self.functions.push((ident.to_string(), s, places));
+            }
+        }
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        if let ast::ExprKind::MethodCall(ref segment, _, _) = e.kind {
+            match &*segment.ident.as_str() {
+                "unwrap" => self.record(".unwrap()", e.span),
+                "expect" => self.record(".expect()", e.span),
+                _ => {}
+            }
+        }
+    }
+
+    fn visit_mac_call(&mut self, mac: &ast::MacCall) {
+        if let Some(segment) = mac.path.segments.last() {
+            match &*segment.ident.as_str() {
+                "panic" => self.record("panic!", mac.span()),
+                "todo" => self.record("todo!", mac.span()),
+                "unimplemented" => self.record("unimplemented!", mac.span()),
+                "unreachable" => self.record("unreachable!", mac.span()),
+                _ => {}
+            }
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let total = Counter::sum(self.totals.values());
+        writeln!(out, "Found {} places which can panic:", total)?;
+        for what in PANICKY {
+            let count = self.totals.get(what).cloned().unwrap_or_default();
+            writeln!(out, "    `{}`: {}", what, count)?;
+        }
+
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by(|a, b| b.2.len().cmp(&a.2.len()));
+        if !functions.is_empty() {
+            writeln!(out, "The functions with the most are:")?;
+        }
+        for &&(ref name, span, ref places) in functions.iter().take(TOP_FUNCTIONS) {
+            writeln!(
+                out,
+                "    `{}`: {} at {}",
+                name,
+                places.len(),
+                cx.source_map.span_to_string(span)
+            )?;
+            for &(what, span) in places.iter().take(TOP_PLACES) {
+                writeln!(out, "        `{}` at {}", what, cx.source_map.span_to_string(span))?;
+            }
+            if places.len() > TOP_PLACES {
+                writeln!(out, "        and {} more", places.len() - TOP_PLACES)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("panics.total", Counter::sum(self.totals.values()));
+        stats.set_count("panics.todo", self.totals.get("todo!").cloned().unwrap_or_default());
+    }
+
+    fn findings(&self, findings: &mut Findings) {
+        let max = match self.max_todos {
+            Some(max) if self.todos.len() > max => max,
+            _ => return,
+        };
+        for &span in &self.todos {
+            findings.push(Finding {
+                id: "todo",
+                severity: Severity::Warning,
+                span,
+                message: format!("`todo!` (the crate has {}, over {})", self.todos.len(), max),
+                value: None,
+            });
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(PanicsMetric::new(self.max_todos))
+    }
+}
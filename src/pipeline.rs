@@ -0,0 +1,170 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Pipeline functions: those shaped like `fn(T, ...) -> T`, which take a value
+// and return a transformed one of the same type (including builder methods
+// which take `self` and return `Self`). Crates which process data tend to be
+// built out of them, so we count them, list the types they're most used for,
+// and find chains of calls to them, e.g., `normalize(trim(s))` or
+// `b.name(n).size(s)`. We compare the types as written, since we don't have
+// types before expansion, and go by the names of the functions for chains.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+// How many types and chains to list.
+const TOP_TYPES: usize = 10;
+const TOP_CHAINS: usize = 10;
+
+// The type a function is a pipeline for, if it is one.
+fn pipeline_type(decl: &ast::FnDecl) -> Option<String> {
+    let output = match decl.output {
+        ast::FnRetTy::Ty(ref ty) => pprust::ty_to_string(ty),
+        ast::FnRetTy::Default(_) => return None,
+    };
+    match decl.inputs.first() {
+        Some(param) if output != "()" && pprust::ty_to_string(&param.ty) == output => {
+            Some(output)
+        }
+        _ => None,
+    }
+}
+
+pub struct PipelineMetric {
+    functions: Counter,
+    // How many pipeline functions there are for each type.
+    types: HashMap<String, Counter>,
+    names: HashSet<String>,
+    // The name and span of each call with arguments, and the span of the
+    // value which would be piped through it.
+    calls: Vec<(String, Span, Span)>,
+}
+
+impl PipelineMetric {
+    pub fn new() -> PipelineMetric {
+        PipelineMetric {
+            functions: Counter::new(),
+            types: HashMap::new(),
+            names: HashSet::new(),
+            calls: vec![],
+        }
+    }
+
+    // We need all the names before we can find chains, so this is done from
+    // the calls we saw once the walk is over. Each chain is found from its
+    // outermost call, following the value which is piped through (the
+    // receiver, for methods) in to the start of it, so that we only count the
+    // whole chain, not each part of it.
+    fn chains(&self) -> Vec<(usize, Span)> {
+        let links: HashMap<Span, Span> = self
+            .calls
+            .iter()
+            .filter(|&&(ref name, _, _)| self.names.contains(name))
+            .map(|&(_, span, piped)| (span, piped))
+            .collect();
+        let piped: HashSet<Span> = links.values().cloned().collect();
+
+        let mut chains = vec![];
+        for &(ref name, span, _) in &self.calls {
+            if !self.names.contains(name) || piped.contains(&span) {
+                continue;
+            }
+            let mut length = 0;
+            let mut current = span;
+            while let Some(&next) = links.get(&current) {
+                length += 1;
+                current = next;
+            }
+            if length >= 2 {
+                chains.push((length, span));
+            }
+        }
+        chains
+    }
+}
+
+impl Metric for PipelineMetric {
+    fn id(&self) -> &'static str {
+        "pipeline"
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(_, ident, sig, _, Some(_)) = fk {
+            self.functions.increment();
+            if let Some(ty) = pipeline_type(&sig.decl) {
+                self.types.entry(ty).or_default().increment();
+                self.names.insert(ident.to_string());
+            }
+        }
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        let (name, args) = match e.kind {
+            ast::ExprKind::Call(ref f, ref args) => match f.kind {
+                ast::ExprKind::Path(None, ref path) => match path.segments.last() {
+                    Some(segment) => (segment.ident, args),
+                    None => return,
+                },
+                _ => return,
+            },
+            ast::ExprKind::MethodCall(ref segment, ref args, _) => (segment.ident, args),
+            _ => return,
+        };
+        if let Some(first) = args.first() {
+            self.calls.push((name.to_string(), e.span, first.span));
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let pipelines = Counter::sum(self.types.values());
+        let percent = if self.functions.get() == 0 {
+            0.0
+        } else {
+            100.0 * pipelines.get() as f64 / self.functions.get() as f64
+        };
+        writeln!(
+            out,
+            "{} functions ({:.1}%) take and return the same type;",
+            pipelines,
+            percent
+        )?;
+        let mut types: Vec<_> = self.types.iter().collect();
+        types.sort_by(|a, b| b.1.get().cmp(&a.1.get()).then(a.0.cmp(b.0)));
+        for (ty, count) in types.into_iter().take(TOP_TYPES) {
+            writeln!(out, "    `{}`: {}", ty, count)?;
+        }
+
+        let mut chains = self.chains();
+        writeln!(out, "Found {} chains of calls to them;", chains.len())?;
+        chains.sort_by(|a, b| b.0.cmp(&a.0));
+        if !chains.is_empty() {
+            writeln!(out, "The longest are:")?;
+        }
+        for &(length, span) in chains.iter().take(TOP_CHAINS) {
+            writeln!(out, "    {} calls at {}", length, cx.source_map.span_to_string(span))?;
+        }
+        Ok(())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("pipeline.functions", Counter::sum(self.types.values()));
+        stats.set_count("pipeline.chains", self.chains().len());
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(PipelineMetric::new())
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Whether a crate's data structures use dynamic or static polymorphism: fields
+// holding trait objects (e.g., `Box<dyn Trait>`, wherever the `dyn` is nested
+// in the field's type) versus type parameters on the struct, enum, or union.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+
+use counter::Counter;
+use messages::Message;
+use metrics::{Metric, ReportContext};
+
+// Counts trait object types, wherever they are nested in a type.
+struct DynCounter {
+    count: usize,
+}
+
+impl<'a> visit::Visitor<'a> for DynCounter {
+    fn visit_ty(&mut self, ty: &'a ast::Ty) {
+        if let ast::TyKind::TraitObject(..) = ty.kind {
+            self.count += 1;
+        }
+        visit::walk_ty(self, ty)
+    }
+}
+
+fn has_dyn(ty: &ast::Ty) -> bool {
+    let mut counter = DynCounter { count: 0 };
+    visit::Visitor::visit_ty(&mut counter, ty);
+    counter.count > 0
+}
+
+pub struct PolymorphismMetric {
+    dyn_fields: Counter,
+    type_params: Counter,
+    // Data types with trait object fields only, type parameters only, or both.
+    dyn_only: Counter,
+    generic_only: Counter,
+    mixed: Counter,
+}
+
+impl PolymorphismMetric {
+    pub fn new() -> PolymorphismMetric {
+        PolymorphismMetric {
+            dyn_fields: Counter::new(),
+            type_params: Counter::new(),
+            dyn_only: Counter::new(),
+            generic_only: Counter::new(),
+            mixed: Counter::new(),
+        }
+    }
+
+    fn check_type(&mut self, dyn_fields: usize, generics: &ast::Generics) {
+        let type_params = generics
+            .params
+            .iter()
+            .filter(|p| matches!(p.kind, ast::GenericParamKind::Type { .. }))
+            .count();
+
+        self.dyn_fields.add(dyn_fields);
+        self.type_params.add(type_params);
+        match (dyn_fields > 0, type_params > 0) {
+            (true, false) => self.dyn_only.increment(),
+            (false, true) => self.generic_only.increment(),
+            (true, true) => self.mixed.increment(),
+            (false, false) => {}
+        }
+    }
+}
+
+impl Metric for PolymorphismMetric {
+    fn id(&self) -> &'static str {
+        "polymorphism"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        match i.kind {
+            ast::ItemKind::Struct(ref data, ref generics) |
+            ast::ItemKind::Union(ref data, ref generics) => {
+                let dyn_fields = data.fields().iter().filter(|f| has_dyn(&f.ty)).count();
+                self.check_type(dyn_fields, generics);
+            }
+            ast::ItemKind::Enum(ref def, ref generics) => {
+                let dyn_fields = def
+                    .variants
+                    .iter()
+                    .flat_map(|v| v.data.fields())
+                    .filter(|f| has_dyn(&f.ty))
+                    .count();
+                self.check_type(dyn_fields, generics);
+            }
+            _ => {}
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let lang = cx.lang;
+        writeln!(
+            out,
+            "{}",
+            lang.render(Message::Polymorphism(self.dyn_fields, self.type_params))
+        )?;
+        writeln!(
+            out,
+            "{}",
+            lang.render(Message::PolymorphicTypes(self.dyn_only, self.generic_only, self.mixed))
+        )
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(PolymorphismMetric::new())
+    }
+}
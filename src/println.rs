@@ -0,0 +1,134 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// The original stupid stat: how many times `println!` is used. We count the
+// rest of the formatting macros too, which tell us how a crate does its output
+// and logging, and then every other macro, so we can list the most used ones:
+// those from the compiler and the standard library separately from the crate's
+// own and other crates'.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+
+use rustc_ast::ast;
+use rustc_ast_pretty::pprust;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use messages::Message;
+use metrics::{Metric, ReportContext};
+
+const FORMAT_MACROS: &[&str] =
+    &["print", "println", "eprint", "eprintln", "write", "writeln", "format"];
+
+// The built-in and standard library macros, which can be used without a path.
+const STD_MACROS: &[&str] = &[
+    "asm", "assert", "assert_eq", "assert_ne", "cfg", "column", "compile_error", "concat",
+    "dbg", "debug_assert", "debug_assert_eq", "debug_assert_ne", "env", "eprint", "eprintln",
+    "file", "format", "format_args", "global_asm", "include", "include_bytes", "include_str",
+    "line", "llvm_asm", "matches", "module_path", "option_env", "panic", "print", "println",
+    "stringify", "thread_local", "todo", "try", "unimplemented", "unreachable", "vec", "write",
+    "writeln",
+];
+
+// Whether the macro at `path` comes with the compiler or the standard
+// library. We go by its name, since we look before name resolution.
+fn is_std_macro(path: &ast::Path) -> bool {
+    match path.segments.first().map(|s| s.ident.as_str()) {
+        Some(ref first) if path.segments.len() > 1 => ["std", "core", "alloc"].contains(&&**first),
+        Some(ref name) => STD_MACROS.contains(&&**name),
+        None => false,
+    }
+}
+
+pub struct PrintlnMetric {
+    // The count of prinlns.
+    println_count: Counter,
+    // The count of each of the formatting macros, including `println!` again.
+    format_macros: BTreeMap<String, Counter>,
+    // The count of every macro, by its path as written, and whether it's from
+    // the standard library.
+    macros: HashMap<String, (usize, bool)>,
+    // How many of the most used macros of each sort to list.
+    top_macros: usize,
+}
+
+impl PrintlnMetric {
+    pub fn new(top_macros: usize) -> PrintlnMetric {
+        PrintlnMetric {
+            println_count: Counter::new(),
+            format_macros: BTreeMap::new(),
+            macros: HashMap::new(),
+            top_macros,
+        }
+    }
+
+    fn write_top_macros(&self, std: bool, out: &mut String) -> fmt::Result {
+        let mut macros: Vec<_> = self
+            .macros
+            .iter()
+            .filter(|&(_, &(_, is_std))| is_std == std)
+            .map(|(path, &(count, _))| (path, count))
+            .collect();
+        // Most used first, then by name so the order is stable.
+        macros.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        for (path, count) in macros.into_iter().take(self.top_macros) {
+            writeln!(out, "    `{}!`: {}", path, count)?;
+        }
+        Ok(())
+    }
+}
+
+impl Metric for PrintlnMetric {
+    fn id(&self) -> &'static str {
+        "println"
+    }
+
+    // We found a macro.
+    fn visit_mac_call(&mut self, mac: &ast::MacCall) {
+        // Find its name and check if it is "println".
+        let path = &mac.path;
+        let path_string = pprust::path_to_string(path);
+        if path_string == "println" {
+            self.println_count.increment();
+        }
+        self.macros.entry(path_string).or_insert((0, is_std_macro(path))).0 += 1;
+        // And whether it is one of the formatting macros, however it's named.
+        if let Some(name) = path.segments.last().map(|s| s.ident.to_string()) {
+            if FORMAT_MACROS.contains(&&*name) {
+                self.format_macros.entry(name).or_insert_with(Counter::new).increment();
+            }
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        let lang = cx.lang;
+        writeln!(out, "{}", lang.render(Message::PrintlnCount(self.println_count)))?;
+        writeln!(out, "{}", lang.render(Message::FormatMacros))?;
+        for name in FORMAT_MACROS {
+            let count = self.format_macros.get(*name).cloned().unwrap_or_default();
+            writeln!(out, "    `{}!`: {}", name, count)?;
+        }
+        if self.top_macros > 0 && !self.macros.is_empty() {
+            writeln!(out, "{}", lang.render(Message::TopStdMacros))?;
+            self.write_top_macros(true, out)?;
+            writeln!(out, "{}", lang.render(Message::TopOtherMacros))?;
+            self.write_top_macros(false, out)?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(PrintlnMetric::new(self.top_macros))
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.println_count = self.println_count;
+        stats.format_macros = self.format_macros.clone();
+    }
+}
@@ -0,0 +1,77 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Threshold profiles, so that a new user can pick how picky the rules should
+// be (`--profile strict`) without tuning every threshold themselves. The
+// thresholds' own settings still win over the profile. The limits on
+// arguments, unsafe blocks, and `todo!`s are findings, and `--fail-if`
+// conditions too.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    Strict,
+    Default,
+    Lenient,
+}
+
+// The thresholds for the rules which have them; see `Options` for what each
+// one means.
+pub struct Thresholds {
+    pub cognitive: usize,
+    pub max_cyclomatic: Option<usize>,
+    pub assert_complexity: Option<usize>,
+    pub min_assertions: usize,
+    pub max_args: Option<usize>,
+    pub max_unsafe_blocks: Option<usize>,
+    pub max_todos: Option<usize>,
+}
+
+impl Profile {
+    pub fn from_name(name: &str) -> Option<Profile> {
+        match name {
+            "strict" => Some(Profile::Strict),
+            "default" => Some(Profile::Default),
+            "lenient" => Some(Profile::Lenient),
+            _ => None,
+        }
+    }
+
+    pub fn thresholds(self) -> Thresholds {
+        match self {
+            Profile::Strict => Thresholds {
+                cognitive: 10,
+                max_cyclomatic: Some(10),
+                assert_complexity: Some(5),
+                min_assertions: 2,
+                max_args: Some(5),
+                max_unsafe_blocks: Some(0),
+                max_todos: Some(0),
+            },
+            // What we've always done: SonarSource's cognitive complexity
+            // threshold, and no other rules unless they're asked for.
+            Profile::Default => Thresholds {
+                cognitive: 15,
+                max_cyclomatic: None,
+                assert_complexity: None,
+                min_assertions: 1,
+                max_args: None,
+                max_unsafe_blocks: None,
+                max_todos: None,
+            },
+            Profile::Lenient => Thresholds {
+                cognitive: 25,
+                max_cyclomatic: None,
+                assert_complexity: None,
+                min_assertions: 1,
+                max_args: None,
+                max_unsafe_blocks: None,
+                max_todos: None,
+            },
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// A manifest of how a report was made, so that it can be made again: the
+// command line, the environment variables we look at, the toolchain, the
+// metrics, and the config file (with a hash of what was in it). It is saved as
+// JSON next to each report file.
+
+use std::env;
+use std::fmt::{self, Write};
+use std::fs;
+
+use rustc_interface::util;
+
+use crate_stats::escape;
+use metrics::METRICS;
+use options::Options;
+use sysroot_path;
+
+// The environment variables which aren't ours, but which change what we do.
+const OTHER_VARS: &[&str] = &[
+    "CARGO_MANIFEST_DIR",
+    "CARGO_PKG_NAME",
+    "CARGO_PRIMARY_PACKAGE",
+    "OUT_DIR",
+    "RUSTC_WRAPPER",
+];
+
+pub struct Provenance {
+    command_line: Vec<String>,
+    env: Vec<(String, String)>,
+    rustc_version: Option<&'static str>,
+    rustc_commit: Option<&'static str>,
+    sysroot: Option<String>,
+    metrics: Vec<&'static str>,
+    // The config file and the hash of its contents.
+    config_file: Option<(String, String)>,
+}
+
+impl Provenance {
+    pub fn record(options: &Options) -> Provenance {
+        let mut env: Vec<_> = env::vars()
+            .filter(|&(ref name, _)| {
+                name.starts_with("STUPID_STATS_") || OTHER_VARS.contains(&&**name)
+            })
+            .collect();
+        env.sort();
+        let config_file = options.config_file().map(|path| {
+            let hash = match fs::read(path) {
+                Ok(contents) => format!("{:016x}", fnv1a(&contents)),
+                Err(_) => "unreadable".to_owned(),
+            };
+            (path.display().to_string(), hash)
+        });
+        Provenance {
+            command_line: env::args().collect(),
+            env,
+            rustc_version: util::version_str(),
+            rustc_commit: util::commit_hash_str(),
+            sysroot: sysroot_path(),
+            metrics: METRICS.iter().map(|m| m.id).filter(|id| options.metric_enabled(id)).collect(),
+            config_file,
+        }
+    }
+
+    pub fn write_json(&self, out: &mut String) -> fmt::Result {
+        let string = |s: Option<&str>| match s {
+            Some(s) => format!("\"{}\"", escape(s)),
+            None => "null".to_owned(),
+        };
+        let list = |items: &mut dyn Iterator<Item = &str>| {
+            let items: Vec<_> = items.map(|i| format!("\"{}\"", escape(i))).collect();
+            format!("[{}]", items.join(", "))
+        };
+
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"stupid_stats_version\": \"{}\",", env!("CARGO_PKG_VERSION"))?;
+        writeln!(
+            out,
+            "  \"command_line\": {},",
+            list(&mut self.command_line.iter().map(|a| &**a))
+        )?;
+        let env: Vec<_> = self
+            .env
+            .iter()
+            .map(|&(ref name, ref value)| format!("\"{}\": \"{}\"", escape(name), escape(value)))
+            .collect();
+        writeln!(out, "  \"env\": {{{}}},", env.join(", "))?;
+        writeln!(out, "  \"rustc_version\": {},", string(self.rustc_version))?;
+        writeln!(out, "  \"rustc_commit\": {},", string(self.rustc_commit))?;
+        writeln!(out, "  \"sysroot\": {},", string(self.sysroot.as_ref().map(|s| &**s)))?;
+        writeln!(out, "  \"metrics\": {},", list(&mut self.metrics.iter().cloned()))?;
+        let (config_file, config_hash) = match self.config_file {
+            Some((ref path, ref hash)) => (Some(&**path), Some(&**hash)),
+            None => (None, None),
+        };
+        writeln!(out, "  \"config_file\": {},", string(config_file))?;
+        writeln!(out, "  \"config_hash\": {}", string(config_hash))?;
+        writeln!(out, "}}")
+    }
+}
+
+// FNV-1a, which is simple and doesn't change between versions of Rust (unlike
+// `DefaultHasher`).
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
@@ -0,0 +1,97 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// How methods take `self`: by value, by reference, by mutable reference, or by
+// some smart pointer (e.g., `self: Box<Self>`).
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit, FnKind};
+use rustc_ast_pretty::pprust;
+use rustc_span::symbol::kw;
+
+use counter::Counter;
+use metrics::{Metric, ReportContext};
+
+pub struct ReceiversMetric {
+    // Receiver kinds, e.g., `&self` or `self: Arc<Self>`, to the number of
+    // methods which use them.
+    receivers: BTreeMap<String, Counter>,
+    // Associated functions with no `self` at all.
+    no_receiver: Counter,
+}
+
+impl ReceiversMetric {
+    pub fn new() -> ReceiversMetric {
+        ReceiversMetric {
+            receivers: BTreeMap::new(),
+            no_receiver: Counter::new(),
+        }
+    }
+}
+
+fn receiver_kind(explicit_self: &ast::ExplicitSelf) -> String {
+    let mutability = |m: ast::Mutability| match m {
+        ast::Mutability::Mut => "mut ",
+        ast::Mutability::Not => "",
+    };
+    match explicit_self.node {
+        // `mut self` is the same as `self` to callers.
+        ast::SelfKind::Value(_) => "self".to_owned(),
+        ast::SelfKind::Region(_, m) => format!("&{}self", mutability(m)),
+        ast::SelfKind::Explicit(ref ty, _) => match ty.kind {
+            _ if is_self(ty) => "self".to_owned(),
+            ast::TyKind::Rptr(_, ref mt) if is_self(&mt.ty) => format!("&{}self", mutability(mt.mutbl)),
+            _ => format!("self: {}", pprust::ty_to_string(ty)),
+        },
+    }
+}
+
+fn is_self(ty: &ast::Ty) -> bool {
+    match ty.kind {
+        ast::TyKind::ImplicitSelf => true,
+        ast::TyKind::Path(None, ref path) => *path == kw::SelfUpper,
+        _ => false,
+    }
+}
+
+impl Metric for ReceiversMetric {
+    fn id(&self) -> &'static str {
+        "receivers"
+    }
+
+    fn visit_assoc_item(&mut self, i: &ast::AssocItem, _: visit::AssocCtxt) {
+        if let ast::AssocItemKind::Fn(box FnKind(_, ref sig, ..)) = i.kind {
+            match sig.decl.get_self() {
+                Some(explicit_self) => {
+                    self.receivers
+                        .entry(receiver_kind(&explicit_self))
+                        .or_insert_with(Counter::new)
+                        .increment();
+                }
+                None => self.no_receiver.increment(),
+            }
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let mut receivers: Vec<_> = self.receivers.iter().collect();
+        receivers.sort_by(|a, b| b.1.get().cmp(&a.1.get()));
+
+        writeln!(out, "Methods take `self` as:")?;
+        for (kind, count) in receivers {
+            writeln!(out, "    `{}`: {}", kind, count)?;
+        }
+        writeln!(out, "{} associated functions have no `self`;", self.no_receiver)
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(ReceiversMetric::new())
+    }
+}
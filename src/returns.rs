@@ -0,0 +1,106 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Style statistics about how functions and blocks end. Idiomatic Rust returns
+// from the end of a function using a tail expression rather than `return x;`,
+// and a block which ends by computing a value and throwing it away (e.g.,
+// `x + 1;`) is usually a mistake.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_span::Span;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use metrics::{Metric, ReportContext};
+
+pub struct ReturnsMetric {
+    // Functions whose last statement is `return expr;`.
+    explicit_returns: Counter,
+    // Functions whose last statement is a tail expression.
+    tail_exprs: Counter,
+    // Blocks whose last statement discards a value.
+    discarded: Vec<Span>,
+}
+
+impl ReturnsMetric {
+    pub fn new() -> ReturnsMetric {
+        ReturnsMetric {
+            explicit_returns: Counter::new(),
+            tail_exprs: Counter::new(),
+            discarded: vec![],
+        }
+    }
+}
+
+// Is this the kind of expression which only exists to compute a value? We don't
+// have types, so we only look at the shape of the expression; calls may have
+// side effects, so we never count them.
+fn is_value(e: &ast::Expr) -> bool {
+    match e.kind {
+        ast::ExprKind::Lit(_) |
+        ast::ExprKind::Path(..) |
+        ast::ExprKind::Binary(..) |
+        ast::ExprKind::Field(..) |
+        ast::ExprKind::Index(..) |
+        ast::ExprKind::Array(_) |
+        ast::ExprKind::Struct(..) => true,
+        ast::ExprKind::Tup(ref elems) => !elems.is_empty(),
+        ast::ExprKind::Paren(ref e) => is_value(e),
+        _ => false,
+    }
+}
+
+impl Metric for ReturnsMetric {
+    fn id(&self) -> &'static str {
+        "returns"
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(_, _, _, _, Some(body)) = fk {
+            match body.stmts.last().map(|s| &s.kind) {
+                Some(&ast::StmtKind::Semi(ref e)) | Some(&ast::StmtKind::Expr(ref e))
+                    if matches!(e.kind, ast::ExprKind::Ret(Some(_))) => self.explicit_returns.increment(),
+                Some(&ast::StmtKind::Expr(_)) => self.tail_exprs.increment(),
+                _ => {}
+            }
+        }
+    }
+
+    fn visit_block(&mut self, b: &ast::Block) {
+        if let Some(&ast::StmtKind::Semi(ref e)) = b.stmts.last().map(|s| &s.kind) {
+            if is_value(e) {
+                self.discarded.push(e.span);
+            }
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "{} functions end with an explicit `return` and {} with a tail expression;",
+            self.explicit_returns, self.tail_exprs
+        )?;
+        writeln!(out, "{} blocks end by discarding a value;", self.discarded.len())?;
+        for &span in &self.discarded {
+            writeln!(out, "    {}", cx.source_map.span_to_string(span))?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(ReturnsMetric::new())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("returns.explicit_returns", self.explicit_returns);
+        stats.set_count("returns.tail_exprs", self.tail_exprs);
+        stats.set_count("returns.discarded_values", self.discarded.len());
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Sampling for the expensive per-function metrics on very large crates. Whether
+// a function is in the sample depends only on a hash of its path, so the same
+// functions are picked every run (by any build of stupid-stats) and results
+// can be compared. Metrics which sample (see `Metric::sampled`) report
+// estimates for the whole crate, with 95% confidence intervals.
+
+use provenance::fnv1a;
+
+// Mixed into every hash; changing it picks a different sample.
+const SEED: u64 = 0x5707_1d57_a75;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    // The fraction of functions to analyse, in (0, 1].
+    fraction: f64,
+}
+
+impl Sample {
+    // Everything, i.e., no sampling.
+    pub fn all() -> Sample {
+        Sample { fraction: 1.0 }
+    }
+
+    // Parses a percentage, e.g., `10%` (the `%` is optional).
+    pub fn parse(s: &str) -> Option<Sample> {
+        let percent: f64 = s.trim().trim_end_matches('%').parse().ok()?;
+        if percent > 0.0 && percent <= 100.0 {
+            Some(Sample { fraction: percent / 100.0 })
+        } else {
+            None
+        }
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.fraction >= 1.0
+    }
+
+    pub fn percent(&self) -> f64 {
+        100.0 * self.fraction
+    }
+
+    // Whether the function at `path` is in the sample.
+    pub fn includes(&self, path: &str) -> bool {
+        if self.is_all() {
+            return true;
+        }
+        let mut bytes = SEED.to_le_bytes().to_vec();
+        bytes.extend_from_slice(path.as_bytes());
+        (fnv1a(&bytes) as f64 / u64::MAX as f64) < self.fraction
+    }
+}
+
+// Estimates how many of `population` functions have some property given that
+// `hits` of the `sampled` functions in the sample do. Returns the estimate and
+// the margin of its 95% confidence interval.
+pub fn estimate_count(hits: usize, sampled: usize, population: usize) -> (f64, f64) {
+    if sampled == 0 {
+        return (0.0, population as f64);
+    }
+    let (n, total) = (sampled as f64, population as f64);
+    let p = hits as f64 / n;
+    // We sample without replacement, so the more of the crate we sample the
+    // less uncertain we are.
+    let correction = if population > 1 {
+        (total - n) / (total - 1.0)
+    } else {
+        0.0
+    };
+    let margin = 1.96 * (p * (1.0 - p) / n * correction).sqrt() * total;
+    (p * total, margin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn everything_sampled() {
+        assert_eq!(estimate_count(3, 10, 10), (3.0, 0.0));
+    }
+
+    #[test]
+    fn estimate_scales_up() {
+        let (estimate, margin) = estimate_count(5, 10, 100);
+        assert!((estimate - 50.0).abs() < 1e-9);
+        // 1.96 * sqrt(0.25 / 10 * 90 / 99) * 100
+        assert!((margin - 29.55).abs() < 0.01);
+    }
+
+    #[test]
+    fn nothing_sampled() {
+        assert_eq!(estimate_count(0, 0, 20), (0.0, 20.0));
+    }
+
+    #[test]
+    fn parse_percentages() {
+        assert_eq!(Sample::parse("10%").map(|s| s.percent()), Some(10.0));
+        assert_eq!(Sample::parse("100").map(|s| s.percent()), Some(100.0));
+        assert!(Sample::parse("0%").is_none());
+        assert!(Sample::parse("150%").is_none());
+    }
+
+    #[test]
+    fn stable_sample() {
+        let sample = Sample::parse("50%").unwrap();
+        let picked: Vec<_> = (0..100).map(|i| sample.includes(&format!("f{}", i))).collect();
+        let again: Vec<_> = (0..100).map(|i| sample.includes(&format!("f{}", i))).collect();
+        assert_eq!(picked, again);
+        let count = picked.iter().filter(|&&p| p).count();
+        assert!(count > 20 && count < 80);
+        assert!(Sample::all().includes("anything"));
+    }
+}
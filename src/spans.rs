@@ -0,0 +1,20 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Helpers for turning spans into numbers we can report.
+
+use rustc_span::Span;
+use rustc_span::source_map::SourceMap;
+
+// The number of source lines covered by `span`, including partial lines at
+// either end.
+pub fn line_count(source_map: &SourceMap, span: Span) -> usize {
+    let lo = source_map.lookup_char_pos(span.lo()).line;
+    let hi = source_map.lookup_char_pos(span.hi()).line;
+    hi - lo + 1
+}
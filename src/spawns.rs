@@ -0,0 +1,148 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Closures and async blocks passed to `thread::spawn`, `tokio::spawn`, and the
+// like, for reviewing concurrent code. We flag those which don't `move` their
+// captures (so capture by reference) and those which use a local whose name
+// suggests it is big (e.g., `buffer` or `cache`), since moving or sharing that
+// into another task may not be what was meant. We can't see types or real
+// captures, so both are guesses from the syntax.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+
+use counter::Counter;
+use findings::{Finding, Findings, Severity};
+use metrics::{Metric, ReportContext};
+
+const SPAWN_PATHS: &[&str] = &[
+    "thread::spawn",
+    "std::thread::spawn",
+    "tokio::spawn",
+    "tokio::task::spawn",
+    "tokio::task::spawn_blocking",
+    "task::spawn",
+    "task::spawn_blocking",
+    "async_std::task::spawn",
+];
+
+// Parts of names which suggest a value is large.
+const LARGE_NAMES: &[&str] = &["buf", "data", "bytes", "vec", "map", "cache", "table", "image"];
+
+// Collects the single-segment paths used in a closure body, i.e., the locals
+// it might capture.
+struct LocalsCollector {
+    names: BTreeSet<String>,
+}
+
+impl<'a> visit::Visitor<'a> for LocalsCollector {
+    fn visit_expr(&mut self, e: &'a ast::Expr) {
+        if let ast::ExprKind::Path(None, ref path) = e.kind {
+            if path.segments.len() == 1 {
+                self.names.insert(path.segments[0].ident.to_string());
+            }
+        }
+        visit::walk_expr(self, e)
+    }
+}
+
+pub struct SpawnsMetric {
+    spawns: Counter,
+    // Flagged spawns and why.
+    flagged: Vec<(Span, String)>,
+}
+
+impl SpawnsMetric {
+    pub fn new() -> SpawnsMetric {
+        SpawnsMetric {
+            spawns: Counter::new(),
+            flagged: vec![],
+        }
+    }
+
+    fn check_spawned(&mut self, arg: &ast::Expr) {
+        let mut collector = LocalsCollector {
+            names: BTreeSet::new(),
+        };
+        let capture = match arg.kind {
+            ast::ExprKind::Closure(capture, _, _, _, ref body, _) => {
+                visit::Visitor::visit_expr(&mut collector, body);
+                capture
+            }
+            ast::ExprKind::Async(capture, _, ref block) => {
+                visit::Visitor::visit_block(&mut collector, block);
+                capture
+            }
+            _ => return,
+        };
+
+        if let ast::CaptureBy::Ref = capture {
+            self.flagged.push((arg.span, "captures by reference (no `move`)".to_owned()));
+        }
+
+        let large: Vec<_> = collector
+            .names
+            .iter()
+            .filter(|n| {
+                let n = n.to_lowercase();
+                LARGE_NAMES.iter().any(|l| n.contains(l))
+            })
+            .map(|n| format!("`{}`", n))
+            .collect();
+        if !large.is_empty() {
+            self.flagged.push((arg.span, format!("uses large-looking locals {}", large.join(", "))));
+        }
+    }
+}
+
+impl Metric for SpawnsMetric {
+    fn id(&self) -> &'static str {
+        "spawns"
+    }
+
+    fn visit_expr(&mut self, e: &ast::Expr) {
+        if let ast::ExprKind::Call(ref f, ref args) = e.kind {
+            if let ast::ExprKind::Path(None, ref path) = f.kind {
+                let path = pprust::path_to_string(path);
+                if SPAWN_PATHS.contains(&path.trim_start_matches("::")) && args.len() == 1 {
+                    self.spawns.increment();
+                    self.check_spawned(&args[0]);
+                }
+            }
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "Found {} spawned closures or async blocks, {} of which need a look;",
+            self.spawns,
+            self.flagged.iter().map(|f| f.0).collect::<HashSet<_>>().len()
+        )
+    }
+
+    fn findings(&self, findings: &mut Findings) {
+        for &(span, ref why) in &self.flagged {
+            findings.push(Finding {
+                id: "spawned-capture",
+                severity: Severity::Note,
+                span,
+                message: format!("spawned task {}", why),
+                value: None,
+            });
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(SpawnsMetric::new())
+    }
+}
@@ -0,0 +1,155 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Stats for each team, for repos with a CODEOWNERS file. We break the crate
+// down by source file and add each file's numbers to all of its owners.
+//
+// This walks the crate after expansion, since that is when out-of-line modules
+// have been loaded. Code from macro expansions is skipped.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::path::PathBuf;
+
+use rustc_ast::{ast, visit};
+use rustc_span::{FileName, Span};
+use rustc_span::source_map::SourceMap;
+
+use cognitive::cognitive_complexity;
+use counter::Counter;
+use metrics::{Metric, ReportContext, Stage};
+use owners::Owners;
+
+#[derive(Default)]
+struct FileStats {
+    lines: Counter,
+    items: Counter,
+    functions: Counter,
+    complexity: Counter,
+}
+
+pub struct TeamsMetric {
+    // What we've walked, as spans, and the complexity of each function; we
+    // only find the files they're in once we have the source map.
+    items: Vec<Span>,
+    functions: Vec<(Span, usize)>,
+    files: BTreeMap<PathBuf, FileStats>,
+}
+
+impl TeamsMetric {
+    pub fn new() -> TeamsMetric {
+        TeamsMetric {
+            items: vec![],
+            functions: vec![],
+            files: BTreeMap::new(),
+        }
+    }
+}
+
+// The stats for the file `span` is in, if it is a real file.
+fn file<'f>(
+    files: &'f mut BTreeMap<PathBuf, FileStats>,
+    source_map: &SourceMap,
+    span: Span,
+) -> Option<&'f mut FileStats> {
+    if span.from_expansion() {
+        return None;
+    }
+    let file = source_map.lookup_char_pos(span.lo()).file;
+    let path = match file.name {
+        FileName::Real(ref name) => name.local_path().to_owned(),
+        _ => return None,
+    };
+    Some(files.entry(path).or_insert_with(|| FileStats {
+        lines: Counter::from(file.count_lines()),
+        ..FileStats::default()
+    }))
+}
+
+impl Metric for TeamsMetric {
+    fn id(&self) -> &'static str {
+        "teams"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Expansion, Stage::Source]
+    }
+
+    // Teams own files, not modules.
+    fn per_module(&self) -> bool {
+        false
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        self.items.push(i.span);
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, s: Span) {
+        if let visit::FnKind::Fn(_, ident, _, _, Some(body)) = fk {
+            self.functions.push((s, cognitive_complexity(ident, body)));
+        }
+    }
+
+    fn check_source(&mut self, source_map: &SourceMap) {
+        for &span in &self.items {
+            if let Some(file) = file(&mut self.files, source_map, span) {
+                file.items.increment();
+            }
+        }
+        for &(span, complexity) in &self.functions {
+            if let Some(file) = file(&mut self.files, source_map, span) {
+                file.functions.increment();
+                file.complexity.add(complexity);
+            }
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let owners = match Owners::find() {
+            Some(owners) => owners,
+            None => return Ok(()),
+        };
+
+        // Files with no owner are reported as owned by nobody.
+        let nobody = ["(no owner)".to_owned()];
+        let mut teams: BTreeMap<&str, (Counter, FileStats)> = BTreeMap::new();
+        for (path, stats) in &self.files {
+            let mut file_owners = owners.owners_of(path);
+            if file_owners.is_empty() {
+                file_owners = &nobody;
+            }
+            for owner in file_owners {
+                let team = teams.entry(owner).or_default();
+                team.0.increment();
+                team.1.lines.add(stats.lines.get());
+                team.1.items.add(stats.items.get());
+                team.1.functions.add(stats.functions.get());
+                team.1.complexity.add(stats.complexity.get());
+            }
+        }
+
+        writeln!(out, "Stats by owner (files, lines, items, functions, complexity):")?;
+        for (owner, &(files, ref stats)) in &teams {
+            writeln!(
+                out,
+                "    {}: {}, {}, {}, {}, {}",
+                owner,
+                files,
+                stats.lines,
+                stats.items,
+                stats.functions,
+                stats.complexity
+            )?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(TeamsMetric::new())
+    }
+}
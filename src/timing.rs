@@ -0,0 +1,140 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Where the time goes, roughly. We time walking each module's own items (not
+// those of its child modules) and scoring its functions, as a proxy for how
+// much code, and how complicated code, each module has; the modules we are
+// slow on are probably where rustc is slow too. For rustc's own timings, build
+// with `-Ztime-passes`.
+//
+// Timings are different on every run, so they are only reported if
+// `STUPID_STATS_TIMING` is set.
+
+use std::fmt::{self, Write};
+use std::time::{Duration, Instant};
+
+use rustc_ast::{ast, visit};
+use rustc_ast::ptr::P;
+use rustc_span::Span;
+
+use cognitive::cognitive_complexity;
+use metrics::{Metric, ReportContext, Stage};
+
+// The number of modules to list in the report.
+const TOP_MODULES: usize = 5;
+
+// Does the work we time: walks everything except nested modules and scores
+// each function.
+struct Walker;
+
+impl<'a> visit::Visitor<'a> for Walker {
+    fn visit_item(&mut self, i: &'a ast::Item) {
+        if let ast::ItemKind::Mod(..) = i.kind {
+            return;
+        }
+        visit::walk_item(self, i)
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind<'a>, s: Span, _: ast::NodeId) {
+        if let visit::FnKind::Fn(_, ident, _, _, Some(body)) = fk {
+            cognitive_complexity(ident, body);
+        }
+        visit::walk_fn(self, fk, s)
+    }
+}
+
+pub struct TimingMetric {
+    enabled: bool,
+    // The path of the module we're in, e.g., `["crate", "foo", "bar"]`.
+    path: Vec<String>,
+    modules: Vec<(String, Duration)>,
+}
+
+impl TimingMetric {
+    pub fn new(enabled: bool) -> TimingMetric {
+        TimingMetric {
+            enabled,
+            path: vec!["crate".to_owned()],
+            modules: vec![],
+        }
+    }
+
+    fn time_module(&mut self, items: &[P<ast::Item>]) {
+        let start = Instant::now();
+        for item in items {
+            visit::Visitor::visit_item(&mut Walker, item);
+        }
+        self.modules.push((self.path.join("::"), start.elapsed()));
+    }
+}
+
+fn millis(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+impl Metric for TimingMetric {
+    fn id(&self) -> &'static str {
+        "timing"
+    }
+
+    // The path is for the whole crate, so we can't be split by module.
+    fn per_module(&self) -> bool {
+        false
+    }
+
+    // We only time the whole crate.
+    fn whole_crate(&self) -> bool {
+        true
+    }
+
+    fn check_crate(&mut self, krate: &ast::Crate, _: Stage) {
+        if self.enabled {
+            self.time_module(&krate.items);
+        }
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        if let ast::ItemKind::Mod(_, ast::ModKind::Loaded(ref items, ..)) = i.kind {
+            self.path.push(i.ident.to_string());
+            if self.enabled {
+                self.time_module(items);
+            }
+        }
+    }
+
+    fn exit_item(&mut self, i: &ast::Item) {
+        if let ast::ItemKind::Mod(..) = i.kind {
+            self.path.pop();
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        if !self.enabled {
+            return Ok(());
+        }
+
+        let total: Duration = self.modules.iter().map(|m| m.1).sum();
+        writeln!(out, "Analysing modules took {:.2}ms; the slowest were:", millis(total))?;
+
+        let mut modules: Vec<_> = self.modules.iter().collect();
+        modules.sort_by(|a, b| b.1.cmp(&a.1));
+        for &&(ref name, time) in modules.iter().take(TOP_MODULES) {
+            let percent = if total == Duration::default() {
+                0.0
+            } else {
+                100.0 * time.as_secs_f64() / total.as_secs_f64()
+            };
+            writeln!(out, "    `{}`: {:.2}ms ({:.0}%)", name, millis(time), percent)?;
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(TimingMetric::new(self.enabled))
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Call sites of APIs the user asked us to track (e.g., `regex::Regex::new`).
+//
+// We ask type checking what each call resolved to, so imports, renames, and
+// method calls (e.g., `re.is_match(s)` for `regex::Regex::is_match`) are all
+// found, and compare the full path of what was called with the tracked paths.
+// Macros are gone by then, so we find their calls from where the expanded
+// code came from.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Write};
+
+use rustc_hir as hir;
+use rustc_hir::def::Res;
+use rustc_hir::def_id::DefId;
+use rustc_hir::intravisit::{self, NestedVisitorMap, Visitor};
+use rustc_middle::hir::map::Map;
+use rustc_middle::ty::print::with_no_trimmed_paths;
+use rustc_middle::ty::{TyCtxt, TypeckResults};
+use rustc_span::Span;
+use rustc_span::hygiene::{ExpnKind, MacroKind};
+
+use crate_stats::CrateStats;
+use metrics::{AnalysisContext, Metric, ReportContext, Stage};
+
+pub struct TrackedMetric {
+    tracked: Vec<String>,
+    // The call sites of each tracked path which is called at all.
+    sites: HashMap<String, Vec<Span>>,
+}
+
+impl TrackedMetric {
+    pub fn new(tracked: &[String]) -> TrackedMetric {
+        TrackedMetric {
+            tracked: tracked.to_owned(),
+            sites: HashMap::new(),
+        }
+    }
+}
+
+impl Metric for TrackedMetric {
+    fn id(&self) -> &'static str {
+        "tracked-calls"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Analysis]
+    }
+
+    fn check_analysis<'tcx>(&mut self, cx: &AnalysisContext<'tcx>) {
+        if self.tracked.is_empty() {
+            return;
+        }
+        let mut finder = Finder {
+            tcx: cx.tcx,
+            results: None,
+            tracked: &self.tracked,
+            sites: &mut self.sites,
+            macros: HashSet::new(),
+        };
+        cx.visit_items(&mut finder);
+        for spans in self.sites.values_mut() {
+            spans.sort();
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        if self.tracked.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(out, "Uses of tracked APIs:")?;
+        for tracked in &self.tracked {
+            let spans = self.sites.get(tracked).map(|s| &s[..]).unwrap_or(&[]);
+            writeln!(out, "    `{}`: {}", tracked, spans.len())?;
+            for &span in spans {
+                writeln!(out, "        {}", cx.source_map.span_to_string(span))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        for tracked in &self.tracked {
+            let count = self.sites.get(tracked).map_or(0, |s| s.len());
+            stats.set_count(&format!("tracked-calls.{}", tracked), count);
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(TrackedMetric::new(&self.tracked))
+    }
+}
+
+struct Finder<'a, 'tcx> {
+    tcx: TyCtxt<'tcx>,
+    // The type checking results for the body we're in.
+    results: Option<&'tcx TypeckResults<'tcx>>,
+    tracked: &'a [String],
+    sites: &'a mut HashMap<String, Vec<Span>>,
+    // The call sites of the macros we've counted; everything a macro expands
+    // to comes from the same one.
+    macros: HashSet<Span>,
+}
+
+impl<'a, 'tcx> Finder<'a, 'tcx> {
+    fn record(&mut self, def_id: DefId, span: Span) {
+        // The full path, not the shortest one which is unambiguous.
+        let path = with_no_trimmed_paths(|| self.tcx.def_path_str(def_id));
+        if self.tracked.contains(&path) {
+            self.sites.entry(path).or_default().push(span);
+        }
+    }
+
+    fn check_macro(&mut self, span: Span) {
+        let expn = span.ctxt().outer_expn_data();
+        if let ExpnKind::Macro(MacroKind::Bang, _) = expn.kind {
+            if let Some(def_id) = expn.macro_def_id {
+                if self.macros.insert(expn.call_site) {
+                    self.record(def_id, expn.call_site);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, 'tcx> Visitor<'tcx> for Finder<'a, 'tcx> {
+    type Map = Map<'tcx>;
+
+    fn nested_visit_map(&mut self) -> NestedVisitorMap<Self::Map> {
+        NestedVisitorMap::OnlyBodies(self.tcx.hir())
+    }
+
+    fn visit_nested_body(&mut self, id: hir::BodyId) {
+        let old = self.results.replace(self.tcx.typeck_body(id));
+        let body = self.tcx.hir().body(id);
+        self.visit_body(body);
+        self.results = old;
+    }
+
+    fn visit_expr(&mut self, e: &'tcx hir::Expr<'tcx>) {
+        self.check_macro(e.span);
+        if let Some(results) = self.results {
+            match e.kind {
+                hir::ExprKind::Call(ref f, _) => {
+                    if let hir::ExprKind::Path(ref qpath) = f.kind {
+                        if let Res::Def(_, def_id) = results.qpath_res(qpath, f.hir_id) {
+                            self.record(def_id, e.span);
+                        }
+                    }
+                }
+                hir::ExprKind::MethodCall(..) => {
+                    if let Some(def_id) = results.type_dependent_def_id(e.hir_id) {
+                        self.record(def_id, e.span);
+                    }
+                }
+                _ => {}
+            }
+        }
+        intravisit::walk_expr(self, e)
+    }
+}
@@ -0,0 +1,122 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Escape-hatch bounds in trait definitions: methods with `where Self: Sized`
+// (which keeps a trait object safe), `?Sized` bounds (more flexible), and
+// `'static` bounds (less flexible).
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit, FnKind};
+use rustc_span::symbol::{kw, sym};
+
+use counter::Counter;
+use metrics::{Metric, ReportContext};
+
+pub struct TraitBoundsMetric {
+    // How many trait definitions we are in.
+    in_trait: usize,
+    trait_methods: Counter,
+    self_sized: Counter,
+    maybe_sized: Counter,
+    statics: Counter,
+}
+
+impl TraitBoundsMetric {
+    pub fn new() -> TraitBoundsMetric {
+        TraitBoundsMetric {
+            in_trait: 0,
+            trait_methods: Counter::new(),
+            self_sized: Counter::new(),
+            maybe_sized: Counter::new(),
+            statics: Counter::new(),
+        }
+    }
+}
+
+// Whether a where clause has `Self: Sized`.
+fn has_self_sized(generics: &ast::Generics) -> bool {
+    generics.where_clause.predicates.iter().any(|p| match *p {
+        ast::WherePredicate::BoundPredicate(ref p) => {
+            let is_self = match p.bounded_ty.kind {
+                ast::TyKind::Path(None, ref path) => *path == kw::SelfUpper,
+                _ => false,
+            };
+            is_self &&
+                p.bounds.iter().any(|b| match *b {
+                    ast::GenericBound::Trait(ref poly, ast::TraitBoundModifier::None) => {
+                        poly.trait_ref.path == sym::Sized
+                    }
+                    _ => false,
+                })
+        }
+        _ => false,
+    })
+}
+
+impl Metric for TraitBoundsMetric {
+    fn id(&self) -> &'static str {
+        "trait-bounds"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        if let ast::ItemKind::Trait(..) = i.kind {
+            self.in_trait += 1;
+        }
+    }
+
+    fn exit_item(&mut self, i: &ast::Item) {
+        if let ast::ItemKind::Trait(..) = i.kind {
+            self.in_trait -= 1;
+        }
+    }
+
+    fn visit_assoc_item(&mut self, i: &ast::AssocItem, ctxt: visit::AssocCtxt) {
+        if let visit::AssocCtxt::Trait = ctxt {
+            if let ast::AssocItemKind::Fn(box FnKind(_, _, ref generics, _)) = i.kind {
+                self.trait_methods.increment();
+                if has_self_sized(generics) {
+                    self.self_sized.increment();
+                }
+            }
+        }
+    }
+
+    fn visit_param_bound(&mut self, bound: &ast::GenericBound) {
+        if self.in_trait > 0 {
+            match *bound {
+                ast::GenericBound::Trait(_, ast::TraitBoundModifier::Maybe) => {
+                    self.maybe_sized.increment()
+                }
+                ast::GenericBound::Outlives(ref lifetime)
+                    if lifetime.ident.name == kw::StaticLifetime =>
+                {
+                    self.statics.increment()
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        writeln!(
+            out,
+            "{} of {} trait methods have `where Self: Sized`;",
+            self.self_sized, self.trait_methods
+        )?;
+        writeln!(
+            out,
+            "Trait definitions have {} `?Sized` bounds and {} `'static` bounds;",
+            self.maybe_sized, self.statics
+        )
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(TraitBoundsMetric::new())
+    }
+}
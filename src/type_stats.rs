@@ -0,0 +1,146 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Stats which come from the type checker rather than the syntax: how many
+// functions are generic (including methods in generic impls, which the syntax
+// of the method doesn't show), how many impls there are of each trait, and the
+// crate's biggest types by size in memory. Generic types don't have a size
+// until they are instantiated, so they aren't in the list.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+
+use rustc_hir as hir;
+use rustc_hir::def::DefKind;
+use rustc_hir::def_id::LOCAL_CRATE;
+use rustc_middle::ty::ParamEnv;
+
+use counter::Counter;
+use metrics::{AnalysisContext, Metric, ReportContext, Stage};
+
+// How many traits and types to list.
+const TOP_TRAITS: usize = 10;
+const TOP_TYPES: usize = 10;
+
+pub struct TypeStats {
+    monomorphic_fns: Counter,
+    generic_fns: Counter,
+    // The number of impls in this crate of each trait, by the trait's path.
+    trait_impls: BTreeMap<String, Counter>,
+    // Types and their sizes in bytes, biggest first.
+    sizes: Vec<(String, u64)>,
+}
+
+impl TypeStats {
+    pub fn new() -> TypeStats {
+        TypeStats {
+            monomorphic_fns: Counter::new(),
+            generic_fns: Counter::new(),
+            trait_impls: BTreeMap::new(),
+            sizes: vec![],
+        }
+    }
+
+    // Functions and methods with bodies; closures belong to their function.
+    fn count_fns(&mut self, cx: &AnalysisContext) {
+        let tcx = cx.tcx;
+        for def_id in tcx.hir().body_owners().filter(|&def_id| cx.includes(def_id)) {
+            let def_id = def_id.to_def_id();
+            match tcx.def_kind(def_id) {
+                DefKind::Fn | DefKind::AssocFn => {}
+                _ => continue,
+            }
+            if tcx.generics_of(def_id).requires_monomorphization(tcx) {
+                self.generic_fns.increment();
+            } else {
+                self.monomorphic_fns.increment();
+            }
+        }
+    }
+
+    fn find_sizes(&mut self, cx: &AnalysisContext) {
+        let tcx = cx.tcx;
+        for item in tcx.hir().krate().items.values() {
+            match item.kind {
+                hir::ItemKind::Struct(..) | hir::ItemKind::Enum(..) | hir::ItemKind::Union(..) => {}
+                _ => continue,
+            }
+            let def_id = tcx.hir().local_def_id(item.hir_id);
+            if !cx.includes(def_id) {
+                continue;
+            }
+            let def_id = def_id.to_def_id();
+            if tcx.generics_of(def_id).requires_monomorphization(tcx) {
+                continue;
+            }
+            // Types which are never constructed can still fail to lay out,
+            // e.g., if they are infinitely big.
+            let ty = tcx.type_of(def_id);
+            if let Ok(layout) = tcx.layout_of(ParamEnv::reveal_all().and(ty)) {
+                self.sizes.push((tcx.def_path_str(def_id), layout.size.bytes()));
+            }
+        }
+        self.sizes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    }
+}
+
+impl Metric for TypeStats {
+    fn id(&self) -> &'static str {
+        "types"
+    }
+
+    fn stages(&self) -> &'static [Stage] {
+        &[Stage::Analysis]
+    }
+
+    fn check_analysis<'tcx>(&mut self, cx: &AnalysisContext<'tcx>) {
+        let tcx = cx.tcx;
+        self.count_fns(cx);
+        for (&trait_def_id, impls) in tcx.all_local_trait_impls(LOCAL_CRATE) {
+            let count = impls
+                .iter()
+                .filter(|&&hir_id| cx.includes(tcx.hir().local_def_id(hir_id)))
+                .count();
+            if count > 0 {
+                self.trait_impls.insert(tcx.def_path_str(trait_def_id), Counter::from(count));
+            }
+        }
+        self.find_sizes(cx);
+    }
+
+    fn report(&self, _: &ReportContext, out: &mut String) -> fmt::Result {
+        let total = Counter::sum(&[self.monomorphic_fns, self.generic_fns]).get();
+        let generic = self.generic_fns.get();
+        let percent = if total == 0 { 0.0 } else { 100.0 * generic as f64 / total as f64 };
+        writeln!(
+            out,
+            "{} functions are monomorphic and {} ({:.0}%) are generic, counting their impls' parameters;",
+            self.monomorphic_fns, self.generic_fns, percent
+        )?;
+
+        let impls = Counter::sum(self.trait_impls.values());
+        writeln!(out, "The crate has {} trait impls, of {} traits;", impls, self.trait_impls.len())?;
+        let mut traits: Vec<_> = self.trait_impls.iter().collect();
+        traits.sort_by(|a, b| b.1.get().cmp(&a.1.get()));
+        for (name, count) in traits.into_iter().take(TOP_TRAITS) {
+            writeln!(out, "    `{}`: {}", name, count)?;
+        }
+
+        if !self.sizes.is_empty() {
+            writeln!(out, "The biggest types are:")?;
+            for &(ref name, size) in self.sizes.iter().take(TOP_TYPES) {
+                writeln!(out, "    `{}`: {} bytes", name, size)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(TypeStats::new())
+    }
+}
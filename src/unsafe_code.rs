@@ -0,0 +1,260 @@
+// Copyright 2015 Nicholas Cameron.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+// Where a crate uses `unsafe`: unsafe functions, blocks, impls, and traits,
+// and how many functions contain any unsafe code at all (an `unsafe fn`, or a
+// function with an `unsafe` block in its body, not counting closures, which
+// belong to the function they're in). Every unsafe block is listed, since
+// those are what a reviewer has to check, and so is every use of inline
+// assembly (`asm!`, `llvm_asm!`, and `global_asm!`), with the function it's
+// in. `unsafe impl Send` and `unsafe impl Sync` come first, with the types
+// they're for: they promise the compiler things it can't check about every
+// use of the type, so they're the riskiest unsafe code in most crates. With
+// `STUPID_STATS_MAX_UNSAFE_BLOCKS`, if there are more blocks than that, each
+// is a finding.
+
+use std::fmt::{self, Write};
+
+use rustc_ast::{ast, visit};
+use rustc_ast_pretty::pprust;
+use rustc_span::Span;
+
+use counter::Counter;
+use crate_stats::CrateStats;
+use findings::{Finding, Findings, Severity};
+use metrics::{Metric, ReportContext};
+
+// The macros for inline assembly, by the last segment of their path.
+const ASM_MACROS: &[&str] = &["asm", "llvm_asm", "global_asm"];
+
+// A use of inline assembly.
+struct Asm {
+    macro_name: String,
+    // The function it's in, and whether that's an `unsafe fn`; `None` for
+    // `global_asm!`, which is an item.
+    function: Option<(String, bool)>,
+    span: Span,
+}
+
+pub struct UnsafeMetric {
+    unsafe_fns: Counter,
+    unsafe_impls: Counter,
+    // `unsafe impl Send` and `Sync`: the trait, the type, and where.
+    send_sync_impls: Vec<(String, String, Span)>,
+    unsafe_traits: Counter,
+    unsafe_blocks: Vec<Span>,
+    functions: Counter,
+    functions_with_unsafe: Counter,
+    // For each function we're in, its name, whether it's an `unsafe fn`, and
+    // whether we've seen an unsafe block in it.
+    fn_stack: Vec<(String, bool, bool)>,
+    asm: Vec<Asm>,
+    max_blocks: Option<usize>,
+}
+
+impl UnsafeMetric {
+    pub fn new(max_blocks: Option<usize>) -> UnsafeMetric {
+        UnsafeMetric {
+            unsafe_fns: Counter::new(),
+            unsafe_impls: Counter::new(),
+            send_sync_impls: vec![],
+            unsafe_traits: Counter::new(),
+            unsafe_blocks: vec![],
+            functions: Counter::new(),
+            functions_with_unsafe: Counter::new(),
+            fn_stack: vec![],
+            asm: vec![],
+            max_blocks,
+        }
+    }
+}
+
+fn is_unsafe(unsafety: ast::Unsafe) -> bool {
+    match unsafety {
+        ast::Unsafe::Yes(_) => true,
+        ast::Unsafe::No => false,
+    }
+}
+
+impl Metric for UnsafeMetric {
+    fn id(&self) -> &'static str {
+        "unsafe"
+    }
+
+    fn visit_item(&mut self, i: &ast::Item) {
+        match i.kind {
+            ast::ItemKind::Impl(box ast::ImplKind {
+                unsafety,
+                polarity: ast::ImplPolarity::Positive,
+                of_trait: Some(ref trait_ref),
+                ref self_ty,
+                ..
+            }) if is_unsafe(unsafety) => {
+                self.unsafe_impls.increment();
+                let trait_name = trait_ref.path.segments.last().map(|s| s.ident.to_string());
+                if let Some(trait_name) = trait_name.filter(|t| t == "Send" || t == "Sync") {
+                    self.send_sync_impls.push((trait_name, pprust::ty_to_string(self_ty), i.span));
+                }
+            }
+            ast::ItemKind::Impl(box ast::ImplKind { unsafety, .. }) if is_unsafe(unsafety) => {
+                self.unsafe_impls.increment();
+            }
+            ast::ItemKind::Trait(box ast::TraitKind(_, unsafety, ..)) if is_unsafe(unsafety) => {
+                self.unsafe_traits.increment();
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        match fk {
+            visit::FnKind::Fn(_, ident, sig, _, Some(_)) => {
+                let unsafe_fn = is_unsafe(sig.header.unsafety);
+                if unsafe_fn {
+                    self.unsafe_fns.increment();
+                }
+                self.functions.increment();
+                self.fn_stack.push((ident.to_string(), unsafe_fn, false));
+            }
+            // Unsafe functions without a body are declarations in traits or
+            // `extern` blocks.
+            visit::FnKind::Fn(_, _, sig, _, None) => {
+                if is_unsafe(sig.header.unsafety) {
+                    self.unsafe_fns.increment();
+                }
+            }
+            visit::FnKind::Closure(..) => {}
+        }
+    }
+
+    fn exit_fn(&mut self, fk: visit::FnKind, _: Span) {
+        if let visit::FnKind::Fn(_, _, _, _, Some(_)) = fk {
+            let (_, unsafe_fn, has_unsafe_block) = self.fn_stack.pop().unwrap();
+            if unsafe_fn || has_unsafe_block {
+                self.functions_with_unsafe.increment();
+            }
+        }
+    }
+
+    fn visit_block(&mut self, b: &ast::Block) {
+        if let ast::BlockCheckMode::Unsafe(ast::UnsafeSource::UserProvided) = b.rules {
+            self.unsafe_blocks.push(b.span);
+            if let Some(&mut (_, _, ref mut has_unsafe_block)) = self.fn_stack.last_mut() {
+                *has_unsafe_block = true;
+            }
+        }
+    }
+
+    fn visit_mac_call(&mut self, mac: &ast::MacCall) {
+        if let Some(segment) = mac.path.segments.last() {
+            let name = segment.ident.as_str();
+            if ASM_MACROS.contains(&&*name) {
+                self.asm.push(Asm {
+                    macro_name: name.to_string(),
+                    function: self
+                        .fn_stack
+                        .last()
+                        .map(|&(ref name, unsafe_fn, _)| (name.clone(), unsafe_fn)),
+                    span: mac.span(),
+                });
+            }
+        }
+    }
+
+    fn report(&self, cx: &ReportContext, out: &mut String) -> fmt::Result {
+        if !self.send_sync_impls.is_empty() {
+            writeln!(
+                out,
+                "Found {} `unsafe impl`s of `Send` or `Sync`, check these first:",
+                self.send_sync_impls.len()
+            )?;
+            for &(ref trait_name, ref self_ty, span) in &self.send_sync_impls {
+                writeln!(
+                    out,
+                    "    `unsafe impl {} for {}` at {}",
+                    trait_name,
+                    self_ty,
+                    cx.source_map.span_to_string(span)
+                )?;
+            }
+        }
+        writeln!(
+            out,
+            "Found {} `unsafe fn`s, {} `unsafe` blocks, {} `unsafe impl`s, and {} `unsafe trait`s;",
+            self.unsafe_fns,
+            self.unsafe_blocks.len(),
+            self.unsafe_impls,
+            self.unsafe_traits
+        )?;
+        let percent = if self.functions.get() == 0 {
+            0.0
+        } else {
+            100.0 * self.functions_with_unsafe.get() as f64 / self.functions.get() as f64
+        };
+        writeln!(
+            out,
+            "{} functions ({:.1}%) contain unsafe code;",
+            self.functions_with_unsafe, percent
+        )?;
+        if !self.unsafe_blocks.is_empty() {
+            writeln!(out, "Unsafe blocks at:")?;
+            for &span in &self.unsafe_blocks {
+                writeln!(out, "    {}", cx.source_map.span_to_string(span))?;
+            }
+        }
+        if !self.asm.is_empty() {
+            writeln!(out, "Found {} uses of inline assembly:", self.asm.len())?;
+            for asm in &self.asm {
+                let place = match asm.function {
+                    Some((ref name, true)) => format!("in `unsafe fn {}`", name),
+                    Some((ref name, false)) => format!("in `{}`", name),
+                    None => "at the top level".to_owned(),
+                };
+                writeln!(
+                    out,
+                    "    `{}!` {} at {}",
+                    asm.macro_name,
+                    place,
+                    cx.source_map.span_to_string(asm.span)
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn findings(&self, findings: &mut Findings) {
+        let max = match self.max_blocks {
+            Some(max) if self.unsafe_blocks.len() > max => max,
+            _ => return,
+        };
+        for &span in &self.unsafe_blocks {
+            findings.push(Finding {
+                id: "unsafe-block",
+                severity: Severity::Warning,
+                span,
+                message: format!(
+                    "`unsafe` block (the crate has {}, over {})",
+                    self.unsafe_blocks.len(),
+                    max
+                ),
+                value: None,
+            });
+        }
+    }
+
+    fn fresh(&self) -> Box<dyn Metric> {
+        Box::new(UnsafeMetric::new(self.max_blocks))
+    }
+
+    fn crate_stats(&self, stats: &mut CrateStats) {
+        stats.set_count("unsafe.fns", self.unsafe_fns);
+        stats.set_count("unsafe.blocks", self.unsafe_blocks.len());
+        stats.set_count("unsafe.impls", self.unsafe_impls);
+        stats.set_count("unsafe.functions_with_unsafe", self.functions_with_unsafe);
+    }
+}